@@ -0,0 +1,120 @@
+require("lib/core")
+
+-- Types
+assert_eq("nil", type(z))
+assert_eq("number", type(1))
+assert_eq("number", type(1.5))
+assert_eq("string", type("x"))
+assert_eq("boolean", type(false))
+assert_eq("table", type({}))
+assert_eq("function", type(print))
+assert_eq("thread", type(coroutine.create(print)))
+
+-- Converting to strings
+assert_eq("10", tostring(10))
+assert_eq("1.5", tostring(1.5))
+assert_eq("nil", tostring(z))
+assert_eq("true", tostring(true))
+function point_name()
+    return "point"
+end
+local point = setmetatable({}, {__tostring = point_name})
+assert_eq("point", tostring(point))
+local named = setmetatable({}, {__name = "Named"})
+assert_eq("Named: ", string.sub(tostring(named), 1, 7))
+
+-- Converting to numbers
+assert_eq(10, tonumber("10"))
+assert_eq(16, tonumber("0x10"))
+assert_eq(1.5, tonumber(" 1.5 "))
+assert_eq(true, tonumber("abc") == z)
+assert_eq(true, tonumber({}) == z)
+assert_eq(255, tonumber("ff", 16))
+assert_eq(255, tonumber("FF", 16))
+assert_eq(-5, tonumber("-101", 2))
+assert_eq(35, tonumber(" z ", 36))
+assert_eq(7, tonumber("7", 10))
+assert_eq(10, tonumber("+10", 10))
+assert_eq(-10, tonumber(" -10 ", 10))
+assert_eq(true, tonumber("+-1", 10) == z)
+assert_eq(true, tonumber("8", 8) == z)
+assert_eq(true, tonumber("", 10) == z)
+assert_eq(true, tonumber("1.5", 10) == z)
+
+-- The standard assert returns all of its arguments
+local a, b, c = assert(1, "message", 3)
+assert_eq(1, a)
+assert_eq("message", b)
+assert_eq(3, c)
+
+-- Raw access ignores metamethods
+function hidden_index()
+    return "default"
+end
+function hidden_newindex()
+    fail("__newindex called")
+end
+function hidden_len()
+    return 100
+end
+function hidden_eq()
+    return true
+end
+local hidden = setmetatable({}, {__index = hidden_index, __newindex = hidden_newindex, __len = hidden_len, __eq = hidden_eq})
+assert_eq("default", hidden.missing)
+assert_eq(true, rawget(hidden, "missing") == z)
+assert_eq(hidden, rawset(hidden, "key", "value"))
+assert_eq("value", rawget(hidden, "key"))
+assert_eq(100, #hidden)
+rawset(hidden, 1, "one")
+assert_eq(1, rawlen(hidden))
+assert_eq(3, rawlen("abc"))
+local other = setmetatable({}, getmetatable(hidden))
+assert_eq(true, hidden == other)
+assert_eq(false, rawequal(hidden, other))
+assert_eq(true, rawequal(hidden, hidden))
+assert_eq(true, rawequal(1, 1.0))
+
+-- Selecting arguments
+assert_eq(3, select("#", "a", "b", "c"))
+assert_eq(0, select("#"))
+assert_eq("b", select(2, "a", "b", "c"))
+assert_eq("c", select(-1, "a", "b", "c"))
+local second, third = select(-2, "a", "b", "c")
+assert_eq("b", second)
+assert_eq("c", third)
+assert_eq(true, select(4, "a", "b", "c") == z)
+
+-- Unpacking and the version
+local x, y = unpack({1, 2})
+assert_eq(1, x)
+assert_eq(2, y)
+assert_eq("Lua 5.4", _VERSION)
+
+-- Errors
+local ok, message = pcall(assert, false)
+assert_eq(false, ok)
+assert_eq("assertion failed!", message)
+ok, message = pcall(assert, z, "custom message")
+-- The message is raised unchanged, without a position
+assert_eq("custom message", message)
+ok, message = pcall(assert, false, point)
+assert_eq(point, message)
+ok, message = pcall(tonumber)
+assert_eq("main:103: bad argument #1 to 'tonumber' (value expected)", message)
+ok, message = pcall(tonumber, "10", 99)
+assert_eq("main:105: bad argument #2 to 'tonumber' (base out of range)", message)
+ok, message = pcall(tonumber, 10, 16)
+assert_eq("main:107: bad argument #1 to 'tonumber' (string expected, got number)", message)
+ok, message = pcall(type)
+assert_eq("main:109: bad argument #1 to 'type' (value expected)", message)
+ok, message = pcall(rawget, "abc", 1)
+assert_eq("main:111: bad argument #1 to 'rawget' (table expected, got string)", message)
+ok, message = pcall(rawlen, 1)
+assert_eq("main:113: bad argument #1 to 'rawlen' (table or string expected)", message)
+ok, message = pcall(select, 0, "a")
+assert_eq("main:115: bad argument #1 to 'select' (index out of range)", message)
+ok, message = pcall(select, -2, "a")
+assert_eq("main:117: bad argument #1 to 'select' (index out of range)", message)
+ok, message = pcall(rawset, {}, z, 1)
+assert_eq("main:119: index is nil", message)
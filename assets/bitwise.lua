@@ -0,0 +1,32 @@
+require("lib/core")
+
+-- Bitwise operators work on integers
+assert_eq(0x0F, 0xFF & 0x0F)
+assert_eq(0xFF, 0xF0 | 0x0F)
+assert_eq(0xF0, 0xFF ~ 0x0F)
+assert_eq(-1, ~0)
+assert_eq(0x0F, ~0xFFFFFFFFFFFFFFF0)
+
+-- Shifts are logical and shifting by 64 or more bits gives zero
+assert_eq(256, 1 << 8)
+assert_eq(0x0F, 0xF0 >> 4)
+assert_eq(0x7FFFFFFFFFFFFFFF, -1 >> 1)
+assert_eq(0, 1 << 64)
+assert_eq(0, -1 >> 64)
+assert_eq(2, 4 << -1)
+
+-- Floats with an exact integer representation are converted
+assert_eq(6, 3.0 << 1)
+
+-- Unpack a packed color
+color = 0x336699
+red = (color >> 16) & 0xFF
+green = color >> 8 & 0xFF
+blue = color & 0xFF
+assert_eq(0x33, red)
+assert_eq(0x66, green)
+assert_eq(0x99, blue)
+
+flags = 1 | 4 | 16
+assert_eq(true, flags & 4 ~= 0)
+assert_eq(false, flags & 2 ~= 0)
@@ -0,0 +1,76 @@
+require "lib/core"
+
+function adder(a)
+    local add = {}
+    add.a = a
+    setmetatable(add, Adder)
+    return add
+end
+
+Adder = {}
+
+function Adder.__call(add, b)
+    return add.a + b
+end
+
+-- Calls can be chained and parenthesized
+assert_eq(5, adder(2)(3))
+assert_eq(7, (adder)(3)(4))
+
+function pair()
+    return 1, 2
+end
+
+local a, b = (pair())
+assert_eq(1, a)
+assert_eq(nil, b)
+
+-- Any prefix expression can be indexed
+Shapes = {}
+Shapes.square = {sides = 4, name = "square"}
+
+function Shapes.get(name)
+    return Shapes[name]
+end
+
+assert_eq(4, Shapes.get("square").sides)
+assert_eq("square", Shapes.get "square".name)
+assert_eq(4, Shapes["square"]["sides"])
+
+-- Table constructors
+local list = {10, 20, [5] = 50, key = "value"; 30}
+assert_eq(10, list[1])
+assert_eq(30, list[3])
+assert_eq(50, list[5])
+assert_eq("value", list.key)
+assert_eq(3, #list)
+
+local all = {pair()}
+assert_eq(2, #all)
+local first = {pair(), 5}
+assert_eq(2, #first)
+assert_eq(5, first[2])
+
+function count(t)
+    return #t
+end
+
+assert_eq(3, count{1, 2, 3})
+
+-- Fields and brackets can be assigned to
+local grid = {{}, {}}
+grid[2][1] = "x"
+Shapes.get("square").sides = 5
+assert_eq("x", grid[2][1])
+assert_eq(5, Shapes.square.sides)
+
+-- Methods receive the object as their first argument
+Counter = {count = 0}
+
+function Counter.increment(self, amount)
+    self.count = self.count + amount
+    return self
+end
+
+Counter:increment(2):increment(3)
+assert_eq(5, Counter.count)
@@ -0,0 +1,72 @@
+require("lib/core")
+
+local limit <const> = 10
+assert_eq(10, limit)
+
+-- Values are closed in reverse order when their block exits
+Closed = {}
+Closed.count = 0
+Closed.order = ""
+
+Closer = {}
+
+function Closer.__close(value, err)
+    Closed.count = Closed.count + 1
+    Closed.order = Closed.order .. value.name
+
+    if err then
+        Closed.err = err
+    end
+end
+
+function closer(name)
+    local value = {}
+    value.name = name
+    return setmetatable(value, Closer)
+end
+
+if true then
+    local a <close> = closer("a")
+    local b <close> = closer("b")
+    local nothing <close> = nil
+    assert_eq(0, Closed.count)
+end
+
+assert_eq(2, Closed.count)
+assert_eq("ba", Closed.order)
+
+-- Returning from a function closes its variables after the values are evaluated
+function with_return()
+    local c <close> = closer("c")
+    return Closed.count
+end
+
+assert_eq(2, with_return())
+assert_eq(3, Closed.count)
+
+-- So does break
+local i = 0
+
+while true do
+    local d <close> = closer("d")
+    i = i + 1
+
+    if i == 2 then
+        break
+    end
+end
+
+assert_eq(2, i)
+assert_eq(5, Closed.count)
+
+-- Errors are passed to '__close' and still propagate
+function with_error()
+    local e <close> = closer("e")
+    error("failed", 0)
+end
+
+local ok, err = pcall(with_error)
+assert_eq(false, ok)
+assert_eq("failed", err)
+assert_eq("failed", Closed.err)
+assert_eq("bacdde", Closed.order)
@@ -0,0 +1,22 @@
+require("lib/core")
+
+-- Strings that contain numerals are converted in arithmetic
+assert_eq(11, "10" + 1)
+assert_eq(30, "0x10" + 14)
+assert_eq(7.5, " 2.5 " * 3)
+assert_eq(-3, -"3")
+assert_eq(1, "3" & 1)
+
+-- Numbers are converted to strings in concatenations
+assert_eq("10", 10 .. "")
+assert_eq("2.0", 4 / 2 .. "")
+assert_eq("0.5", 0.5 .. "")
+assert_eq("1e+15", 1e15 .. "")
+assert_eq("x = 3", "x = " .. 1 + 2)
+
+-- tonumber uses the same conversion
+assert_eq(16, tonumber("0x10"))
+assert_eq(100.0, tonumber("1e2"))
+assert_eq(5, tonumber("  5  "))
+assert_eq(nil, tonumber("5 apples"))
+assert_eq(nil, tonumber("0x"))
@@ -0,0 +1,28 @@
+require("lib/core")
+
+-- Values of different types are never equal
+assert_eq(false, 1 == "1")
+assert_eq(true, 1 ~= "1")
+assert_eq(false, 0 == false)
+
+-- Integers and floats are compared by their value
+assert_eq(true, 1 == 1.0)
+assert_eq(true, 2 < 2.5)
+assert_eq(false, 3 <= 2.5)
+
+-- Tables are only equal to themselves
+a = {}
+b = {}
+c = a
+assert_eq(false, a == b)
+assert_eq(true, a == c)
+assert_eq(true, a ~= b)
+
+-- Strings are ordered byte by byte
+assert_eq(true, "a" < "b")
+assert_eq(true, "abc" < "abd")
+assert_eq(true, "Z" < "a")
+assert_eq(true, "" < "a")
+assert_eq(true, "10" < "9")
+assert_eq(true, "b" >= "a")
+assert_eq(false, "b" <= "a")
@@ -0,0 +1,87 @@
+require("lib/core")
+
+-- Values are passed both ways through resume and yield
+function accumulate(a, b)
+    local total = a + b
+    local extra = coroutine.yield(total)
+    total = total + extra
+    local x, y = coroutine.yield(total, "second")
+    return x * y
+end
+
+co = coroutine.create(accumulate)
+assert_eq("suspended", coroutine.status(co))
+
+local ok, total = coroutine.resume(co, 1, 2)
+assert_eq(true, ok)
+assert_eq(3, total)
+
+local ok, total, label = coroutine.resume(co, 10)
+assert_eq(13, total)
+assert_eq("second", label)
+
+local ok, product = coroutine.resume(co, 6, 7)
+assert_eq(true, ok)
+assert_eq(42, product)
+assert_eq("dead", coroutine.status(co))
+
+local ok, message = coroutine.resume(co)
+assert_eq(false, ok)
+assert_eq("cannot resume dead coroutine", message)
+
+-- Yields work across nested lua calls
+function produce(n)
+    coroutine.yield(n * 10)
+end
+
+function producer()
+    local i = 1
+    while i <= 3 do
+        produce(i)
+        i = i + 1
+    end
+    return "done"
+end
+
+local next_value = coroutine.wrap(producer)
+assert_eq(10, next_value())
+assert_eq(20, next_value())
+assert_eq(30, next_value())
+assert_eq("done", next_value())
+
+-- Errors are returned by resume
+function broken()
+    coroutine.yield(1)
+    error("broken", 0)
+end
+
+co = coroutine.create(broken)
+coroutine.resume(co)
+local ok, message = coroutine.resume(co)
+assert_eq(false, ok)
+assert_eq("broken", message)
+assert_eq("dead", coroutine.status(co))
+
+-- Coroutines know their status
+function check_status()
+    assert_eq("running", coroutine.status(running_co))
+    assert_eq(true, coroutine.isyieldable())
+    local ok = coroutine.resume(inner_co)
+    assert_eq(true, ok)
+end
+
+function check_outer_status()
+    assert_eq("normal", coroutine.status(running_co))
+end
+
+running_co = coroutine.create(check_status)
+inner_co = coroutine.create(check_outer_status)
+local ok = coroutine.resume(running_co)
+assert_eq(true, ok)
+assert_eq(false, coroutine.isyieldable())
+
+-- Suspended coroutines can be closed
+co = coroutine.create(produce)
+coroutine.resume(co, 1)
+assert_eq(true, coroutine.close(co))
+assert_eq("dead", coroutine.status(co))
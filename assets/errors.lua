@@ -0,0 +1,117 @@
+require("lib/core")
+
+-- pcall returns true and the results when there is no error
+function divide(a, b)
+    if b == 0 then
+        error("division by zero")
+    end
+    return a // b, a % b
+end
+
+local ok, quotient, remainder = pcall(divide, 7, 2)
+assert_eq(true, ok)
+assert_eq(3, quotient)
+assert_eq(1, remainder)
+
+-- Errors get the position of the call to error
+local ok, message = pcall(divide, 1, 0)
+assert_eq(false, ok)
+assert_eq("main:6: division by zero", message)
+
+-- Level 2 points to the caller, level 0 adds no position
+function check_positive(x)
+    if x <= 0 then
+        error("expected a positive number", 2)
+    end
+end
+
+function use_positive()
+    check_positive(-1)
+end
+
+local ok, message = pcall(use_positive)
+assert_eq("main:29: expected a positive number", message)
+
+function raw_error()
+    error("no position", 0)
+end
+
+local ok, message = pcall(raw_error)
+assert_eq("no position", message)
+
+-- Any value can be thrown
+function throw_table()
+    local e = {}
+    e.code = 42
+    error(e)
+end
+
+local ok, e = pcall(throw_table)
+assert_eq(false, ok)
+assert_eq(42, e.code)
+
+-- Errors raised by the interpreter are caught too
+function bad_arithmetic()
+    local x = {}
+    return x + 1
+end
+
+local ok, message = pcall(bad_arithmetic)
+assert_eq(false, ok)
+assert_eq("main:56: attempt to perform arithmetic on a table value (local 'x')", message)
+
+-- Scopes are restored after an error
+local before = "still here"
+pcall(bad_arithmetic)
+assert_eq("still here", before)
+
+-- The message handler of xpcall gets the error value
+function add_context(message)
+    return "handled: " .. message
+end
+
+local ok, message = xpcall(divide, add_context, 1, 0)
+assert_eq(false, ok)
+assert_eq("handled: main:6: division by zero", message)
+
+local ok, value = xpcall(divide, add_context, 9, 4)
+assert_eq(true, ok)
+assert_eq(2, value)
+
+-- Errors in the message handler are reported
+function broken_handler(message)
+    error("again")
+end
+
+local ok, message = xpcall(divide, broken_handler, 1, 0)
+assert_eq("error in error handling", message)
+
+-- Protected calls can be nested
+function nested()
+    local ok, message = pcall(divide, 1, 0)
+    assert_eq(false, ok)
+    error("outer", 0)
+end
+
+local ok, message = xpcall(nested, add_context)
+assert_eq("handled: outer", message)
+
+-- debug.traceback adds the calls that led to an error, other values are returned untouched
+function failing()
+    error("deep")
+end
+
+local ok, trace = xpcall(failing, debug.traceback)
+assert_eq(false, ok)
+assert_eq(true, trace ~= nil)
+
+local value = {}
+assert_eq(value, debug.traceback(value))
+
+-- A nil level is the same as level 1
+function nil_level()
+    error("level one", z)
+end
+
+local ok, message = pcall(nil_level)
+assert_eq("main:113: level one", message)
@@ -1,5 +1,5 @@
 require("lib/core")
 
-assert(5 + 5, 12 - 2)
-assert(5 * (5 - 3), (5/4) * (6 + 2))
-assert(5 + 5, 3 + 5 + 2)
\ No newline at end of file
+assert_eq(5 + 5, 12 - 2)
+assert_eq(5 * (5 - 3), (5/4) * (6 + 2))
+assert_eq(5 + 5, 3 + 5 + 2)
\ No newline at end of file
@@ -6,4 +6,4 @@ for i=0, 10, 1 do
     print(i)
 end
 
-assert(x, 50)
\ No newline at end of file
+assert_eq(x, 50)
\ No newline at end of file
@@ -0,0 +1,126 @@
+require("lib/core")
+
+local path = "target/io_library.txt"
+local newline = string.char(10)
+
+-- Writing returns the file so calls can be chained
+local f = io.open(path, "w")
+assert_eq("file", io.type(f))
+assert_eq(f, f:write("first line", newline, 42, " ", 1.5, newline))
+f:write("third"):write(" line", newline, "last")
+assert_eq(true, f:close())
+assert_eq("closed file", io.type(f))
+assert_eq(true, io.type(42) == z)
+
+-- Reading lines, numbers, counts and the rest of the file
+f = io.open(path, "r")
+assert_eq("first line", f:read("l"))
+local number, float = f:read("n", "n")
+assert_eq(42, number)
+assert_eq(1.5, float)
+assert_eq(newline, f:read("L"))
+assert_eq("third", f:read(5))
+assert_eq(" line" .. newline .. "last", f:read("a"))
+assert_eq("", f:read("a"))
+assert_eq(true, f:read("l") == z)
+assert_eq(true, f:read(0) == z)
+f:close()
+
+-- Lua 5.1 formats with a '*' still work, and a failed number stops the reading
+f = io.open(path)
+local missing, after = f:read("*n", "l")
+assert_eq(true, missing == z)
+assert_eq(true, after == z)
+assert_eq("first line", f:read("*l"))
+f:close()
+
+-- Seeking moves the position, reads after seeks see the new position
+f = io.open(path, "r+")
+assert_eq(0, f:seek("set"))
+assert_eq("first", f:read(5))
+assert_eq(5, f:seek())
+assert_eq(29, f:seek("end", -4))
+assert_eq("last", f:read("a"))
+f:seek("set", 6)
+f:write("LINE")
+f:seek("set")
+assert_eq("first LINE", f:read("l"))
+assert_eq(true, f:setvbuf("no"))
+assert_eq(true, f:flush())
+f:close()
+
+-- Appending always writes at the end
+f = io.open(path, "a")
+f:write(newline, "appended")
+f:close()
+
+-- Files hold bytes, so binary data is read back unchanged
+local binary_path = "target/io_library.bin"
+f = io.open(binary_path, "wb")
+f:write(string.char(0, 200, 255, 13, 10, 128))
+f:close()
+f = io.open(binary_path, "rb")
+assert_eq(string.char(0, 200), f:read(2))
+assert_eq(string.char(255, 13, 10, 128), f:read("a"))
+f:close()
+os.remove(binary_path)
+
+-- Iterating over lines, file:lines keeps the file open and io.lines closes it
+f = io.open(path)
+local iterator = f:lines()
+assert_eq("first LINE", iterator())
+assert_eq("42 1.5", iterator())
+f:close()
+
+local count = 0
+local next_line = io.lines(path, "L")
+local line = next_line()
+while line do
+    count = count + 1
+    line = next_line()
+end
+assert_eq(5, count)
+
+-- Failures return nil, a message and the error number
+local missing_file, message, errno = io.open("target/does/not/exist.txt")
+assert_eq(true, missing_file == z)
+assert_eq("target/does/not/exist.txt: No such file or directory", message)
+assert_eq(2, errno)
+
+-- Files are closed at the end of a block when they are in a '<close>' variable
+local kept = z
+do
+    local closing <close> = io.open(path)
+    kept = closing
+    assert_eq("first LINE", closing:read("l"))
+end
+assert_eq("closed file", io.type(kept))
+
+-- Errors for invalid arguments and closed files
+local ok, error_message = pcall(io.open, path, "rw")
+assert_eq("main:100: bad argument #2 to 'open' (invalid mode)", error_message)
+ok, error_message = pcall(f.read, f)
+assert_eq("main:102: attempt to use a closed file", error_message)
+f = io.open(path)
+ok, error_message = pcall(f.read, f, "x")
+assert_eq("main:105: bad argument #1 to 'read' (invalid format)", error_message)
+ok, error_message = pcall(f.seek, f, "middle")
+assert_eq("main:107: bad argument #1 to 'seek' (invalid option 'middle')", error_message)
+ok, error_message = pcall(f.read, 5)
+assert_eq("main:109: bad argument #1 to 'read' (FILE* expected, got number)", error_message)
+ok, error_message = pcall(io.lines, "target/does/not/exist.txt")
+assert_eq("main:111: cannot open file 'target/does/not/exist.txt' (No such file or directory)", error_message)
+f:close()
+
+-- The standard files can't be closed
+local closed, close_message = io.stdout:close()
+assert_eq("cannot close standard file", close_message)
+assert_eq(io.stdout, io.write(""))
+
+-- Files are userdata, scripts can't change their fields or their metatable
+assert_eq("userdata", type(io.stdout))
+ok, error_message = pcall(rawset, io.stdout, "x", 1)
+assert_eq("main:122: bad argument #1 to 'rawset' (table expected, got userdata)", error_message)
+ok, error_message = pcall(setmetatable, io.stdout, z)
+assert_eq("main:124: bad argument #1 to 'setmetatable' (table expected, got userdata)", error_message)
+assert_eq("file", io.type(io.stdout))
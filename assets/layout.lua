@@ -0,0 +1,43 @@
+require("lib/core")
+
+-- Statements can share a line and expressions can span several
+a = 1 b = 2; c = 3
+assert_eq(6, a + b + c)
+
+local total = a +
+    b *
+    c
+assert_eq(7, total)
+
+function sum(x, y,
+             z)
+    return x + y
+        + z
+end
+
+assert_eq(6, sum(
+    1,
+    2,
+    3
+))
+
+local config = {
+    name = "layout",
+    size = 2;
+    [10] = "ten",
+}
+
+assert_eq("layout", config.name)
+assert_eq("ten", config[10])
+
+local x, y =
+    sum(1, 2, 3),
+    config.size
+assert_eq(6, x)
+assert_eq(2, y)
+
+function nothing()
+    return
+end
+
+assert_eq(nil, nothing())
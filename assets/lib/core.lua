@@ -7,7 +7,7 @@ if test_global_core_lib_loaded == false then
 end
 test_global_core_lib_loaded = true
 
-function assert(x, y)
+function assert_eq(x, y)
     if x == y then
         return 0;
     end
@@ -0,0 +1,81 @@
+require("lib/core")
+
+local nl = string.char(10)
+
+-- Loading strings
+local chunk = load("return 1 + 2")
+assert_eq(3, chunk())
+local add = load("local a = 2" .. nl .. "return a * 10")
+assert_eq(20, add())
+
+-- Chunks see and change the global variables
+counter = 1
+load("counter = counter + 1")()
+assert_eq(2, counter)
+
+-- Syntax errors are returned instead of raised
+local broken, message = load("x = = 1")
+assert_eq(true, broken == z)
+assert_eq("string", type(message))
+local text_only, mode_message = load("return 1", "chunk", "b")
+assert_eq(true, text_only == z)
+assert_eq("attempt to load a text chunk (mode is 'b')", mode_message)
+assert_eq(1, load("return 1", "chunk", "t")())
+
+-- Reader functions return the source in pieces
+pieces = {"return ", "4", " * ", "5"}
+next_piece = 0
+function reader()
+    next_piece = next_piece + 1
+    return pieces[next_piece]
+end
+assert_eq(20, load(reader)())
+function bad_reader()
+    return 1
+end
+local no_chunk, reader_message = load(bad_reader)
+assert_eq(true, no_chunk == z)
+assert_eq("reader function must return a string", reader_message)
+
+-- Custom environments hold the globals of the chunk and the functions it defines
+local env = {x = 5}
+local sandboxed = load("y = x * 2" .. nl .. "function get_y() return y end", "sandbox", "t", env)
+sandboxed()
+assert_eq(10, env.y)
+assert_eq(true, y == z)
+assert_eq(true, get_y == z)
+assert_eq(10, env.get_y())
+assert_eq(true, load("return print", "sandbox", "t", {})() == z)
+
+-- Environments follow their metamethods
+local fallback = setmetatable({}, {__index = {value = "from fallback"}})
+assert_eq("from fallback", load("return value", "fallback", "t", fallback)())
+
+-- Loading and running files
+local path = "target/load_chunk.lua"
+local file = io.open(path, "w")
+file:write("#!/usr/bin/env lua" .. nl .. "runs = runs + 1" .. nl .. "return runs, 7")
+file:close()
+runs = 0
+local loaded = loadfile(path)
+assert_eq(1, loaded())
+local value, seven = dofile(path)
+assert_eq(2, value)
+assert_eq(7, seven)
+local file_env = {runs = 10}
+loadfile(path, "t", file_env)()
+assert_eq(11, file_env.runs)
+assert_eq(2, runs)
+os.remove(path)
+
+local missing, missing_message = loadfile("target/load_missing.lua")
+assert_eq(true, missing == z)
+assert_eq("cannot open target/load_missing.lua: No such file or directory", missing_message)
+
+-- Errors
+local ok, error_message = pcall(dofile, "target/load_missing.lua")
+assert_eq("cannot open target/load_missing.lua: No such file or directory", error_message)
+ok, error_message = pcall(load, {})
+assert_eq("main:78: bad argument #1 to 'load' (function expected, got table)", error_message)
+ok, error_message = pcall(load, "return 1", "chunk", "t", 1)
+assert_eq("main:80: bad argument #4 to 'load' (table expected, got number)", error_message)
@@ -23,4 +23,4 @@ end
 
 local result = fib(50);
 print(result)
-assert(result, 12586269025)
\ No newline at end of file
+assert_eq(result, 12586269025)
\ No newline at end of file
@@ -0,0 +1,78 @@
+require("lib/core")
+
+-- Rounding keeps integers and converts floats that fit
+assert_eq(3, math.floor(3.7))
+assert_eq("integer", math.type(math.floor(3.7)))
+assert_eq(-4, math.floor(-3.5))
+assert_eq(4, math.ceil(3.2))
+assert_eq(5, math.floor(5))
+assert_eq("float", math.type(math.floor(1e100)))
+assert_eq(7, math.abs(-7))
+assert_eq(2.5, math.abs(-2.5))
+
+-- Float functions
+assert_eq(3.0, math.sqrt(9))
+assert_eq(0.0, math.sin(0))
+assert_eq(1.0, math.cos(0))
+assert_eq(1.0, math.exp(0))
+assert_eq(3.0, math.log(8, 2))
+assert_eq(2.0, math.log(100, 10))
+assert_eq(1.0, math.log(math.exp(1)))
+assert_eq(true, math.abs(math.atan(1, 1) - math.pi / 4) < 1e-12)
+assert_eq(true, math.abs(math.atan(1) - math.pi / 4) < 1e-12)
+
+-- Remainders and integral parts
+assert_eq(1, math.fmod(7, 3))
+assert_eq(-1, math.fmod(-7, 3))
+assert_eq(1.5, math.fmod(5.5, 2))
+local integral, fraction = math.modf(3.75)
+assert_eq(3.0, integral)
+assert_eq(0.75, fraction)
+integral, fraction = math.modf(-2.5)
+assert_eq(-2.0, integral)
+assert_eq(-0.5, fraction)
+
+-- Min and max keep the type of the chosen value
+assert_eq(1, math.min(3, 1, 2))
+assert_eq(3.5, math.max(3, 1, 3.5))
+assert_eq("integer", math.type(math.max(1, 2)))
+
+-- Constants and integer helpers
+assert_eq(true, math.huge > 1e308)
+assert_eq(9223372036854775807, math.maxinteger)
+assert_eq(true, math.mininteger < 0)
+assert_eq(3, math.tointeger(3.0))
+assert_eq(true, math.tointeger(3.5) == z)
+assert_eq("float", math.type(1.0))
+assert_eq(true, math.type("1") == z)
+assert_eq(true, math.ult(1, -1))
+assert_eq(false, math.ult(-1, 1))
+
+-- Random numbers stay in their ranges
+for i = 1, 200 do
+    local x = math.random()
+    assert_eq(true, x >= 0)
+    assert_eq(true, x < 1)
+    local y = math.random(6)
+    assert_eq(true, y >= 1)
+    assert_eq(true, y <= 6)
+    local w = math.random(-3, 3)
+    assert_eq(true, w >= -3)
+    assert_eq(true, w <= 3)
+end
+assert_eq(5, math.random(5, 5))
+
+-- The same seed gives the same numbers
+math.randomseed(42)
+local first = math.random(1, 1000000)
+local second = math.random()
+math.randomseed(42)
+assert_eq(first, math.random(1, 1000000))
+assert_eq(second, math.random())
+
+local ok, message = pcall(math.random, 2, 1)
+assert_eq("main:73: bad argument #1 to 'random' (interval is empty)", message)
+ok, message = pcall(math.fmod, 1, 0)
+assert_eq("main:75: bad argument #2 to 'fmod' (zero)", message)
+ok, message = pcall(math.floor, "x")
+assert_eq("main:77: bad argument #1 to 'floor' (number expected, got string)", message)
@@ -0,0 +1,168 @@
+require("lib/core")
+
+-- Classes are built from a metatable that is its own '__index'
+Vector = {}
+Vector.__index = Vector
+Vector.__name = "Vector"
+
+function Vector.new(x, y)
+    local v = {}
+    v.x = x
+    v.y = y
+    return setmetatable(v, Vector)
+end
+
+function Vector.length_squared(v)
+    return v.x * v.x + v.y * v.y
+end
+
+function Vector.__add(a, b)
+    return Vector.new(a.x + b.x, a.y + b.y)
+end
+
+function Vector.__mul(a, b)
+    return Vector.new(a.x * b, a.y * b)
+end
+
+function Vector.__unm(v)
+    return Vector.new(-v.x, -v.y)
+end
+
+function Vector.__eq(a, b)
+    if a.x == b.x then
+        return a.y == b.y
+    end
+
+    return false
+end
+
+function Vector.__lt(a, b)
+    return Vector.length_squared(a) < Vector.length_squared(b)
+end
+
+function Vector.__le(a, b)
+    return Vector.length_squared(a) <= Vector.length_squared(b)
+end
+
+function Vector.__len(v)
+    return 2
+end
+
+function Vector.__concat(a, b)
+    return "Vector(" .. a.x .. ", " .. a.y .. ")" .. b
+end
+
+function Vector.__call(v, scale)
+    return v.x * scale
+end
+
+local a = Vector.new(1, 2)
+local b = Vector.new(3, 4)
+
+-- Methods are found through '__index'
+assert_eq(5, a.length_squared(a))
+assert_eq(true, getmetatable(a) == Vector)
+
+-- Arithmetic events
+local c = a + b
+assert_eq(4, c.x)
+assert_eq(6, c.y)
+local d = a * 3
+assert_eq(6, d.y)
+local e = -a
+assert_eq(-1, e.x)
+
+-- Comparison events
+local same = Vector.new(1, 2)
+local swapped = Vector.new(2, 1)
+assert_eq(true, a == same)
+assert_eq(false, a == b)
+assert_eq(true, a < b)
+assert_eq(true, a <= swapped)
+assert_eq(false, b <= a)
+
+assert_eq(2, #a)
+assert_eq(10, a(10))
+assert_eq("Vector(1, 2)!", a .. "!")
+print(a)
+
+-- '__index' and '__newindex' can be functions
+Defaults = {}
+
+function Defaults.__index(t, key)
+    return "default " .. key
+end
+
+function Defaults.__newindex(t, key, value)
+    Log.last = key
+end
+
+Log = {}
+local settings = setmetatable({}, Defaults)
+assert_eq("default color", settings.color)
+settings.color = "red"
+assert_eq("color", Log.last)
+assert_eq("default color", settings.color)
+
+-- '__newindex' can redirect assignments to another table
+Proxy = {}
+Proxy.__index = Log
+Proxy.__newindex = Log
+local proxy = setmetatable({}, Proxy)
+proxy.size = 10
+assert_eq(10, Log.size)
+assert_eq(10, proxy.size)
+
+-- Bitwise events
+Flags = {}
+
+function Flags.__band(a, b)
+    return "band"
+end
+
+function Flags.__shl(a, b)
+    return "shl"
+end
+
+local flags = setmetatable({}, Flags)
+assert_eq("band", flags & 1)
+assert_eq("shl", 1 << flags)
+
+-- Protected metatables
+Locked = {}
+Locked.__metatable = "locked"
+local locked = setmetatable({}, Locked)
+assert_eq("locked", getmetatable(locked))
+
+-- A '__call' handler can itself be a callable table
+Caller = {}
+
+function Caller.__call(handler, target, x)
+    return x * 10
+end
+
+Callable = {}
+Callable.__call = setmetatable({}, Caller)
+local callable = setmetatable({}, Callable)
+assert_eq(70, callable(7))
+
+-- Other metamethods can be callable tables too
+Summed = {}
+Summed.__add = setmetatable({}, Caller)
+assert_eq(20, setmetatable({}, Summed) + 2)
+
+-- A callable table used as '__index' is indexed, so classes can have a constructor
+ClassMeta = {}
+
+function ClassMeta.__call(class, name)
+    local object = {}
+    object.name = name
+    return setmetatable(object, class)
+end
+
+Class = setmetatable({}, ClassMeta)
+Class.__index = Class
+Class.kind = "class"
+local object = Class("first")
+assert_eq("first", object.name)
+assert_eq("class", object.kind)
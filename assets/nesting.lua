@@ -0,0 +1,90 @@
+require("lib/core")
+
+-- Blocks nest to any depth
+function count_even(limit)
+    local count = 0
+
+    for i = 1, 1000 do
+        if i > limit then
+            break
+        end
+
+        if i % 2 == 0 then
+            count = count + 1
+        end
+    end
+
+    return count
+end
+
+assert_eq(5, count_even(10))
+
+function grid_sum(size)
+    local total = 0
+
+    for x = 1, 1000 do
+        for y = 1, 1000 do
+            local j = 0
+
+            while j < 1 do
+                total = total + x * y
+                j = j + 1
+            end
+
+            if y == size then
+                break
+            end
+        end
+
+        if x == size then
+            break
+        end
+    end
+
+    return total
+end
+
+assert_eq(36, grid_sum(3))
+
+function outer()
+    function inner(x)
+        return x * 2
+    end
+
+    return inner(4)
+end
+
+assert_eq(8, outer())
+
+function classify(n)
+    if n < 0 then
+        return "negative"
+    elseif n == 0 then
+        return "zero"
+    elseif n < 10 then
+        return "small"
+    else
+        if n < 100 then
+            return "medium"
+        end
+
+        return "large"
+    end
+end
+
+assert_eq("negative", classify(-1))
+assert_eq("zero", classify(0))
+assert_eq("small", classify(5))
+assert_eq("medium", classify(50))
+assert_eq("large", classify(500))
+
+local value = 1
+
+do
+    value = value + 1
+    do
+        value = value * 10
+    end
+end
+
+assert_eq(20, value)
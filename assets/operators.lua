@@ -0,0 +1,19 @@
+require("lib/core")
+
+-- Floor division and modulo round towards minus infinity
+assert_eq(3, 7 // 2)
+assert_eq(-4, -7 // 2)
+assert_eq(3.0, 7.5 // 2)
+assert_eq(1, 7 % 3)
+assert_eq(2, -7 % 3)
+assert_eq(-2, 7 % -3)
+assert_eq(1.5, 5.5 % 2)
+
+-- Exponentiation always gives a float and is right associative
+assert_eq(1024.0, 2 ^ 10)
+assert_eq(512.0, 2 ^ 3 ^ 2)
+assert_eq(-4.0, -2 ^ 2)
+
+-- The length of a string is its number of bytes
+assert_eq(5, #"hello")
+assert_eq(0, #{})
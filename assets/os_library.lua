@@ -0,0 +1,74 @@
+require("lib/core")
+
+-- Formatting a fixed time in UTC, 1700000000 is Tuesday 2023-11-14 22:13:20
+local t = 1700000000
+assert_eq("2023-11-14 22:13:20", os.date("!%Y-%m-%d %H:%M:%S", t))
+assert_eq("Tue Nov 14 22:13:20 2023", os.date("!%c", t))
+assert_eq("Tuesday November 318 10 PM", os.date("!%A %B %j %I %p", t))
+assert_eq("11/14/23 22:13:20 22:13", os.date("!%x %X %R", t))
+assert_eq("2 2 46 46 46 2023 23", os.date("!%u %w %U %W %V %G %g", t))
+assert_eq("+0000 UTC 100%", os.date("!%z %Z 100%%", t))
+assert_eq("Thu Jan  1 00:00:00 1970", os.date("!%c", 0))
+assert_eq("1969-12-31 23:59:59", os.date("!%F %T", -1))
+assert_eq("2000-02-29", os.date("!%F", 951782400))
+
+-- ISO weeks at the edges of years
+assert_eq("2020-W53", os.date("!%G-W%V", 1609459200))
+assert_eq("2025-W01", os.date("!%G-W%V", 1735516800))
+
+-- Date tables
+local d = os.date("!*t", t)
+assert_eq(2023, d.year)
+assert_eq(11, d.month)
+assert_eq(14, d.day)
+assert_eq(22, d.hour)
+assert_eq(13, d.min)
+assert_eq(20, d.sec)
+assert_eq(3, d.wday)
+assert_eq(318, d.yday)
+assert_eq(false, d.isdst)
+
+-- os.time turns date tables back into times and normalizes their fields
+assert_eq(t, os.time(os.date("*t", t)))
+local overflow = {year = 2023, month = 13, day = 32, hour = 0}
+local normalized = os.time(overflow)
+assert_eq("2024-02-01 00:00:00", os.date("%Y-%m-%d %H:%M:%S", normalized))
+assert_eq(2024, overflow.year)
+assert_eq(2, overflow.month)
+assert_eq(1, overflow.day)
+assert_eq(12, os.date("*t", os.time({year = 2000, month = 1, day = 1})).hour)
+
+-- Time differences and the clocks
+assert_eq(10.0, os.difftime(t + 10, t))
+assert_eq("float", math.type(os.clock()))
+assert_eq(true, os.clock() >= 0)
+assert_eq("integer", math.type(os.time()))
+
+-- Environment variables that don't exist are nil
+assert_eq(true, os.getenv("AURORA_VARIABLE_THAT_DOES_NOT_EXIST") == z)
+
+-- Temporary files can be renamed and removed
+local name = os.tmpname()
+local f = io.open(name, "w")
+f:write("temporary")
+f:close()
+local renamed = name .. ".renamed"
+assert_eq(true, os.rename(name, renamed))
+assert_eq(true, io.open(name) == z)
+assert_eq(true, os.remove(renamed))
+local removed, message, errno = os.remove(renamed)
+assert_eq(true, removed == z)
+assert_eq(renamed .. ": No such file or directory", message)
+assert_eq(2, errno)
+
+-- Errors
+local ok, error_message = pcall(os.date, "%Ez", t)
+assert_eq("main:65: bad argument #1 to 'date' (invalid conversion specifier '%Ez')", error_message)
+ok, error_message = pcall(os.date, "%Q", t)
+assert_eq("main:67: bad argument #1 to 'date' (invalid conversion specifier '%Q')", error_message)
+ok, error_message = pcall(os.time, {year = 2000})
+assert_eq("main:69: field 'month' missing in date table", error_message)
+ok, error_message = pcall(os.time, {year = 2000, month = 1, day = 1.5})
+assert_eq("main:71: field 'day' is not an integer", error_message)
+ok, error_message = pcall(os.time, {year = 2000, month = 1, day = 3000000000})
+assert_eq("main:73: field 'day' is out-of-bound", error_message)
@@ -0,0 +1,73 @@
+require("lib/core")
+
+-- Character classes and quantifiers
+assert_eq(5, string.find("abcd1234", "%d"))
+assert_eq("1234", string.match("abcd1234", "%d+"))
+assert_eq("abcd", string.match("abcd1234", "%a*"))
+assert_eq("", string.match("1234", "%a*"))
+assert_eq("<b>", string.match("<b>text</b>", "<.->"))
+assert_eq("<b>text</b>", string.match("<b>text</b>", "<.*>"))
+assert_eq("color", string.match("the color red", "colou?r"))
+
+-- Anchors
+assert_eq("hello", string.match("hello world", "^%a+"))
+assert_eq(true, string.match("hello world", "^world") == z)
+assert_eq("world", string.match("hello world", "%a+$"))
+
+-- Find returns the captures after the positions
+local first, last, key, value = string.find("  name = aurora", "(%w+)%s*=%s*(%w+)")
+assert_eq(3, first)
+assert_eq(15, last)
+assert_eq("name", key)
+assert_eq("aurora", value)
+
+-- Plain find ignores special characters
+assert_eq(2, string.find("a.b", ".", 1, true))
+assert_eq(1, string.find("a.b", "."))
+
+-- Position captures, back references, balanced matches and frontiers
+local before, after = string.match("hello", "()ll()")
+assert_eq(3, before)
+assert_eq(5, after)
+local quote, word = string.match("say 'hi' now", "(['])(.-)%1")
+assert_eq("hi", word)
+assert_eq("(a(b)c)", string.match("f(a(b)c)", "%b()"))
+assert_eq("THE", string.match("THE (quick) fox", "%f[%a]%a+"))
+
+-- Gmatch iterates over every match
+local words = string.gmatch("one two  three", "%a+")
+assert_eq("one", words())
+assert_eq("two", words())
+assert_eq("three", words())
+assert_eq(true, words() == z)
+
+local fields = string.gmatch("a=1, b=2", "(%w+)=(%w+)")
+local k, v = fields()
+assert_eq("a", k)
+assert_eq("1", v)
+k, v = fields()
+assert_eq("b", k)
+assert_eq("2", v)
+
+-- Gsub with captures in the replacement
+assert_eq("world hello", string.gsub("hello world", "(%w+) (%w+)", "%2 %1"))
+assert_eq("hello", string.gsub("  hello  ", "^%s*(.-)%s*$", "%1"))
+assert_eq("x-x-x", string.gsub("a-b-c", "%a", "x"))
+local result, count = string.gsub("abc", "%w", "%0%0")
+assert_eq("aabbcc", result)
+assert_eq(3, count)
+assert_eq("Xbc", string.gsub("abc", "^.", "X"))
+
+-- Captures are passed to replacement functions
+function double(x)
+    return x * 2
+end
+assert_eq("2 4 6", string.gsub("1 2 3", "%d", double))
+
+-- Malformed patterns are errors
+local ok, message = pcall(string.find, "a", "[a")
+assert_eq("main:68: malformed pattern (missing ']')", message)
+ok, message = pcall(string.match, "a", "%")
+assert_eq("main:70: malformed pattern (ends with '%')", message)
+ok, message = pcall(string.gsub, "a", "(a)", "%2")
+assert_eq("main:72: invalid capture index %2 in replacement string", message)
@@ -0,0 +1,146 @@
+require("lib/core")
+
+-- Length, case and repetition
+local s = "Hello World"
+assert_eq(11, string.len(s))
+assert_eq("HELLO WORLD", string.upper(s))
+assert_eq("hello world", string.lower(s))
+assert_eq("dlroW olleH", string.reverse(s))
+assert_eq("ababab", string.rep("ab", 3))
+assert_eq("a, a, a", string.rep("a", 3, ", "))
+assert_eq("", string.rep("a", 0))
+
+-- Sub accepts negative positions and clamps out of range ones
+assert_eq("World", string.sub(s, 7))
+assert_eq("Hello", string.sub(s, 1, 5))
+assert_eq("rld", string.sub(s, -3))
+assert_eq("Wor", string.sub(s, -5, -3))
+assert_eq("Hello World", string.sub(s, -100, 100))
+assert_eq("", string.sub(s, 5, 2))
+
+-- Bytes and characters
+assert_eq(72, string.byte(s))
+local a, b, c = string.byte(s, 1, 3)
+assert_eq(101, b)
+assert_eq(108, c)
+assert_eq(100, string.byte(s, -1))
+assert_eq("Hi!", string.char(72, 105, 33))
+assert_eq("", string.char())
+
+-- Strings are bytes, characters outside of ASCII take several of them
+assert_eq(1, #string.char(255))
+assert_eq(200, string.byte(string.char(200)))
+assert_eq(3, #"hé")
+assert_eq(string.char(195), string.sub("héllo", 2, 2))
+assert_eq(string.char(169, 195) .. "h", string.reverse("hé"))
+local x, y, w = string.byte(string.char(0, 128, 255), 1, 3)
+assert_eq(128, y)
+assert_eq(255, w)
+
+-- Methods are found through the string metatable
+assert_eq("HELLO WORLD", s:upper())
+assert_eq(11, s:len())
+assert_eq("Hel", s:sub(1, 3))
+assert_eq("xyxy", ("xy"):rep(2))
+
+-- Integer conversions
+assert_eq("42", string.format("%d", 42))
+assert_eq("-42", string.format("%i", -42))
+assert_eq("   42", string.format("%5d", 42))
+assert_eq("42   |", string.format("%-5d|", 42))
+assert_eq("00042", string.format("%05d", 42))
+assert_eq("+42", string.format("%+d", 42))
+assert_eq(" 42", string.format("% d", 42))
+assert_eq("007", string.format("%.3d", 7))
+assert_eq("3", string.format("%d", 3.0))
+assert_eq("ff", string.format("%x", 255))
+assert_eq("FF", string.format("%X", 255))
+assert_eq("0xff", string.format("%#x", 255))
+assert_eq("17", string.format("%o", 15))
+assert_eq("ffffffffffffffff", string.format("%x", -1))
+assert_eq("A", string.format("%c", 65))
+
+-- Float conversions
+assert_eq("3.140000", string.format("%f", 3.14))
+assert_eq("3.14", string.format("%.2f", 3.14159))
+assert_eq("  3.1", string.format("%5.1f", 3.14159))
+assert_eq("1.500000e+02", string.format("%e", 150))
+assert_eq("1.5E+02", string.format("%.1E", 150))
+assert_eq("0.0001", string.format("%g", 0.0001))
+assert_eq("1e-05", string.format("%g", 0.00001))
+assert_eq("1E+20", string.format("%G", 1e20))
+assert_eq("100000", string.format("%g", 100000))
+assert_eq("1.00000", string.format("%#g", 1))
+assert_eq("0x1p+0", string.format("%a", 1))
+assert_eq("0x1.8p+1", string.format("%a", 3))
+assert_eq("-0X1.8P+1", string.format("%A", -3))
+assert_eq("inf", string.format("%f", 1 / 0))
+assert_eq("-inf", string.format("%.1f", -1 / 0))
+
+-- Strings, percent signs and quoting
+assert_eq("[hello]", string.format("[%s]", "hello"))
+assert_eq("[   hi]", string.format("[%5s]", "hi"))
+assert_eq("[hi   ]", string.format("[%-5s]", "hi"))
+assert_eq("hel", string.format("%.3s", "hello"))
+assert_eq("100%", string.format("%d%%", 100))
+assert_eq("1 true nil", string.format("%s %s %s", 1, true, z))
+assert_eq("42", string.format("%q", 42))
+assert_eq("0x1.8p+1", string.format("%q", 3.0))
+assert_eq("1e9999", string.format("%q", 1 / 0))
+assert_eq(string.char(34, 200, 34), string.format("%q", string.char(200)))
+assert_eq(string.char(200), string.format("%c", 200))
+assert_eq("[  hé]", string.format("[%5s]", "hé"))
+assert_eq("a=1, b=x", string.format("a=%d, b=%s", 1, "x"))
+
+-- Find returns the positions of a match, searching from init
+assert_eq(7, string.find(s, "World"))
+local first, last = string.find(s, "o", 6)
+assert_eq(8, first)
+assert_eq(8, last)
+assert_eq(true, string.find(s, "xyz") == z)
+assert_eq(1, string.find(s, ""))
+assert_eq(true, string.find(s, "H", 100) == z)
+assert_eq(7, string.find(s, "W", -5))
+assert_eq(4, string.find("a.b.c", ".", 3, true))
+
+-- Match returns the matched text
+assert_eq("World", string.match(s, "World"))
+assert_eq(true, string.match(s, "world") == z)
+
+-- Gmatch iterates over every match
+local words = string.gmatch("one two three", "t")
+assert_eq("t", words())
+assert_eq("t", words())
+assert_eq(true, words() == z)
+
+-- Gsub replaces matches and counts them
+local result, replaced = string.gsub("hello world", "o", "0")
+assert_eq("hell0 w0rld", result)
+assert_eq(2, replaced)
+assert_eq("hell0 world", string.gsub("hello world", "o", "0", 1))
+assert_eq("[o]ne [o]ne", string.gsub("one one", "o", "[%0]"))
+assert_eq("-a-b-", string.gsub("ab", "", "-"))
+assert_eq("100%", string.gsub("100", "100", "100%%"))
+
+-- Tables and functions can produce the replacement, false and nil keep the match
+local names = {}
+names.cat = "dog"
+assert_eq("dog and mouse", string.gsub("cat and mouse", "cat", names))
+assert_eq("cat and mouse", string.gsub("cat and mouse", "mouse", names))
+assert_eq("CAT and mouse", string.gsub("cat and mouse", "cat", string.upper))
+function keep(x)
+    return false
+end
+assert_eq("cat and mouse", string.gsub("cat and mouse", "cat", keep))
+
+-- Errors name the function and the bad argument
+local ok, message = pcall(string.rep)
+assert_eq("main:137: bad argument #1 to 'rep' (string expected, got no value)", message)
+ok, message = pcall(string.format, "%d", 1.5)
+assert_eq("main:139: bad argument #2 to 'format' (number has no integer representation)", message)
+ok, message = pcall(string.format, "%y", 1)
+assert_eq("main:141: invalid conversion '%y' to 'format'", message)
+ok, message = pcall(string.format, "%d")
+assert_eq("main:143: bad argument #2 to 'format' (no value)", message)
+ok, message = pcall(string.char, 256)
+assert_eq("main:145: bad argument #1 to 'char' (value out of range)", message)
@@ -0,0 +1,75 @@
+require("lib/core")
+
+-- Insert appends or shifts the elements after the position up
+local t = {"a", "c"}
+table.insert(t, "d")
+table.insert(t, 2, "b")
+assert_eq(4, #t)
+assert_eq("a,b,c,d", table.concat(t, ","))
+table.insert(t, 5, "e")
+assert_eq("abcde", table.concat(t))
+
+-- Remove returns the element and shifts the rest down
+assert_eq("e", table.remove(t))
+assert_eq("a", table.remove(t, 1))
+assert_eq("b c d", table.concat(t, " "))
+assert_eq(3, #t)
+local empty = {}
+assert_eq(true, table.remove(empty) == z)
+
+-- Concat accepts a range and numbers
+local numbers = {1, 2, 3, 4.5}
+assert_eq("2-3", table.concat(numbers, "-", 2, 3))
+assert_eq("1 2 3 4.5", table.concat(numbers, " "))
+assert_eq("", table.concat(numbers, ",", 3, 2))
+
+-- Unpack and pack
+local a, b, c = table.unpack({10, 20, 30})
+assert_eq(10, a)
+assert_eq(30, c)
+local second, third = table.unpack({10, 20, 30}, 2)
+assert_eq(20, second)
+assert_eq(30, third)
+local x, y = table.unpack({10, 20, 30}, 2, 3)
+assert_eq(30, y)
+local packed = table.pack(1, z, 3)
+assert_eq(3, packed.n)
+assert_eq(3, packed[3])
+
+-- Move copies ranges, overlapping ones included
+local moved = table.move({1, 2, 3}, 1, 3, 2)
+assert_eq("1,1,2,3", table.concat(moved, ","))
+local target = table.move({1, 2, 3}, 2, 3, 1, {})
+assert_eq("2,3", table.concat(target, ","))
+
+-- Sort uses '<' or a comparator function
+local words = {"pear", "apple", "fig", "banana"}
+table.sort(words)
+assert_eq("apple banana fig pear", table.concat(words, " "))
+
+local values = {5, 2, 8, 1, 9, 3, 7, 4, 6, 10, 15, 12, 11, 14, 13}
+table.sort(values)
+assert_eq("1 2 3 4 5 6 7 8 9 10 11 12 13 14 15", table.concat(values, " "))
+
+function greater(a, b)
+    return a > b
+end
+table.sort(values, greater)
+assert_eq("15 14 13 12 11 10 9 8 7 6 5 4 3 2 1", table.concat(values, " "))
+
+-- Comparators that aren't consistent are errors instead of crashes
+function always(a, b)
+    return true
+end
+local ok, message = pcall(table.sort, values, always)
+assert_eq(false, ok)
+assert_eq("main:64: invalid order function for sorting", message)
+
+ok, message = pcall(table.sort, {1, "x", 2})
+assert_eq("main:68: attempt to compare string with number", message)
+ok, message = pcall(table.insert, {}, 1, 2, 3)
+assert_eq("main:70: wrong number of arguments to 'insert'", message)
+ok, message = pcall(table.insert, {}, 5, 1)
+assert_eq("main:72: bad argument #2 to 'insert' (position out of bounds)", message)
+ok, message = pcall(table.concat, {1, {}, 3})
+assert_eq("main:74: invalid value (at index 2) in table for 'concat'", message)
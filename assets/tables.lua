@@ -8,7 +8,7 @@ numbers.one_fifth = 1 / 5
 
 num = numbers
 
-assert(1/5, numbers.one_fifth)
+assert_eq(1/5, numbers.one_fifth)
 print("Pi is " .. num.pi)
 print("1/5 is " .. num.one_fifth)
 
@@ -26,5 +26,5 @@ local ten = math.double(5);
 print("5 * 2 = "  .. ten)
 
 print("")
-print("Numbers: " .. numbers)
-print("Double Function: " .. math.double)
+print("Numbers:", numbers)
+print("Double Function:", math.double)
@@ -12,12 +12,12 @@ x = 5
 y = x 
 y = 6
 
-assert(x, 5)
+assert_eq(x, 5)
 
 -- Tables are passed by reference
 x = {}
 y = x
 y.foo = "Foo"
 print("TEST")
-assert(x.foo, "Foo")
+assert_eq(x.foo, "Foo")
 
@@ -0,0 +1,92 @@
+require("lib/core")
+
+-- Building strings from code points
+assert_eq("héllo", utf8.char(104, 233, 108, 108, 111))
+assert_eq("日本語", utf8.char(26085, 26412, 35486))
+assert_eq("€", utf8.char(8364))
+assert_eq("", utf8.char())
+assert_eq(4, #utf8.char(128512))
+
+-- Lengths count characters, not bytes
+local name = "Zoë Ångström"
+assert_eq(15, #name)
+assert_eq(12, utf8.len(name))
+assert_eq(3, utf8.len("日本語"))
+assert_eq(2, utf8.len("日本語", 4))
+assert_eq(1, utf8.len("日本語", -3))
+assert_eq(0, utf8.len(""))
+
+-- Invalid input returns nil and the position of the first bad byte
+local count, position = utf8.len("héllo", 3)
+assert_eq(true, count == z)
+assert_eq(3, position)
+count, position = utf8.len(string.char(195))
+assert_eq(true, count == z)
+assert_eq(1, position)
+count, position = utf8.len("ab" .. string.char(255))
+assert_eq(true, count == z)
+assert_eq(3, position)
+
+-- Code points of a range of bytes
+assert_eq(233, utf8.codepoint("é"))
+local a, b, c = utf8.codepoint("日本語", 1, -1)
+assert_eq(26085, a)
+assert_eq(26412, b)
+assert_eq(35486, c)
+assert_eq(26412, utf8.codepoint("日本語", 4))
+
+-- Byte offsets of characters
+assert_eq(1, utf8.offset("日本語", 1))
+assert_eq(4, utf8.offset("日本語", 2))
+assert_eq(7, utf8.offset("日本語", -1))
+assert_eq(10, utf8.offset("日本語", 4))
+assert_eq(true, utf8.offset("日本語", 5) == z)
+assert_eq(4, utf8.offset("日本語", 0, 5))
+assert_eq(1, utf8.offset("abc", -3))
+
+-- Iterating over the characters
+local iterator, s, index = utf8.codes("añ日")
+local p, code = iterator(s, index)
+assert_eq(1, p)
+assert_eq(97, code)
+p, code = iterator(s, p)
+assert_eq(2, p)
+assert_eq(241, code)
+p, code = iterator(s, p)
+assert_eq(4, p)
+assert_eq(26085, code)
+assert_eq(true, iterator(s, p) == z)
+
+-- The char pattern matches one character at a time
+local chars = {}
+local next_char = string.gmatch("año日", utf8.charpattern)
+local char = next_char()
+while char do
+    table.insert(chars, char)
+    char = next_char()
+end
+assert_eq("a,ñ,o,日", table.concat(chars, ","))
+assert_eq("ñ", string.match("ñu", utf8.charpattern))
+
+-- Lax mode gives the same results for valid text
+assert_eq(true, utf8.len("abc", 1, -1, true) == 3)
+
+-- Surrogates and code points beyond unicode are encoded as they are, only lax mode reads them back
+local surrogate = utf8.char(55296)
+assert_eq(string.char(237, 160, 128), surrogate)
+assert_eq(55296, utf8.codepoint(surrogate, 1, 1, true))
+assert_eq(true, utf8.len(surrogate) == z)
+assert_eq(6, #utf8.char(2147483647))
+assert_eq(2147483647, utf8.codepoint(utf8.char(2147483647), 1, 1, true))
+
+-- Errors
+local ok, message = pcall(utf8.char, -1)
+assert_eq("main:83: bad argument #1 to 'char' (value out of range)", message)
+ok, message = pcall(utf8.codepoint, "héllo", 3)
+assert_eq("main:85: invalid UTF-8 code", message)
+ok, message = pcall(utf8.codepoint, "abc", 4)
+assert_eq("main:87: bad argument #3 to 'codepoint' (out of bounds)", message)
+ok, message = pcall(utf8.len, "abc", 5)
+assert_eq("main:89: bad argument #2 to 'len' (initial position out of bounds)", message)
+ok, message = pcall(utf8.offset, "héllo", 1, 3)
+assert_eq("main:91: initial position is a continuation byte", message)
@@ -8,6 +8,7 @@ use linefeed::{Interface, ReadResult};
 use aurora::interpreter::{Interpreter, function::FunctionDef};
 use aurora::parser;
 use aurora::error::LuaError;
+use aurora::diagnostic::Renderer;
 use aurora::data::LuaData;
 
 
@@ -27,10 +28,10 @@ fn run_console() -> io::Result<()>{
 
     while let ReadResult::Input(line) = interface.read_line()? {
         interface.add_history_unique(line.clone());
-        match run_line(&mut intepreter, line){
+        match run_line(&mut intepreter, line.clone()){
             Err(errors) => {
                 for e in errors{
-                    println!("{}", e);
+                    println!("{}", Renderer::new(true).render(&e, Some(&line)));
                 }
             },
             _ => (),
@@ -43,7 +44,7 @@ fn run_console() -> io::Result<()>{
 fn create_console_interpreter() -> Interpreter{
     let mut intepreter = Interpreter::new();
 
-    intepreter.func_manager.register_func("quit".to_string(), FunctionDef::Rust(|_, _| -> Result<Option<LuaData>, LuaError>{
+    intepreter.register_func("quit", FunctionDef::Rust(|_, _| -> Result<Vec<LuaData>, LuaError>{
         ::std::process::exit(0);
     }));
 
@@ -51,11 +52,8 @@ fn create_console_interpreter() -> Interpreter{
 }
 
 fn run_line(intepreter: &mut Interpreter, line: String) -> Result<(), Vec<LuaError>>{
-    let tokens = parser::scanner::scan(line)?;
-    let stmts = match parser::parse(tokens){
-        Err(e) => return Err(vec![e]),
-        Ok(x) => x,
-    };
+    let tokens = parser::scanner::scan_chunk(line, "stdin")?;
+    let stmts = parser::parse(tokens)?;
 
     for stmt in stmts{
         match intepreter.run_stmt(&mut stmt.clone()){
@@ -23,6 +23,9 @@ fn main() {
             .short("q")
             .long("quiet")
             .conflicts_with("verbose"))
+        .arg(Arg::with_name("no-color")
+            .help("Prints errors without terminal colors")
+            .long("no-color"))
         .arg(Arg::with_name("file")
             .short("f")
             .long("file")
@@ -47,18 +50,21 @@ fn create_config(matches: &ArgMatches) -> Config{
         LogLevel::Normal
     };
     
-    Config::new(log_level)
+    let mut config = Config::new(log_level);
+    config.color = !matches.is_present("no-color");
+
+    config
 }
 
 fn run_file(name: &str, config: Config){
     let src = load_file(name);
     let mut aurora = Aurora::new(config);
 
-    match aurora.run(src){
+    match aurora.run_chunk(src, &format!("assets/{}.lua", name)){
         Ok(_) => (),
         Err(errors) => {
             for e in errors{
-                println!("{}", e)
+                println!("{}", aurora.render_error(&e))
             }
         },
     }
@@ -1,17 +1,39 @@
+use std::fmt;
+use super::interpreter::clock::{Clock, Environment};
+
 #[derive(Debug, PartialEq)]
 pub enum LogLevel{
     Quiet, Normal, Verbose 
 }
 
-#[derive(Debug, PartialEq)]
 pub struct Config{
     pub log_level: LogLevel,
+    /// Whether rendered errors use terminal colors
+    pub color: bool,
+    /// Seeds 'math.random' so runs can be replayed, None seeds it from the time
+    pub random_seed: Option<i64>,
+    /// The clock of the os library, None uses the system clock
+    pub clock: Option<Box<dyn Clock>>,
+    /// The variables 'os.getenv' sees, None uses the environment of the process
+    pub environment: Option<Box<dyn Environment>>,
 }
 
 impl Config{
 
     pub fn new(level: LogLevel) -> Config{
-        Config{log_level: level}
+        Config{log_level: level, color: true, random_seed: None, clock: None, environment: None}
     }
 
-}
\ No newline at end of file
+}
+
+impl fmt::Debug for Config{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        f.debug_struct("Config")
+            .field("log_level", &self.log_level)
+            .field("color", &self.color)
+            .field("random_seed", &self.random_seed)
+            .field("clock", &self.clock.as_ref().map(|_| "custom"))
+            .field("environment", &self.environment.as_ref().map(|_| "custom"))
+            .finish()
+    }
+}
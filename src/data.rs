@@ -1,51 +1,426 @@
 
 use std::fmt;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum LuaData{
-    Str(String),
+    /// Lua strings are byte strings and can hold any bytes, not only UTF-8
+    Str(Vec<u8>),
     Number(f64),
+    Int(i64),
     Bool(bool),
     Func(i64),
     Table(i64),
+    Thread(ObjectRef),
+    UserData(ObjectRef),
     Nil,
 }
 
+/// A reference to a coroutine or a userdata. When the last reference is dropped the id is added
+/// to the released list, so the interpreter can end or finalize the object and remove it
+#[derive(Clone)]
+pub struct ObjectRef(Arc<ObjectId>);
+
+struct ObjectId{
+    id: i64,
+    released: Arc<Mutex<Vec<i64>>>,
+}
+
+impl ObjectRef{
+
+    pub fn new(id: i64, released: Arc<Mutex<Vec<i64>>>) -> ObjectRef{
+        ObjectRef(Arc::new(ObjectId{id, released}))
+    }
+
+    pub fn id(&self) -> i64{
+        self.0.id
+    }
+}
+
+impl Drop for ObjectId{
+
+    fn drop(&mut self){
+        // The list only holds ids, so it is still usable if a thread panicked while holding the lock
+        let mut released = self.released.lock().unwrap_or_else(|e| e.into_inner());
+        released.push(self.id);
+    }
+}
+
+impl PartialEq for ObjectRef{
+
+    fn eq(&self, other: &ObjectRef) -> bool{
+        self.id() == other.id()
+    }
+}
+
+impl Eq for ObjectRef{}
+
+impl Hash for ObjectRef{
+
+    fn hash<H: Hasher>(&self, state: &mut H){
+        self.id().hash(state);
+    }
+}
+
+impl fmt::Debug for ObjectRef{
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "ObjectRef({})", self.id())
+    }
+}
+
 impl LuaData{
 
-    pub fn to_num(&self) -> f64{
+    /// Converts numbers and strings that contain a numeral to a float
+    pub fn to_num(&self) -> Option<f64>{
+        match self.to_number()?{
+            LuaData::Int(x) => Some(x as f64),
+            LuaData::Number(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Converts a number to an integer following Lua's rules:
+    /// floats are only converted if they have an exact integer representation
+    pub fn to_int(&self) -> Option<i64>{
+        match self{
+            LuaData::Int(x) => Some(*x),
+            LuaData::Number(x) => float_to_int(*x),
+            _ => None,
+        }
+    }
+
+    /// Converts numbers and strings that contain a numeral to a number
+    pub fn to_number(&self) -> Option<LuaData>{
+        match self{
+            LuaData::Int(_) | LuaData::Number(_) => Some(self.clone()),
+            LuaData::Str(x) => str_to_number(std::str::from_utf8(x).ok()?),
+            _ => None,
+        }
+    }
+
+    /// Compares two values without invoking any metamethods.
+    /// Numbers are equal if they have the same mathematical value,
+    /// tables and functions are only equal to themselves
+    pub fn raw_equals(&self, other: &LuaData) -> bool{
+        match (self, other){
+            (LuaData::Str(x), LuaData::Str(y)) => x == y,
+            (LuaData::Bool(x), LuaData::Bool(y)) => x == y,
+            (LuaData::Table(x), LuaData::Table(y)) => x == y,
+            (LuaData::Func(x), LuaData::Func(y)) => x == y,
+            (LuaData::Thread(x), LuaData::Thread(y)) => x == y,
+            (LuaData::UserData(x), LuaData::UserData(y)) => x == y,
+            (LuaData::Nil, LuaData::Nil) => true,
+            (x, y) if x.is_number() && y.is_number() => compare_numbers(x, y) == Some(Ordering::Equal),
+            _ => false,
+        }
+    }
+
+    pub fn is_number(&self) -> bool{
+        matches!(self, LuaData::Number(_) | LuaData::Int(_))
+    }
+
+    pub fn type_name(&self) -> &'static str{
         match self{
-            LuaData::Number(x) => *x,
-            LuaData::Bool(false) => 0.0,
-            LuaData::Bool(true) => 1.0,
-            LuaData::Table(n) => *n as f64,
-            LuaData::Func(n) => *n as f64,
-            LuaData::Str(_) => unimplemented!("String to number conversion isn't implemented"),
-            LuaData::Nil => 0.0,
+            LuaData::Str(_) => "string",
+            LuaData::Number(_) | LuaData::Int(_) => "number",
+            LuaData::Bool(_) => "boolean",
+            LuaData::Func(_) => "function",
+            LuaData::Table(_) => "table",
+            LuaData::Thread(_) => "thread",
+            LuaData::UserData(_) => "userdata",
+            LuaData::Nil => "nil",
         }
     }
 
     pub fn to_string(&self) -> String{
         match self{
-            LuaData::Str(x) => (*x).to_string(),
+            LuaData::Str(x) => String::from_utf8_lossy(x).into_owned(),
             LuaData::Bool(x) => format!("{}", x),
-            LuaData::Number(x) => format!("{}", x),
-            LuaData::Table(id) => format!("Table[{}]", id),
-            LuaData::Func(id) => format!("Func[{}]", id),
+            LuaData::Number(x) => format_float(*x),
+            LuaData::Int(x) => format!("{}", x),
+            LuaData::Table(id) => format!("table: 0x{:08x}", id),
+            LuaData::Func(id) => format!("function: 0x{:08x}", id),
+            LuaData::Thread(x) => format!("thread: 0x{:08x}", x.id()),
+            LuaData::UserData(x) => format!("userdata: 0x{:08x}", x.id()),
             LuaData::Nil => "nil".to_string(),
         }
     }
 
+    /// The bytes of a string or the text of any other value, strings are kept as they are
+    pub fn to_bytes(&self) -> Vec<u8>{
+        match self{
+            LuaData::Str(x) => x.clone(),
+            x => x.to_string().into_bytes(),
+        }
+    }
+
     pub fn to_bool(&self) -> bool{
         match self{
             LuaData::Nil => false,
-            LuaData::Str(x) if x == "false" => false,
+            LuaData::Str(x) if x == b"false" => false,
             LuaData::Bool(x) => *x,
             _ => true
         }
     }
 }
 
+/// Compares two numbers by their mathematical value, integers and floats are compared exactly.
+/// Returns None if either value isn't a number or is NaN
+pub fn compare_numbers(left: &LuaData, right: &LuaData) -> Option<Ordering>{
+    match (left, right){
+        (LuaData::Int(x), LuaData::Int(y)) => Some(x.cmp(y)),
+        (LuaData::Number(x), LuaData::Number(y)) => x.partial_cmp(y),
+        (LuaData::Int(x), LuaData::Number(y)) => compare_int_float(*x, *y),
+        (LuaData::Number(x), LuaData::Int(y)) => compare_int_float(*y, *x).map(Ordering::reverse),
+        _ => None,
+    }
+}
+
+fn compare_int_float(int: i64, float: f64) -> Option<Ordering>{
+    if float.is_nan(){
+        return None;
+    }
+
+    if float >= 9_223_372_036_854_775_808.0{
+        return Some(Ordering::Less);
+    }
+
+    if float < -9_223_372_036_854_775_808.0{
+        return Some(Ordering::Greater);
+    }
+
+    let floor = float.floor();
+
+    match int.cmp(&(floor as i64)){
+        Ordering::Equal if float > floor => Some(Ordering::Less),
+        x => Some(x),
+    }
+}
+
+/// Converts a float to an integer if it has an exact integer representation
+pub fn float_to_int(x: f64) -> Option<i64>{
+    // 2^63 is exactly representable as a float, while i64::MAX is not
+    if x.floor() == x && (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&x){
+        Some(x as i64)
+    }else{
+        None
+    }
+}
+
+/// Converts a string to a number the same way the lua lexer reads numerals.
+/// Leading and trailing whitespace is ignored, and hexadecimal numbers are supported.
+/// 
+/// ```
+/// use aurora::data::{str_to_number, LuaData};
+/// 
+/// assert_eq!(Some(LuaData::Int(10)), str_to_number(" 10 "));
+/// assert_eq!(Some(LuaData::Int(255)), str_to_number("0xff"));
+/// assert_eq!(Some(LuaData::Number(150.0)), str_to_number("1.5e2"));
+/// assert_eq!(None, str_to_number("10 apples"));
+/// ```
+pub fn str_to_number(string: &str) -> Option<LuaData>{
+    let string = string.trim_matches(is_lua_space);
+
+    if let Some(x) = str_to_int(string){
+        return Some(LuaData::Int(x));
+    }
+
+    str_to_float(string).map(LuaData::Number)
+}
+
+fn is_lua_space(c: char) -> bool{
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0B' | '\x0C')
+}
+
+/// Splits the sign from a numeral, returning whether it was negative
+fn split_sign(string: &str) -> (bool, &str){
+    if let Some(rest) = string.strip_prefix('-'){
+        (true, rest)
+    }else if let Some(rest) = string.strip_prefix('+'){
+        (false, rest)
+    }else{
+        (false, string)
+    }
+}
+
+fn split_hex_prefix(string: &str) -> Option<&str>{
+    string.strip_prefix("0x").or_else(|| string.strip_prefix("0X"))
+}
+
+fn str_to_int(string: &str) -> Option<i64>{
+    let (negative, string) = split_sign(string);
+    let value = if let Some(digits) = split_hex_prefix(string){
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()){
+            return None;
+        }
+
+        // Hexadecimal integers wrap around on overflow
+        digits.chars().fold(0i64, |acc, c| acc.wrapping_mul(16).wrapping_add(i64::from(c.to_digit(16).unwrap())))
+    }else{
+        if string.is_empty() || !string.chars().all(|c| c.is_ascii_digit()){
+            return None;
+        }
+
+        // Decimal integers that overflow are read as floats instead
+        let value = string.parse::<u64>().ok()?;
+
+        if value > i64::MAX as u64 + if negative {1} else {0}{
+            return None;
+        }
+
+        value as i64
+    };
+
+    Some(if negative {value.wrapping_neg()} else {value})
+}
+
+fn str_to_float(string: &str) -> Option<f64>{
+    let (negative, string) = split_sign(string);
+    let value = match split_hex_prefix(string){
+        Some(digits) => hex_str_to_float(digits)?,
+        None => decimal_str_to_float(string)?,
+    };
+
+    Some(if negative {-value} else {value})
+}
+
+fn decimal_str_to_float(string: &str) -> Option<f64>{
+    let (mantissa, exponent) = match string.find(['e', 'E']){
+        Some(index) => (&string[..index], Some(&string[index + 1..])),
+        None => (string, None),
+    };
+
+    let mut had_digit = false;
+    let mut had_decimal = false;
+
+    for c in mantissa.chars(){
+        match c{
+            '0'..='9' => had_digit = true,
+            '.' if !had_decimal => had_decimal = true,
+            _ => return None,
+        }
+    }
+
+    if !had_digit{
+        return None;
+    }
+
+    if let Some(exponent) = exponent{
+        let digits = split_sign(exponent).1;
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()){
+            return None;
+        }
+    }
+
+    string.parse::<f64>().ok()
+}
+
+fn hex_str_to_float(string: &str) -> Option<f64>{
+    let (mantissa, exponent) = match string.find(['p', 'P']){
+        Some(index) => (&string[..index], Some(&string[index + 1..])),
+        None => (string, None),
+    };
+
+    let mut value = 0.0;
+    let mut exponent_offset: i64 = 0;
+    let mut had_digit = false;
+    let mut had_decimal = false;
+
+    for c in mantissa.chars(){
+        if c == '.' && !had_decimal{
+            had_decimal = true;
+            continue;
+        }
+
+        value = value * 16.0 + f64::from(c.to_digit(16)?);
+        had_digit = true;
+
+        if had_decimal{
+            exponent_offset -= 4;
+        }
+    }
+
+    if !had_digit{
+        return None;
+    }
+
+    let exponent = match exponent{
+        Some(exponent) => {
+            let (negative, digits) = split_sign(exponent);
+
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()){
+                return None;
+            }
+
+            let value = digits.parse::<i64>().unwrap_or(i64::MAX);
+
+            if negative {-value} else {value}
+        },
+        None => 0,
+    };
+
+    let exponent = exponent.saturating_add(exponent_offset).max(i64::from(i32::MIN)).min(i64::from(i32::MAX));
+
+    Some(value * 2f64.powi(exponent as i32))
+}
+
+/// Formats a float the same way lua does (using "%.14g"),
+/// floats that look like an integer get a '.0' suffix
+pub fn format_float(x: f64) -> String{
+    if x.is_nan(){
+        return if x.is_sign_negative() {"-nan"} else {"nan"}.to_string();
+    }
+
+    if x.is_infinite(){
+        return if x < 0.0 {"-inf"} else {"inf"}.to_string();
+    }
+
+    let string = format_general(x, 14);
+
+    if string.contains(['.', 'e']){
+        string
+    }else{
+        format!("{}.0", string)
+    }
+}
+
+/// Formats a finite float like C's "%.{precision}g"
+pub fn format_general(x: f64, precision: usize) -> String{
+    let precision = precision.max(1);
+
+    if x == 0.0{
+        return if x.is_sign_negative() {"-0"} else {"0"}.to_string();
+    }
+
+    // Let rust do the rounding, then read the exponent back out
+    let scientific = format!("{:.*e}", precision - 1, x);
+    let exponent_index = scientific.find('e').unwrap();
+    let exponent: i32 = scientific[exponent_index + 1..].parse().unwrap();
+
+    if exponent < -4 || exponent >= precision as i32{
+        let mantissa = strip_trailing_zeros(&scientific[..exponent_index]);
+        let sign = if exponent < 0 {'-'} else {'+'};
+
+        format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+    }else{
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+
+        strip_trailing_zeros(&format!("{:.*}", decimals, x)).to_string()
+    }
+}
+
+fn strip_trailing_zeros(string: &str) -> &str{
+    if string.contains('.'){
+        string.trim_end_matches('0').trim_end_matches('.')
+    }else{
+        string
+    }
+}
+
 impl fmt::Display for LuaData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.to_string())
@@ -55,12 +430,14 @@ impl fmt::Display for LuaData {
 #[cfg(test)]
 mod data_tests{
 
-    use super::LuaData;
+    use std::cmp::Ordering;
+    use super::{LuaData, str_to_number, format_float, compare_numbers};
 
     #[test]
     fn to_num_test(){
-        let start_vec = vec![LuaData::Number(5.0), LuaData::Nil, LuaData::Bool(false), LuaData::Bool(true)];
-        let expected_vec = vec![5.0, 0.0, 0.0, 1.0];    
+        let start_vec = vec![LuaData::Number(5.0), LuaData::Int(3), LuaData::Str(" 0x10 ".into()), LuaData::Nil, 
+            LuaData::Bool(false), LuaData::Bool(true), LuaData::Table(1), LuaData::Str("abc".into())];
+        let expected_vec = vec![Some(5.0), Some(3.0), Some(16.0), None, None, None, None, None];    
 
         for index in 0..start_vec.len(){            
             let actual = start_vec[index].clone();
@@ -70,11 +447,83 @@ mod data_tests{
         }
     }
 
+    #[test]
+    fn to_int_test(){
+        let start_vec = vec![LuaData::Int(-7), LuaData::Number(3.0), LuaData::Number(3.5), 
+            LuaData::Number(9_223_372_036_854_775_808.0), LuaData::Number(-9_223_372_036_854_775_808.0), 
+            LuaData::Number(f64::NAN), LuaData::Str("3".into()), LuaData::Nil];
+        let expected_vec = vec![Some(-7), Some(3), None, None, Some(i64::MIN), None, None, None];
+
+        for index in 0..start_vec.len(){
+            assert_eq!(start_vec[index].to_int(), expected_vec[index], "{:?} != {:?}", start_vec[index], expected_vec[index]);
+        }
+    }
+
+    #[test]
+    fn str_to_number_test(){
+        let start_vec = vec!["10", "  -7\t\n", "+3", "0x10", "0XfF", "-0x1", "1.5", ".5", "5.", "1e2", "2E-1", 
+            "0x.8", "0x1p4", "9223372036854775807", "9223372036854775808", "-9223372036854775808", "0xffffffffffffffff"];
+        let expected_vec = vec![LuaData::Int(10), LuaData::Int(-7), LuaData::Int(3), LuaData::Int(16), LuaData::Int(255), 
+            LuaData::Int(-1), LuaData::Number(1.5), LuaData::Number(0.5), LuaData::Number(5.0), LuaData::Number(100.0), 
+            LuaData::Number(0.2), LuaData::Number(0.5), LuaData::Number(16.0), LuaData::Int(i64::MAX), 
+            LuaData::Number(9_223_372_036_854_775_808.0), LuaData::Int(i64::MIN), LuaData::Int(-1)];
+
+        for index in 0..start_vec.len(){
+            assert_eq!(str_to_number(start_vec[index]), Some(expected_vec[index].clone()), "{:?} != {:?}", start_vec[index], expected_vec[index]);
+        }
+
+        for invalid in vec!["", " ", "abc", "1a", "0x", "1e", "1e+", "..", "1.2.3", "inf", "nan", "- 1", "0x1p"]{
+            assert_eq!(str_to_number(invalid), None, "{:?} should not be a number", invalid);
+        }
+    }
+
+    #[test]
+    fn format_float_test(){
+        let start_vec = vec![6.0, 0.2, -1.5, 1e15, 1e100, 123456789012345.0, 0.0001, 0.00001, 1.0 / 3.0, 
+            f64::INFINITY, f64::NEG_INFINITY, -0.0];
+        let expected_vec = vec!["6.0", "0.2", "-1.5", "1e+15", "1e+100", "1.2345678901234e+14", "0.0001", "1e-05", 
+            "0.33333333333333", "inf", "-inf", "-0.0"];
+
+        for index in 0..start_vec.len(){
+            assert_eq!(format_float(start_vec[index]), expected_vec[index]);
+        }
+    }
+
+    #[test]
+    fn raw_equals_test(){
+        let equal = vec![(LuaData::Int(1), LuaData::Number(1.0)), (LuaData::Nil, LuaData::Nil), (LuaData::Table(1), LuaData::Table(1)),
+            (LuaData::Str("a".into()), LuaData::Str("a".into())), (LuaData::Int(i64::MAX), LuaData::Int(i64::MAX))];
+        let not_equal = vec![(LuaData::Int(1), LuaData::Str("1".into())), (LuaData::Table(1), LuaData::Table(2)), 
+            (LuaData::Table(1), LuaData::Func(1)), (LuaData::Bool(false), LuaData::Nil), (LuaData::Number(f64::NAN), LuaData::Number(f64::NAN)),
+            (LuaData::Int(i64::MAX), LuaData::Number(9_223_372_036_854_775_808.0)), (LuaData::Int(2), LuaData::Number(2.5))];
+
+        for (left, right) in equal{
+            assert!(left.raw_equals(&right), "{:?} != {:?}", left, right);
+        }
+
+        for (left, right) in not_equal{
+            assert!(!left.raw_equals(&right), "{:?} == {:?}", left, right);
+        }
+    }
+
+    #[test]
+    fn compare_numbers_test(){
+        assert_eq!(compare_numbers(&LuaData::Int(2), &LuaData::Number(2.5)), Some(Ordering::Less));
+        assert_eq!(compare_numbers(&LuaData::Number(-2.5), &LuaData::Int(-2)), Some(Ordering::Less));
+        assert_eq!(compare_numbers(&LuaData::Int(3), &LuaData::Number(2.5)), Some(Ordering::Greater));
+        assert_eq!(compare_numbers(&LuaData::Int(i64::MAX), &LuaData::Number(9_223_372_036_854_775_808.0)), Some(Ordering::Less));
+        assert_eq!(compare_numbers(&LuaData::Int(i64::MIN), &LuaData::Number(-1e300)), Some(Ordering::Greater));
+        assert_eq!(compare_numbers(&LuaData::Int(1), &LuaData::Number(f64::NAN)), None);
+        assert_eq!(compare_numbers(&LuaData::Int(1), &LuaData::Str("1".into())), None);
+    }
+
     #[test]
     fn to_str_test(){
-        let start_vec = vec![LuaData::Str("foo".to_string()), LuaData::Bool(true), 
-            LuaData::Bool(false), LuaData::Number(12.34), LuaData::Nil];
-        let expected_vec: Vec<String> = vec!["foo", "true", "false", "12.34", "nil"]
+        let start_vec = vec![LuaData::Str("foo".into()), LuaData::Bool(true), 
+            LuaData::Bool(false), LuaData::Number(12.34), LuaData::Int(12), LuaData::Number(12.0), LuaData::Nil,
+            LuaData::Table(26), LuaData::Func(3)];
+        let expected_vec: Vec<String> = vec!["foo", "true", "false", "12.34", "12", "12.0", "nil",
+            "table: 0x0000001a", "function: 0x00000003"]
             .iter().map(|x| x.to_string()).collect();    
 
         for index in 0..start_vec.len(){            
@@ -87,8 +536,8 @@ mod data_tests{
 
     #[test]
     fn to_bool_test(){
-        let start_vec = vec![LuaData::Bool(false), LuaData::Nil, LuaData::Str("false".to_string()), LuaData::Number(0.0), 
-            LuaData::Str("foo".to_string()), LuaData::Bool(true)];
+        let start_vec = vec![LuaData::Bool(false), LuaData::Nil, LuaData::Str("false".into()), LuaData::Number(0.0), 
+            LuaData::Str("foo".into()), LuaData::Bool(true)];
         let expected_vec = vec![false, false, false, true, true, true];   
 
         for index in 0..start_vec.len(){            
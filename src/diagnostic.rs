@@ -0,0 +1,163 @@
+use super::Span;
+use super::error::{LuaError, ErrorType};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// How many spaces a tab in a source line takes up in a snippet
+const TAB_WIDTH: usize = 4;
+
+/// Renders errors with the source they point at, like:
+///
+/// ```text
+/// error: attempt to call a nil value (global 'pritn')
+///  --> assets/game.lua:3:1
+///   |
+/// 3 | pritn("hello")
+///   | ^^^^^^^^^^^^^^
+/// ```
+pub struct Renderer{
+    color: bool,
+}
+
+/// A span that is underlined in a snippet, the primary one with '^' and labels with '-'
+struct Annotation<'a>{
+    span: &'a Span,
+    label: Option<&'a str>,
+    primary: bool,
+}
+
+impl Renderer{
+
+    pub fn new(color: bool) -> Renderer{
+        Renderer {color}
+    }
+
+    /// Renders an error, the source is the code of the chunk the error happened in.
+    /// Without the source only the message and position are shown
+    pub fn render(&self, error: &LuaError, source: Option<&str>) -> String{
+        let title = match error.error_type(){
+            ErrorType::Lexical | ErrorType::Parse => "syntax error",
+            ErrorType::Runtime => "error",
+        };
+        let mut out = format!("{}: {}\n", self.paint(RED, title), self.paint(BOLD, &error.message));
+
+        if let Some(span) = error.span(){
+            let mut annotations = vec![Annotation {span, label: None, primary: true}];
+            annotations.extend(error.labels().iter()
+                .filter(|(label_span, _)| label_span.chunk == span.chunk && label_span.start_line > 0)
+                .map(|(span, label)| Annotation {span, label: Some(label), primary: false}));
+
+            let width = annotations.iter().map(|a| a.span.end_line.max(a.span.start_line)).max().unwrap_or(0).to_string().len();
+            let gutter = " ".repeat(width);
+
+            if span.start_line == 0{
+                out.push_str(&format!("{}{} {}\n", gutter, self.paint(BLUE, "-->"), span.chunk));
+            }else{
+                out.push_str(&format!("{}{} {}:{}:{}\n", gutter, self.paint(BLUE, "-->"), span.chunk, span.start_line, span.start_column));
+            }
+
+            if let (Some(source), true) = (source, span.start_line > 0){
+                out.push_str(&format!("{} {}\n", gutter, self.paint(BLUE, "|")));
+                out.push_str(&self.snippet(source, &annotations, width));
+            }
+
+            for note in error.notes(){
+                out.push_str(&format!("{} {} note: {}\n", gutter, self.paint(BLUE, "="), note));
+            }
+        }else{
+            for note in error.notes(){
+                out.push_str(&format!("{} note: {}\n", self.paint(BLUE, "="), note));
+            }
+        }
+
+        if let Some(traceback) = error.traceback(){
+            out.push_str(traceback);
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// The lines touched by the annotations, each followed by the underlines on it
+    fn snippet(&self, source: &str, annotations: &[Annotation], width: usize) -> String{
+        let lines: Vec<&str> = source.lines().collect();
+        let mut line_numbers: Vec<usize> = annotations.iter()
+            .flat_map(|a| a.span.start_line..=a.span.end_line.max(a.span.start_line))
+            .collect();
+        line_numbers.sort();
+        line_numbers.dedup();
+
+        let mut out = String::new();
+        let mut previous = None;
+
+        for number in line_numbers{
+            let line = match lines.get(number - 1){
+                Some(x) => x.trim_end(),
+                None => "",
+            };
+
+            if let Some(previous) = previous{
+                if number > previous + 1{
+                    out.push_str(&format!("{}\n", self.paint(BLUE, "...")));
+                }
+            }
+            previous = Some(number);
+
+            let number_text = self.paint(BLUE, &format!("{:>1$}", number, width));
+            out.push_str(format!("{} {} {}", number_text, self.paint(BLUE, "|"), line.replace('\t', &" ".repeat(TAB_WIDTH))).trim_end());
+            out.push('\n');
+
+            for annotation in annotations{
+                if let Some(underline) = self.underline(line, number, annotation){
+                    out.push_str(&format!("{} {} {}\n", " ".repeat(width), self.paint(BLUE, "|"), underline));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// The underline of an annotation on one line, the label is shown on the last line of the span.
+    /// Columns count tabs as one character, so they are moved to where the expanded tabs put them
+    fn underline(&self, line: &str, number: usize, annotation: &Annotation) -> Option<String>{
+        let span = annotation.span;
+        let end_line = span.end_line.max(span.start_line);
+
+        if number < span.start_line || number > end_line{
+            return None;
+        }
+
+        let length = line.chars().count();
+        let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+        let start = if number == span.start_line {span.start_column.max(1) - 1} else {indent};
+        let end = if number == end_line {span.end_column.max(start + 1)} else {length.max(start + 1)};
+
+        let (start, end) = (display_column(line, start), display_column(line, end));
+        let (marker, color) = if annotation.primary {("^", RED)} else {("-", BLUE)};
+        let mut underline = self.paint(color, &marker.repeat(end - start));
+
+        if let (Some(label), true) = (annotation.label, number == end_line){
+            underline = format!("{} {}", underline, self.paint(color, label));
+        }
+
+        Some(format!("{}{}", " ".repeat(start), underline))
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String{
+        if self.color{
+            format!("{}{}{}", color, text, RESET)
+        }else{
+            text.to_string()
+        }
+    }
+}
+
+/// The width of the first characters of a line once its tabs are expanded
+fn display_column(line: &str, column: usize) -> usize{
+    let chars = line.chars().take(column);
+
+    chars.map(|c| if c == '\t' {TAB_WIDTH} else {1}).sum::<usize>() + column.saturating_sub(line.chars().count())
+}
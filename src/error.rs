@@ -1,5 +1,8 @@
 
 use std::fmt;
+use std::error::Error;
+use super::data::LuaData;
+use super::Span;
 
 #[derive(Debug)]
 pub enum ErrorType{
@@ -8,22 +11,70 @@ pub enum ErrorType{
     Runtime,
 }
 
+/// What went wrong, hosts can match on this instead of the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind{
+    SyntaxError(SyntaxErrorKind),
+    /// A nil variable was called, indexed or used as an operand
+    UndefinedVariable,
+    /// A value of the wrong type was used, i.e. arithmetic on a table or a bad argument
+    TypeMismatch,
+    /// A function got too few or too many arguments
+    ArityMismatch,
+    /// A file couldn't be opened or read
+    IoError,
+    /// A value raised by 'error'
+    Thrown,
+    /// The script called 'os.exit' with the code
+    Exit(i32),
+    /// Any other runtime error
+    Runtime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxErrorKind{
+    /// A character or number the scanner doesn't understand
+    InvalidToken,
+    UnexpectedToken,
+    /// A block without its 'end'
+    UnclosedBlock,
+    /// A statement that isn't allowed where it is, i.e. 'break' outside of a loop
+    InvalidStatement,
+}
+
 #[derive(Debug)]
 pub struct LuaError{
+    pub message: String,
+    /// Set once the message handler of 'xpcall' has been called for the error
+    pub(crate) handled: bool,
+    /// Boxed so results that carry an error stay small
+    details: Box<ErrorDetails>,
+}
+
+#[derive(Debug)]
+struct ErrorDetails{
     error_type: ErrorType,
-    location: Option<String>,
-    pub message: String
+    kind: ErrorKind,
+    span: Option<Span>,
+    /// The value thrown by 'error', None for errors raised by the interpreter
+    value: Option<LuaData>,
+    /// Other places in the source that help explain the error
+    labels: Vec<(Span, String)>,
+    notes: Vec<String>,
+    traceback: Option<String>,
+    /// The error that caused this one, i.e. the io error of a file that couldn't be read
+    source: Option<Box<dyn Error + Send + Sync>>,
 }
 
 impl fmt::Display for LuaError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let location = if let Some(ref x) = self.location{
-            format!(" at {}", x)
+        let location = if let Some(ref x) = self.details.span{
+            format!(" at {}", x.location())
         }else{
             String::new()
         };
 
-        match self.error_type{
+        match self.details.error_type{
             ErrorType::Lexical => write!(f, "[Lexical Exception{}] {}", location, self.message),
             ErrorType::Parse => write!(f, "[Parse Exception{}] {}", location, self.message),
             ErrorType::Runtime => write!(f, "[Runtime Exception{}] {}", location, self.message),
@@ -31,22 +82,131 @@ impl fmt::Display for LuaError {
     }
 }
 
+impl Error for LuaError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        self.details.source.as_ref().map(|e| e.as_ref() as &(dyn Error + 'static))
+    }
+}
+
 impl LuaError{
 
-    pub fn create(message: &str, error_type: ErrorType, location: Option<String>) -> LuaError{
-        LuaError {error_type, message: message.to_string(), location}
+    pub fn create(message: &str, error_type: ErrorType, span: Option<Span>) -> LuaError{
+        let kind = match error_type{
+            ErrorType::Lexical => ErrorKind::SyntaxError(SyntaxErrorKind::InvalidToken),
+            ErrorType::Parse => ErrorKind::SyntaxError(SyntaxErrorKind::UnexpectedToken),
+            ErrorType::Runtime => ErrorKind::Runtime,
+        };
+
+        LuaError {message: message.to_string(), handled: false, details: Box::new(ErrorDetails {error_type, kind, span, value: None,
+            labels: Vec::new(), notes: Vec::new(), traceback: None, source: None})}
+    }
+
+    /// Creates an error that carries a lua value, the position is part of the value
+    pub fn create_value(value: LuaData) -> LuaError{
+        let message = match value{
+            LuaData::Str(_) | LuaData::Int(_) | LuaData::Number(_) => value.to_string(),
+            ref x => format!("(error object is a {} value)", x.type_name()),
+        };
+
+        let mut error = LuaError::create(&message, ErrorType::Runtime, None).with_kind(ErrorKind::Thrown);
+        error.details.value = Some(value);
+        error
+    }
+
+    /// The lua value of the error. Errors raised by the interpreter
+    /// are converted to a string that starts with their position
+    pub fn value(&self) -> LuaData{
+        match (&self.details.value, &self.details.span){
+            (Some(value), _) => value.clone(),
+            (None, Some(span)) => LuaData::Str(format!("{}: {}", span.location(), self.message).into_bytes()),
+            (None, None) => LuaData::Str(self.message.clone().into_bytes()),
+        }
+    }
+
+    /// Replaces the error with a lua value, the kind and traceback are kept
+    pub fn set_value(&mut self, value: LuaData){
+        let traceback = self.details.traceback.take();
+        *self = LuaError{handled: self.handled, ..LuaError::create_value(value).with_kind(self.details.kind)};
+        self.details.traceback = traceback;
+    }
+
+    pub fn create_lexical(message: &str, span: Option<Span>) -> LuaError{
+        LuaError::create(message, ErrorType::Lexical, span)
+    }
+
+    pub fn create_parse(message: &str, span: Option<Span>) -> LuaError{
+        LuaError::create(message, ErrorType::Parse, span)
+    }
+
+    pub fn create_runtime(message: &str, span: Option<Span>) -> LuaError{
+        LuaError::create(message, ErrorType::Runtime, span)
+    }
+
+    pub fn with_kind(mut self, kind: ErrorKind) -> LuaError{
+        self.details.kind = kind;
+        self
+    }
+
+    /// Sets the error that caused this one, returned by 'source'
+    pub fn with_source<E: Error + Send + Sync + 'static>(mut self, source: E) -> LuaError{
+        self.details.source = Some(Box::new(source));
+        self
+    }
+
+    /// Adds a secondary label that points at another part of the source
+    pub fn with_label(mut self, span: Span, label: &str) -> LuaError{
+        self.details.labels.push((span, label.to_string()));
+        self
+    }
+
+    /// Adds a note that is shown below the source snippet
+    pub fn with_note(mut self, note: &str) -> LuaError{
+        self.details.notes.push(note.to_string());
+        self
+    }
+
+    pub fn error_type(&self) -> &ErrorType{
+        &self.details.error_type
+    }
+
+    pub fn kind(&self) -> ErrorKind{
+        self.details.kind
+    }
+
+    pub fn is_syntax_error(&self) -> bool{
+        matches!(self.details.kind, ErrorKind::SyntaxError(_))
+    }
+
+    /// Where the error happened, None for errors thrown with a lua value
+    pub fn span(&self) -> Option<&Span>{
+        self.details.span.as_ref()
+    }
+
+    /// The name of the chunk the error happened in, i.e. the file name
+    pub fn chunk(&self) -> Option<&str>{
+        self.details.span.as_ref().map(|span| span.chunk.as_str())
+    }
+
+    /// The lua value that was raised with 'error', None for errors raised by the interpreter
+    pub fn lua_value(&self) -> Option<&LuaData>{
+        self.details.value.as_ref()
+    }
+
+    /// The call stack when the error happened, if the interpreter recorded it
+    pub fn traceback(&self) -> Option<&str>{
+        self.details.traceback.as_deref()
     }
 
-    pub fn create_lexical(message: &str, location: Option<String>) -> LuaError{
-        LuaError::create(message, ErrorType::Lexical, location)
+    pub fn set_traceback(&mut self, traceback: String){
+        self.details.traceback = Some(traceback);
     }
 
-    pub fn create_parse(message: &str, location: Option<String>) -> LuaError{
-        LuaError::create(message, ErrorType::Parse, location)
+    pub fn labels(&self) -> &[(Span, String)]{
+        &self.details.labels
     }
 
-    pub fn create_runtime(message: &str, location: Option<String>) -> LuaError{
-        LuaError::create(message, ErrorType::Runtime, location)
+    pub fn notes(&self) -> &[String]{
+        &self.details.notes
     }
 
 }
\ No newline at end of file
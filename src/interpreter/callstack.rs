@@ -0,0 +1,113 @@
+use super::Interpreter;
+use super::super::{Span, error::LuaError};
+
+/// A function call that is in progress
+pub(super) struct Call{
+    /// The name the function was registered with, None for the main chunk of a module
+    pub name: Option<String>,
+    pub is_rust: bool,
+    /// Where the function was called from
+    pub call_site: Option<Span>,
+    /// The table that holds the globals of the function, None for the global variables
+    pub env: Option<i64>,
+}
+
+/// A frame of the call stack as shown in tracebacks
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame{
+    /// The name of the function, None for a main chunk
+    pub name: Option<String>,
+    /// The chunk that is running, "[C]" for rust functions
+    pub chunk: String,
+    /// The line that is running, 0 for rust functions and unknown positions
+    pub line: usize,
+    pub is_rust: bool,
+}
+
+impl CallFrame{
+
+    fn describe(&self) -> String{
+        let location = match (self.is_rust, self.line){
+            (true, _) => "[C]".to_string(),
+            (false, 0) => self.chunk.clone(),
+            (false, line) => format!("{}:{}", self.chunk, line),
+        };
+
+        match self.name{
+            Some(ref name) => format!("{}: in function '{}'", location, name),
+            None => format!("{}: in main chunk", location),
+        }
+    }
+}
+
+impl Interpreter{
+
+    /// The frames of the call stack, the innermost call first and the main chunk last
+    pub fn call_stack(&self) -> Vec<CallFrame>{
+        let mut frames = Vec::new();
+        // The position each call is at is where the call above it was made
+        let mut position = self.current_stmt_location.as_ref();
+
+        for call in self.calls.iter().rev(){
+            frames.push(frame(call.name.clone(), call.is_rust, position));
+            position = call.call_site.as_ref();
+        }
+
+        frames.push(frame(None, false, position));
+
+        frames
+    }
+
+    /// The current call stack, i.e. "stack traceback:\n\t[C]: in function 'error'\n\tmain:3: in main chunk"
+    pub fn traceback(&self) -> String{
+        self.traceback_from(0)
+    }
+
+    /// The call stack without the innermost frames up to the level
+    pub fn traceback_from(&self, level: usize) -> String{
+        let mut traceback = "stack traceback:".to_string();
+
+        for frame in self.call_stack().iter().skip(level){
+            traceback.push_str("\n\t");
+            traceback.push_str(&frame.describe());
+        }
+
+        traceback
+    }
+
+    /// Records the call stack in a runtime error that doesn't have one yet.
+    /// Called before the frames are removed, so the innermost frame is where the error happened
+    pub fn attach_traceback(&self, mut error: LuaError) -> LuaError{
+        if error.traceback().is_none() && !error.is_syntax_error(){
+            error.set_traceback(self.traceback());
+        }
+
+        error
+    }
+
+    pub(super) fn push_call(&mut self, name: Option<String>, is_rust: bool, env: Option<i64>){
+        let call_site = self.current_stmt_location.clone();
+
+        self.calls.push(Call{name, is_rust, call_site, env});
+    }
+
+    /// The table that holds the globals of the running function, see 'load'
+    pub(super) fn env(&self) -> Option<i64>{
+        self.calls.last().and_then(|call| call.env)
+    }
+
+    /// Removes the innermost call and goes back to the position it was called from
+    pub(super) fn pop_call(&mut self){
+        if let Some(call) = self.calls.pop(){
+            self.current_stmt_location = call.call_site;
+        }
+    }
+}
+
+fn frame(name: Option<String>, is_rust: bool, position: Option<&Span>) -> CallFrame{
+    match position{
+        Some(span) if !is_rust => CallFrame{name, chunk: span.chunk.clone(), line: span.start_line, is_rust},
+        None if !is_rust => CallFrame{name, chunk: "?".to_string(), line: 0, is_rust},
+        _ => CallFrame{name, chunk: "[C]".to_string(), line: 0, is_rust},
+    }
+}
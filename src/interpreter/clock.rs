@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::env;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Where the os library gets the time from. Hosts can replace it through 'Config' to freeze time.
+/// It is Send so that the interpreter can move to the thread of a coroutine
+pub trait Clock: Send{
+    /// Seconds since the unix epoch, returned by 'os.time'
+    fn now(&self) -> i64;
+
+    /// Seconds of processor time used by the script, returned by 'os.clock'
+    fn cpu_time(&self) -> f64;
+
+    /// How many seconds local time is ahead of UTC at a time, used by 'os.date' and 'os.time'
+    fn utc_offset(&self, _time: i64) -> i64{
+        0
+    }
+}
+
+/// The clock of the machine. The standard library can't read the local timezone or the
+/// processor time, so local time is UTC and 'os.clock' counts the seconds since the clock was created
+pub struct SystemClock{
+    start: Instant,
+}
+
+impl SystemClock{
+
+    pub fn new() -> SystemClock{
+        SystemClock {start: Instant::now()}
+    }
+}
+
+impl Default for SystemClock{
+    fn default() -> SystemClock{
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock{
+
+    fn now(&self) -> i64{
+        match SystemTime::now().duration_since(UNIX_EPOCH){
+            Ok(x) => x.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        }
+    }
+
+    fn cpu_time(&self) -> f64{
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+/// A clock that always returns the same time, for tests and replays
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedClock{
+    pub time: i64,
+    pub cpu_time: f64,
+    /// Seconds local time is ahead of UTC
+    pub utc_offset: i64,
+}
+
+impl FixedClock{
+
+    pub fn new(time: i64) -> FixedClock{
+        FixedClock {time, cpu_time: 0.0, utc_offset: 0}
+    }
+}
+
+impl Clock for FixedClock{
+
+    fn now(&self) -> i64{
+        self.time
+    }
+
+    fn cpu_time(&self) -> f64{
+        self.cpu_time
+    }
+
+    fn utc_offset(&self, _time: i64) -> i64{
+        self.utc_offset
+    }
+}
+
+/// Where 'os.getenv' reads variables from, hosts can replace it through 'Config'
+pub trait Environment: Send{
+    fn var(&self, name: &str) -> Option<String>;
+}
+
+/// The environment variables of the process
+pub struct SystemEnvironment{}
+
+impl Environment for SystemEnvironment{
+
+    fn var(&self, name: &str) -> Option<String>{
+        env::var(name).ok()
+    }
+}
+
+impl Environment for HashMap<String, String>{
+
+    fn var(&self, name: &str) -> Option<String>{
+        self.get(name).cloned()
+    }
+}
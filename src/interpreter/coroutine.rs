@@ -0,0 +1,367 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread::{self, JoinHandle};
+
+use super::Interpreter;
+use super::callstack::Call;
+use super::super::{Span, data::{LuaData, ObjectRef}, error::LuaError};
+
+/// Stack size of the threads that coroutines run on
+const COROUTINE_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// The channels a coroutine uses to yield to the thread that resumed it
+type YieldChannels = (Sender<Transfer>, Receiver<Resume>);
+
+/// The arguments a coroutine is resumed with, and the interpreter it runs on until it yields
+type Resume = (Vec<LuaData>, Box<Interpreter>);
+
+thread_local!{
+    /// The channels of the coroutine that runs on this thread
+    static YIELD_CHANNELS: RefCell<Option<YieldChannels>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoroutineStatus{
+    Suspended,
+    Running,
+    /// Active but not running, because it resumed another coroutine
+    Normal,
+    Dead,
+}
+
+impl CoroutineStatus{
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            CoroutineStatus::Suspended => "suspended",
+            CoroutineStatus::Running => "running",
+            CoroutineStatus::Normal => "normal",
+            CoroutineStatus::Dead => "dead",
+        }
+    }
+}
+
+/// What a coroutine sends back to the thread that resumed it, always with the interpreter
+enum Transfer{
+    Yield(Vec<LuaData>, Box<Interpreter>),
+    Return(Result<Vec<LuaData>, LuaError>, Box<Interpreter>),
+    Panic(Box<dyn Any + Send>, Box<Interpreter>),
+}
+
+/// Unwinds the thread of a coroutine that will never be resumed again
+struct CoroutineKilled;
+
+/// A coroutine runs on its own thread so that it can be suspended in the middle of nested calls.
+/// The interpreter is moved to the thread of the coroutine when it is resumed and back when it
+/// yields or returns, so only one thread has it at a time. Host functions called by a coroutine
+/// run on its thread, so they see the thread-local state of that thread
+pub struct Coroutine{
+    pub status: CoroutineStatus,
+    func: LuaData,
+    /// The scopes of the coroutine while it isn't running
+    stack: Vec<HashMap<String, LuaData>>,
+    return_val: Option<Vec<LuaData>>,
+    location: Option<Span>,
+    error_handlers: Vec<Option<LuaData>>,
+    calls: Vec<Call>,
+    to_be_closed: Vec<LuaData>,
+    breaking: bool,
+    /// Channels to the thread, None until the coroutine is first resumed.
+    /// Dropping them makes the thread of a suspended coroutine exit
+    channels: Option<(Sender<Resume>, Receiver<Transfer>)>,
+    thread: Option<JoinHandle<()>>,
+    /// The error value of a coroutine that died from an error
+    error: Option<LuaData>,
+}
+
+impl Coroutine{
+
+    /// Makes the thread of a suspended coroutine unwind and waits for it to exit
+    fn end_thread(&mut self){
+        self.channels = None;
+
+        if let Some(thread) = self.thread.take(){
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Coroutine{
+
+    fn drop(&mut self){
+        self.end_thread();
+    }
+}
+
+impl Interpreter{
+
+    /// Creates a suspended coroutine. It is ended and removed once the returned reference
+    /// and all of its copies are dropped
+    pub fn create_coroutine(&mut self, func: LuaData) -> ObjectRef{
+        self.collect_coroutines();
+
+        let id = self.coroutine_count;
+        self.coroutine_count += 1;
+
+        self.coroutines.insert(id, Coroutine{
+            status: CoroutineStatus::Suspended,
+            func,
+            stack: vec![HashMap::new()],
+            return_val: None,
+            location: None,
+            error_handlers: Vec::new(),
+            calls: Vec::new(),
+            to_be_closed: Vec::new(),
+            breaking: false,
+            channels: None,
+            thread: None,
+            error: None,
+        });
+
+        ObjectRef::new(id, self.released_coroutines.clone())
+    }
+
+    pub fn get_coroutine(&self, id: i64) -> Option<&Coroutine>{
+        self.coroutines.get(&id)
+    }
+
+    /// The number of coroutines that haven't been removed yet
+    pub fn live_coroutines(&self) -> usize{
+        self.coroutines.len()
+    }
+
+    /// The coroutine that is running, None on the main thread
+    pub fn current_coroutine(&self) -> Option<i64>{
+        self.current_coroutine
+    }
+
+    /// Starts or continues a coroutine. Returns the values it yielded or returned,
+    /// or the error value if the coroutine can't be resumed or raised an error
+    pub fn resume(&mut self, id: i64, args: Vec<LuaData>) -> Result<Vec<LuaData>, LuaData>{
+        match self.coroutines[&id].status{
+            CoroutineStatus::Suspended => (),
+            CoroutineStatus::Dead => return Err(LuaData::Str("cannot resume dead coroutine".into())),
+            _ => return Err(LuaData::Str("cannot resume non-suspended coroutine".into())),
+        }
+
+        let (sender, receiver) = match self.coroutines.get_mut(&id).unwrap().channels.take(){
+            Some(x) => x,
+            None => match self.spawn_coroutine(id){
+                Ok(x) => x,
+                Err(e) => {
+                    self.coroutines.get_mut(&id).unwrap().status = CoroutineStatus::Dead;
+                    return Err(e);
+                },
+            },
+        };
+
+        let previous = self.current_coroutine.replace(id);
+        self.set_coroutine_status(previous, CoroutineStatus::Normal);
+        self.set_coroutine_status(Some(id), CoroutineStatus::Running);
+        self.swap_execution_state(id);
+
+        // The coroutine has the interpreter until it yields or returns
+        let interpreter = self.take_interpreter();
+        let transfer = match sender.send((args, interpreter)){
+            Ok(()) => Some(receiver.recv().expect("coroutine threads always send the interpreter back")),
+            Err(mpsc::SendError((_, interpreter))) => {
+                self.restore_interpreter(interpreter);
+                None
+            },
+        };
+
+        let result = match transfer{
+            Some(Transfer::Yield(values, interpreter)) => {
+                self.restore_interpreter(interpreter);
+                Ok(Ok(values))
+            },
+            Some(Transfer::Return(result, interpreter)) => {
+                self.restore_interpreter(interpreter);
+                result.map(Err).map_err(|e| e.value())
+            },
+            Some(Transfer::Panic(payload, interpreter)) => {
+                self.restore_interpreter(interpreter);
+                panic::resume_unwind(payload)
+            },
+            None => Err(LuaData::Str("cannot resume dead coroutine".into())),
+        };
+
+        self.swap_execution_state(id);
+        self.current_coroutine = previous;
+        self.set_coroutine_status(previous, CoroutineStatus::Running);
+
+        let coroutine = self.coroutines.get_mut(&id).unwrap();
+        coroutine.status = CoroutineStatus::Dead;
+
+        let result = match result{
+            Ok(Ok(values)) => {
+                coroutine.status = CoroutineStatus::Suspended;
+                coroutine.channels = Some((sender, receiver));
+
+                Ok(values)
+            },
+            Ok(Err(values)) => Ok(values),
+            Err(error) => {
+                coroutine.error = Some(error.clone());
+
+                Err(error)
+            },
+        };
+
+        self.collect_coroutines();
+
+        result
+    }
+
+    /// Suspends the running coroutine, returns the values it is resumed with
+    pub fn yield_coroutine(&mut self, values: Vec<LuaData>) -> Result<Vec<LuaData>, LuaError>{
+        let resumed = YIELD_CHANNELS.with(|channels| {
+            let channels = channels.borrow();
+            let (sender, receiver) = channels.as_ref()?;
+
+            let interpreter = self.take_interpreter();
+            if let Err(mpsc::SendError(Transfer::Yield(_, interpreter))) = sender.send(Transfer::Yield(values, interpreter)){
+                self.restore_interpreter(interpreter);
+                return Some(None);
+            }
+
+            Some(receiver.recv().ok())
+        });
+
+        match resumed{
+            Some(Some((values, interpreter))) => {
+                self.restore_interpreter(interpreter);
+                Ok(values)
+            },
+            Some(None) => panic::resume_unwind(Box::new(CoroutineKilled)),
+            None => Err(self.error("attempt to yield from outside a coroutine".to_string())),
+        }
+    }
+
+    /// Kills a suspended coroutine and closes its pending '<close>' variables.
+    /// Returns the error value of a coroutine that died from an error
+    pub fn close_coroutine(&mut self, id: i64) -> Result<Option<LuaData>, LuaError>{
+        let coroutine = self.coroutines.get_mut(&id).unwrap();
+
+        match coroutine.status{
+            CoroutineStatus::Suspended | CoroutineStatus::Dead => {
+                coroutine.status = CoroutineStatus::Dead;
+                coroutine.end_thread();
+
+                let mut error = coroutine.error.take();
+                let to_be_closed = mem::take(&mut coroutine.to_be_closed);
+
+                for value in to_be_closed.iter().rev(){
+                    let value_error = error.clone().unwrap_or(LuaData::Nil);
+
+                    if let Err(e) = self.close_value(value, value_error){
+                        error = Some(e.value());
+                    }
+                }
+
+                Ok(error)
+            },
+            x => Err(self.error(format!("cannot close a {} coroutine", x.name()))),
+        }
+    }
+
+    /// Ends and removes the coroutines that nothing refers to anymore. Ending a coroutine can
+    /// release others that only it referred to. Active coroutines are kept until they stop
+    fn collect_coroutines(&mut self){
+        let mut active = Vec::new();
+
+        loop{
+            let released = mem::take(&mut *self.released_coroutines.lock().unwrap_or_else(|e| e.into_inner()));
+            if released.is_empty(){
+                break;
+            }
+
+            for id in released{
+                match self.coroutines.get(&id).map(|x| x.status){
+                    Some(CoroutineStatus::Running) | Some(CoroutineStatus::Normal) => active.push(id),
+                    _ => {
+                        self.coroutines.remove(&id);
+                    },
+                }
+            }
+        }
+
+        self.released_coroutines.lock().unwrap_or_else(|e| e.into_inner()).extend(active);
+    }
+
+    /// Moves the interpreter out so it can be sent to another thread, leaving the placeholder
+    /// in its place. The placeholder is only built on the first switch and reused after that
+    fn take_interpreter(&mut self) -> Box<Interpreter>{
+        let mut interpreter = self.placeholder.take().unwrap_or_else(|| Box::new(Interpreter::new()));
+        mem::swap(self, &mut *interpreter);
+
+        interpreter
+    }
+
+    /// Moves the interpreter back in and keeps the placeholder for the next switch
+    fn restore_interpreter(&mut self, mut interpreter: Box<Interpreter>){
+        mem::swap(self, &mut *interpreter);
+        self.placeholder = Some(interpreter);
+    }
+
+    /// Starts the thread of a coroutine, it waits for the first resume
+    fn spawn_coroutine(&mut self, id: i64) -> Result<(Sender<Resume>, Receiver<Transfer>), LuaData>{
+        let (resume_sender, resume_receiver) = mpsc::channel::<Resume>();
+        let (yield_sender, yield_receiver) = mpsc::channel();
+        let func = self.coroutines[&id].func.clone();
+
+        let result = thread::Builder::new()
+            .name(format!("coroutine {}", id))
+            .stack_size(COROUTINE_STACK_SIZE)
+            .spawn(move || {
+                let (args, mut interpreter) = match resume_receiver.recv(){
+                    Ok(x) => x,
+                    Err(_) => return,
+                };
+
+                YIELD_CHANNELS.with(|channels| *channels.borrow_mut() = Some((yield_sender.clone(), resume_receiver)));
+
+                let result = panic::catch_unwind(AssertUnwindSafe(|| interpreter.call(&func, args)));
+
+                let transfer = match result{
+                    Ok(result) => Transfer::Return(result, interpreter),
+                    Err(ref payload) if payload.is::<CoroutineKilled>() => return,
+                    Err(payload) => Transfer::Panic(payload, interpreter),
+                };
+
+                let _ = yield_sender.send(transfer);
+            });
+
+        match result{
+            Ok(thread) => {
+                self.coroutines.get_mut(&id).unwrap().thread = Some(thread);
+
+                Ok((resume_sender, yield_receiver))
+            },
+            Err(e) => Err(LuaData::Str(format!("unable to create coroutine thread: {}", e).into_bytes())),
+        }
+    }
+
+    /// Swaps the scopes of the interpreter with those of a coroutine
+    fn swap_execution_state(&mut self, id: i64){
+        let coroutine = self.coroutines.get_mut(&id).unwrap();
+
+        mem::swap(&mut self.stack, &mut coroutine.stack);
+        mem::swap(&mut self.return_val, &mut coroutine.return_val);
+        mem::swap(&mut self.current_stmt_location, &mut coroutine.location);
+        mem::swap(&mut self.error_handlers, &mut coroutine.error_handlers);
+        mem::swap(&mut self.calls, &mut coroutine.calls);
+        mem::swap(&mut self.to_be_closed, &mut coroutine.to_be_closed);
+        mem::swap(&mut self.breaking, &mut coroutine.breaking);
+    }
+
+    fn set_coroutine_status(&mut self, id: Option<i64>, status: CoroutineStatus){
+        if let Some(coroutine) = id.and_then(|id| self.coroutines.get_mut(&id)){
+            coroutine.status = status;
+        }
+    }
+}
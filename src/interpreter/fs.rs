@@ -0,0 +1,92 @@
+use std::fs;
+use std::io::{self, Read, Write, Seek};
+
+/// How a file is opened, the modes of 'io.open' map to these
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenOptions{
+    pub read: bool,
+    pub write: bool,
+    /// Writes always go to the end of the file
+    pub append: bool,
+    /// The file is emptied when it is opened
+    pub truncate: bool,
+    /// The file is created if it doesn't exist
+    pub create: bool,
+}
+
+impl OpenOptions{
+
+    /// Options for reading an existing file
+    pub fn read() -> OpenOptions{
+        OpenOptions {read: true, ..OpenOptions::default()}
+    }
+
+    /// Converts an 'io.open' mode such as "r", "w+" or "ab", None if the mode is invalid
+    pub fn from_mode(mode: &str) -> Option<OpenOptions>{
+        let mode = mode.strip_suffix('b').unwrap_or(mode);
+        let (base, update) = match mode.strip_suffix('+'){
+            Some(base) => (base, true),
+            None => (mode, false),
+        };
+
+        match base{
+            "r" => Some(OpenOptions {read: true, write: update, ..OpenOptions::default()}),
+            "w" => Some(OpenOptions {read: update, write: true, truncate: true, create: true, ..OpenOptions::default()}),
+            "a" => Some(OpenOptions {read: update, write: true, append: true, create: true, ..OpenOptions::default()}),
+            _ => None,
+        }
+    }
+}
+
+/// An open file
+pub trait FileHandle: Read + Write + Seek + Send{}
+
+impl<T: Read + Write + Seek + Send> FileHandle for T{}
+
+/// Where the io and os libraries and 'require' get their files from.
+/// Hosts can replace it with 'Interpreter::set_file_system' to sandbox scripts.
+/// It has to be Send since coroutines run the interpreter on their own threads
+pub trait FileSystem: Send{
+    fn open(&mut self, path: &str, options: &OpenOptions) -> io::Result<Box<dyn FileHandle>>;
+
+    fn remove(&mut self, path: &str) -> io::Result<()>;
+
+    fn rename(&mut self, from: &str, to: &str) -> io::Result<()>;
+
+    fn read_to_string(&mut self, path: &str) -> io::Result<String>{
+        let mut contents = String::new();
+        self.open(path, &OpenOptions::read())?.read_to_string(&mut contents)?;
+
+        Ok(contents)
+    }
+}
+
+/// The filesystem of the machine
+pub struct StdFileSystem{}
+
+impl FileSystem for StdFileSystem{
+
+    fn open(&mut self, path: &str, options: &OpenOptions) -> io::Result<Box<dyn FileHandle>>{
+        let file = fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write && !options.append)
+            .append(options.append)
+            .truncate(options.truncate)
+            .create(options.create)
+            .open(path)?;
+
+        Ok(Box::new(file))
+    }
+
+    fn remove(&mut self, path: &str) -> io::Result<()>{
+        // Like C's remove, empty directories can be removed too
+        match fs::metadata(path){
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir(path),
+            _ => fs::remove_file(path),
+        }
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> io::Result<()>{
+        fs::rename(from, to)
+    }
+}
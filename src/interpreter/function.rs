@@ -3,38 +3,31 @@ use super::{Interpreter};
 use std::collections::{HashMap};
 use super::super::{Token, Stmt, data::LuaData, error::LuaError};
 
-type RustFunc = fn(Vec<LuaData>, &mut Interpreter) -> Result<Option<LuaData>, LuaError>;
+pub type RustFunc = fn(Vec<LuaData>, &mut Interpreter) -> Result<Vec<LuaData>, LuaError>;
 
 #[derive(Clone)]
 pub struct LuaFunc{
     pub arg_defs: Vec<Token>,
     pub stmts: Vec<Stmt>,
+    /// The table that holds the globals, None for the global variables
+    pub env: Option<i64>,
 }
 
 impl LuaFunc{
 
-    pub fn new(arg_defs: Vec<Token>, stmts: Vec<Stmt>) -> LuaFunc{
-        LuaFunc{arg_defs: arg_defs, stmts: stmts}
+    pub fn new(arg_defs: Vec<Token>, stmts: Vec<Stmt>, env: Option<i64>) -> LuaFunc{
+        LuaFunc{arg_defs, stmts, env}
     }
 
-    pub fn execute(&mut self, arg_data: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Option<LuaData>, LuaError>{
-        if self.arg_defs.len() != arg_data.len(){
-            return Err(interpreter.error(format!("Incorrect number of arguments found! Expected {} but found {}", self.arg_defs.len(), arg_data.len())));
-        }
-
+    pub fn execute(&mut self, arg_data: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
         self.add_args(interpreter, arg_data)?;
 
-        for stmt in &self.stmts{
-            if let Some(_) = interpreter.return_val{
-                break;
-            }
-
-            interpreter.run_stmt(&mut stmt.clone())?;
-        }
+        interpreter.run_block(&mut self.stmts)?;
 
-        Ok(interpreter.return_val.clone())
+        Ok(interpreter.return_val.clone().unwrap_or_default())
     }
 
+    /// Missing arguments are nil and extra arguments are dropped
     fn add_args(&self, interpreter: &mut Interpreter, data: Vec<LuaData>) -> Result<(), LuaError>{
         for x in 0..self.arg_defs.len(){
             let name = match self.arg_defs.get(x).unwrap(){
@@ -42,7 +35,7 @@ impl LuaFunc{
                 x => return Err(interpreter.error(format!("Expected identifier but found {:?}", x))),    
             }.to_string();
 
-            interpreter.assign_variable(name, data.get(x).unwrap().clone(), true)?
+            interpreter.assign_variable(name, data.get(x).cloned().unwrap_or(LuaData::Nil), true)?
         }
 
         Ok(())
@@ -52,17 +45,21 @@ impl LuaFunc{
 #[derive(Clone)]
 pub enum FunctionDef{
     Lua(LuaFunc),
-    Rust(RustFunc)
+    Rust(RustFunc),
+    /// A rust function that gets the bound values before its arguments
+    Bound(RustFunc, Vec<LuaData>),
 }
 
 #[derive(Clone)]
 pub struct Function{
     pub def: FunctionDef,
-    pub id: i64
+    pub id: i64,
+    /// The name the function was registered with, shown in tracebacks
+    pub name: String,
 }
 
-pub fn create_function(id: i64, def: FunctionDef) -> Function{
-    Function{def, id}
+pub fn create_function(id: i64, name: String, def: FunctionDef) -> Function{
+    Function{def, id, name}
 }
 
 ///
@@ -88,8 +85,8 @@ impl FunctionManager{
     pub fn register_func(&mut self, name: String, def: FunctionDef) -> i64{
         let id = self.func_count;
         self.func_count += 1;
-        self.func_names.insert(name, id);
-        self.funcs.insert(id, create_function(id, def));
+        self.func_names.insert(name.clone(), id);
+        self.funcs.insert(id, create_function(id, name, def));
 
         id
     }
@@ -0,0 +1,311 @@
+use super::*;
+use super::super::super::parser;
+use std::io::Read;
+
+/// The base functions that aren't part of a library table, i.e. 'type' and 'select'
+pub struct BaseLib{}
+
+impl Library for BaseLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        interpreter.register_func("type", FunctionDef::Rust(type_));
+        interpreter.register_func("tostring", FunctionDef::Rust(tostring));
+        interpreter.register_func("tonumber", FunctionDef::Rust(tonumber));
+        interpreter.register_func("assert", FunctionDef::Rust(assert));
+        interpreter.register_func("rawget", FunctionDef::Rust(rawget));
+        interpreter.register_func("rawset", FunctionDef::Rust(rawset));
+        interpreter.register_func("rawequal", FunctionDef::Rust(rawequal));
+        interpreter.register_func("rawlen", FunctionDef::Rust(rawlen));
+        interpreter.register_func("select", FunctionDef::Rust(select));
+        interpreter.register_func("unpack", FunctionDef::Rust(table::unpack));
+        interpreter.register_func("load", FunctionDef::Rust(load));
+        interpreter.register_func("loadfile", FunctionDef::Rust(loadfile));
+        interpreter.register_func("dofile", FunctionDef::Rust(dofile));
+
+        interpreter.globals.insert("_VERSION".to_string(), LuaData::Str("Lua 5.4".into()));
+    }
+
+}
+
+/// type(v), the name of the type of v
+fn type_(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let value = check_any(interpreter, &args, 0, "type")?;
+
+    Ok(vec![LuaData::Str(value.type_name().into())])
+}
+
+/// tostring(v), converts v to a string using the '__tostring' and '__name' metafields
+fn tostring(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let value = check_any(interpreter, &args, 0, "tostring")?;
+
+    Ok(vec![LuaData::Str(interpreter.tostring(&value)?)])
+}
+
+/// tonumber(v [, base]), converts v to a number or returns nil. With a base, v must be a
+/// string containing an integer in that base, the letters stand for the digits from 10 to 35
+fn tonumber(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    if matches!(args.get(1), None | Some(LuaData::Nil)){
+        let value = check_any(interpreter, &args, 0, "tonumber")?;
+
+        return Ok(vec![value.to_number().unwrap_or(LuaData::Nil)]);
+    }
+
+    let base = check_int(interpreter, &args, 1, "tonumber")?;
+    let s = match args.first(){
+        Some(LuaData::Str(x)) => String::from_utf8_lossy(x).into_owned(),
+        _ => return Err(type_error(interpreter, &args, 0, "tonumber", "string")),
+    };
+
+    if !(2..=36).contains(&base){
+        return Err(arg_error(interpreter, 1, "tonumber", "base out of range"));
+    }
+
+    Ok(vec![parse_int(&s, base as u32).map(LuaData::Int).unwrap_or(LuaData::Nil)])
+}
+
+/// Parses an integer in a base like lua's l_str2int, surrounding spaces are allowed and overflows wrap around
+fn parse_int(s: &str, base: u32) -> Option<i64>{
+    let s = s.trim_matches(|c: char| c.is_ascii_whitespace() || c == '\x0B');
+    let (negative, digits) = match s.strip_prefix('-'){
+        Some(x) => (true, x),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+
+    if digits.is_empty(){
+        return None;
+    }
+
+    let mut value: i64 = 0;
+    for c in digits.chars(){
+        let digit = c.to_digit(36).filter(|x| *x < base)?;
+        value = value.wrapping_mul(base as i64).wrapping_add(digit as i64);
+    }
+
+    Some(if negative {value.wrapping_neg()} else {value})
+}
+
+/// assert(v [, message, ...]), returns all the arguments if v is true, otherwise raises the message as it is
+fn assert(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let value = check_any(interpreter, &args, 0, "assert")?;
+
+    if value.to_bool(){
+        return Ok(args);
+    }
+
+    let message = match args.get(1){
+        None | Some(LuaData::Nil) => LuaData::Str("assertion failed!".into()),
+        Some(x) => x.clone(),
+    };
+
+    Err(LuaError::create_value(message))
+}
+
+fn check_table(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<i64, LuaError>{
+    match args.get(index){
+        Some(LuaData::Table(id)) => Ok(*id),
+        _ => Err(type_error(interpreter, args, index, func, "table")),
+    }
+}
+
+/// rawget(t, k), t[k] without invoking '__index'
+fn rawget(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = check_table(interpreter, &args, 0, "rawget")?;
+    let key = check_any(interpreter, &args, 1, "rawget")?;
+
+    Ok(vec![interpreter.get_table(id).unwrap().get(&key)])
+}
+
+/// rawset(t, k, v), sets t[k] without invoking '__newindex' and returns t
+fn rawset(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = check_table(interpreter, &args, 0, "rawset")?;
+    let key = check_any(interpreter, &args, 1, "rawset")?;
+    let value = check_any(interpreter, &args, 2, "rawset")?;
+
+    if let Err(message) = interpreter.get_table_mut(id).unwrap().set(key, value){
+        return Err(interpreter.error(message.to_string()));
+    }
+
+    Ok(vec![LuaData::Table(id)])
+}
+
+/// rawequal(a, b), compares a and b without invoking '__eq'
+fn rawequal(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let left = check_any(interpreter, &args, 0, "rawequal")?;
+    let right = check_any(interpreter, &args, 1, "rawequal")?;
+
+    Ok(vec![LuaData::Bool(left.raw_equals(&right))])
+}
+
+/// rawlen(v), the length of a table or string without invoking '__len'
+fn rawlen(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match args.first(){
+        Some(LuaData::Table(id)) => Ok(vec![LuaData::Int(interpreter.get_table(*id).unwrap().border())]),
+        Some(LuaData::Str(x)) => Ok(vec![LuaData::Int(x.len() as i64)]),
+        _ => Err(arg_error(interpreter, 0, "rawlen", "table or string expected")),
+    }
+}
+
+/// select(n, ...), the arguments after the n-th one, negative indices count from the end.
+/// select('#', ...) returns the number of arguments
+fn select(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let count = args.len().saturating_sub(1) as i64;
+
+    if matches!(args.first(), Some(LuaData::Str(x)) if x == b"#"){
+        return Ok(vec![LuaData::Int(count)]);
+    }
+
+    let n = check_int(interpreter, &args, 0, "select")?;
+    let start = if n < 0 {count + n} else {n - 1};
+
+    if n == 0 || start < 0{
+        return Err(arg_error(interpreter, 0, "select", "index out of range"));
+    }
+
+    Ok(args.into_iter().skip(start as usize + 1).collect())
+}
+
+/// load(chunk [, chunkname [, mode [, env]]]), compiles a string, or the pieces returned by a reader function
+/// until it returns nil or an empty string. Returns the chunk as a function or nil and the error message.
+/// The globals of the chunk and the functions it defines are stored in env if it is given
+fn load(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let mode = opt_mode(interpreter, &args, 2, "load")?;
+    let env = opt_env(interpreter, &args, 3, "load")?;
+
+    let (src, default_name) = match args.first(){
+        Some(LuaData::Func(_)) => {
+            let reader = args[0].clone();
+            let mut src = Vec::new();
+
+            loop{
+                let piece = match interpreter.protected_call(&reader, Vec::new(), None){
+                    Ok(x) => x.into_iter().next().unwrap_or(LuaData::Nil),
+                    Err(e) => {
+                        interpreter.check_exit()?;
+                        return Ok(vec![LuaData::Nil, e]);
+                    },
+                };
+
+                match piece{
+                    LuaData::Str(ref x) if !x.is_empty() => src.extend_from_slice(x),
+                    LuaData::Nil | LuaData::Str(_) => break,
+                    _ => return Ok(vec![LuaData::Nil, LuaData::Str("reader function must return a string".into())]),
+                }
+            }
+
+            (String::from_utf8_lossy(&src).into_owned(), "=(load)".to_string())
+        },
+        Some(x) if x.is_number() || matches!(x, LuaData::Str(_)) => (x.to_string(), x.to_string()),
+        _ => return Err(type_error(interpreter, &args, 0, "load", "function")),
+    };
+
+    let name = match args.get(1){
+        None | Some(LuaData::Nil) => default_name,
+        Some(_) => check_text(interpreter, &args, 1, "load")?,
+    };
+
+    compile(interpreter, src, &chunk_id(&name), &mode, env)
+}
+
+/// loadfile([filename [, mode [, env]]]), like 'load' with the contents of a file, or stdin without a filename
+fn loadfile(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let filename = match args.first(){
+        None | Some(LuaData::Nil) => None,
+        Some(_) => Some(check_text(interpreter, &args, 0, "loadfile")?),
+    };
+    let mode = opt_mode(interpreter, &args, 1, "loadfile")?;
+    let env = opt_env(interpreter, &args, 2, "loadfile")?;
+
+    match read_chunk(interpreter, filename.as_deref()){
+        Ok((src, name)) => compile(interpreter, src, &name, &mode, env),
+        Err(message) => Ok(vec![LuaData::Nil, LuaData::Str(message.into_bytes())]),
+    }
+}
+
+/// dofile([filename]), runs a file and returns its results. Errors are raised instead of returned
+fn dofile(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let filename = match args.first(){
+        None | Some(LuaData::Nil) => None,
+        Some(_) => Some(check_text(interpreter, &args, 0, "dofile")?),
+    };
+
+    let chunk = match read_chunk(interpreter, filename.as_deref()){
+        Ok((src, name)) => compile(interpreter, src, &name, "bt", None)?,
+        Err(message) => return Err(LuaError::create_value(LuaData::Str(message.into_bytes()))),
+    };
+
+    match chunk.as_slice(){
+        [func] => interpreter.call(func, Vec::new()),
+        _ => Err(LuaError::create_value(chunk[1].clone())),
+    }
+}
+
+/// Reads a file through the filesystem of the interpreter, or stdin without a name. Returns the source and the chunk name
+fn read_chunk(interpreter: &mut Interpreter, filename: Option<&str>) -> Result<(String, String), String>{
+    let (result, name) = match filename{
+        Some(filename) => (interpreter.file_system().read_to_string(filename), filename.to_string()),
+        None => {
+            let mut src = String::new();
+            (::std::io::stdin().read_to_string(&mut src).map(|_| src), "stdin".to_string())
+        },
+    };
+
+    match result{
+        // A first line starting with '#' is skipped, so scripts can start with '#!', the newline keeps the line numbers
+        Ok(src) if src.starts_with('#') => Ok((src.find('\n').map(|x| src[x..].to_string()).unwrap_or_default(), name)),
+        Ok(src) => Ok((src, name)),
+        Err(e) => Err(format!("cannot open {}: {}", name, io::error_message(&e))),
+    }
+}
+
+/// Scans and parses a chunk into a function. Syntax errors are returned as nil and the message
+fn compile(interpreter: &mut Interpreter, src: String, name: &str, mode: &str, env: Option<i64>) -> Result<Vec<LuaData>, LuaError>{
+    // There are no binary chunks, so only the text mode can load anything
+    if !mode.contains('t'){
+        return Ok(vec![LuaData::Nil, LuaData::Str(format!("attempt to load a text chunk (mode is '{}')", mode).into_bytes())]);
+    }
+
+    interpreter.add_source(name, &src);
+
+    let stmts = match parser::scanner::scan_chunk(src, name).and_then(parser::parse){
+        Ok(x) => x,
+        Err(errors) => return Ok(vec![LuaData::Nil, errors[0].value()]),
+    };
+
+    let func = LuaFunc::new(Vec::new(), stmts, env);
+    let id = interpreter.func_manager.register_func(name.to_string(), FunctionDef::Lua(func));
+
+    Ok(vec![LuaData::Func(id)])
+}
+
+/// The name of a chunk in messages like lua's luaO_chunkid: '=name' and '@file' are shown without the
+/// prefix and the source of strings is shown as [string "first line..."]
+fn chunk_id(name: &str) -> String{
+    const MAX_SOURCE: usize = 40;
+
+    if let Some(x) = name.strip_prefix('=').or_else(|| name.strip_prefix('@')){
+        return x.to_string();
+    }
+
+    let line = name.lines().next().unwrap_or("");
+    if line.len() == name.len() && line.chars().count() <= MAX_SOURCE{
+        return format!("[string \"{}\"]", line);
+    }
+
+    format!("[string \"{}...\"]", line.chars().take(MAX_SOURCE).collect::<String>())
+}
+
+fn opt_mode(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<String, LuaError>{
+    match args.get(index){
+        None | Some(LuaData::Nil) => Ok("bt".to_string()),
+        Some(_) => check_text(interpreter, args, index, func),
+    }
+}
+
+/// The environment of a chunk, nil uses the global variables
+fn opt_env(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<Option<i64>, LuaError>{
+    match args.get(index){
+        None | Some(LuaData::Nil) => Ok(None),
+        Some(LuaData::Table(id)) => Ok(Some(*id)),
+        Some(_) => Err(type_error(interpreter, args, index, func, "table")),
+    }
+}
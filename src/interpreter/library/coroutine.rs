@@ -0,0 +1,94 @@
+use super::*;
+
+/// The 'coroutine' table
+pub struct CoroutineLib{}
+
+impl Library for CoroutineLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        interpreter.register_lib("coroutine", &[
+            ("create", create),
+            ("resume", resume),
+            ("yield", yield_),
+            ("status", status),
+            ("wrap", wrap),
+            ("isyieldable", is_yieldable),
+            ("close", close),
+        ]);
+    }
+
+}
+
+fn create(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match args.first(){
+        Some(LuaData::Func(_)) => Ok(vec![LuaData::Thread(interpreter.create_coroutine(args[0].clone()))]),
+        _ => Err(type_error(interpreter, &args, 0, "create", "function")),
+    }
+}
+
+fn resume(mut args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = get_coroutine(&args, interpreter, "resume")?;
+    args.remove(0);
+
+    let result = interpreter.resume(id, args);
+    interpreter.check_exit()?;
+
+    Ok(protected_results(result))
+}
+
+fn yield_(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    interpreter.yield_coroutine(args)
+}
+
+fn status(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = get_coroutine(&args, interpreter, "status")?;
+    let status = interpreter.get_coroutine(id).unwrap().status;
+
+    Ok(vec![LuaData::Str(status.name().into())])
+}
+
+/// Creates a function that resumes the coroutine and raises its errors
+fn wrap(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let coroutine = match args.first(){
+        Some(LuaData::Func(_)) => LuaData::Thread(interpreter.create_coroutine(args[0].clone())),
+        _ => return Err(type_error(interpreter, &args, 0, "wrap", "function")),
+    };
+    let id = interpreter.func_manager.register_func("wrap".to_string(), FunctionDef::Bound(resume_wrapped, vec![coroutine]));
+
+    Ok(vec![LuaData::Func(id)])
+}
+
+fn resume_wrapped(mut args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = match args.remove(0){
+        LuaData::Thread(coroutine) => coroutine.id(),
+        _ => unreachable!("wrapped functions are bound to a coroutine"),
+    };
+
+    let result = interpreter.resume(id, args);
+    interpreter.check_exit()?;
+
+    match result{
+        Ok(values) => Ok(values),
+        Err(e) => Err(LuaError::create_value(e)),
+    }
+}
+
+fn is_yieldable(_: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    Ok(vec![LuaData::Bool(interpreter.current_coroutine().is_some())])
+}
+
+fn close(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = get_coroutine(&args, interpreter, "close")?;
+
+    Ok(match interpreter.close_coroutine(id)?{
+        Some(error) => vec![LuaData::Bool(false), error],
+        None => vec![LuaData::Bool(true)],
+    })
+}
+
+fn get_coroutine(args: &[LuaData], interpreter: &Interpreter, func: &str) -> Result<i64, LuaError>{
+    match args.first(){
+        Some(LuaData::Thread(coroutine)) => Ok(coroutine.id()),
+        _ => Err(type_error(interpreter, args, 0, func, "coroutine")),
+    }
+}
@@ -0,0 +1,35 @@
+use super::*;
+
+/// The 'debug' table
+pub struct DebugLib{}
+
+impl Library for DebugLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        interpreter.register_lib("debug", &[
+            ("traceback", traceback),
+        ]);
+    }
+
+}
+
+/// debug.traceback([message [, level]]), level 1 is the function that called traceback.
+/// Messages that aren't strings or nil are returned untouched
+fn traceback(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let message = args.first().cloned().unwrap_or(LuaData::Nil);
+    let level = match args.get(1){
+        Some(x) => match x.to_number().and_then(|x| x.to_int()){
+            Some(x) => x.max(0) as usize,
+            None => return Err(type_error(interpreter, &args, 1, "traceback", "number")),
+        },
+        None => 1,
+    };
+
+    let traceback = interpreter.traceback_from(level);
+
+    match message{
+        LuaData::Nil => Ok(vec![LuaData::Str(traceback.into_bytes())]),
+        LuaData::Str(_) | LuaData::Int(_) | LuaData::Number(_) => Ok(vec![LuaData::Str([message.to_bytes(), format!("\n{}", traceback).into_bytes()].concat())]),
+        x => Ok(vec![x]),
+    }
+}
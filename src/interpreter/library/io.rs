@@ -0,0 +1,660 @@
+use super::*;
+use super::super::fs::{FileHandle, OpenOptions};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write, Seek, SeekFrom};
+
+/// The 'io' table. Files are userdata whose metatable holds the file methods,
+/// the open files themselves are kept by the interpreter. A file that nothing refers to
+/// anymore is closed by its '__gc' metamethod
+pub struct IoLib{}
+
+impl Library for IoLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        let id = interpreter.register_lib("io", &[
+            ("open", open),
+            ("type", type_),
+        ]);
+
+        let methods = interpreter.create_table();
+        register_methods(interpreter, methods, &[
+            ("read", read),
+            ("write", write),
+            ("lines", lines),
+            ("seek", seek),
+            ("setvbuf", setvbuf),
+            ("flush", flush),
+            ("close", close),
+        ]);
+
+        let metatable = interpreter.create_table();
+        register_methods(interpreter, metatable, &[
+            ("__close", close_variable),
+            ("__gc", gc),
+            ("__tostring", tostring),
+        ]);
+        let table = interpreter.get_table_mut(metatable).unwrap();
+        table.assign_variable("__index".to_string(), LuaData::Table(methods));
+        table.assign_variable("__name".to_string(), LuaData::Str("FILE*".into()));
+        interpreter.file_metatable = Some(metatable);
+
+        let stdin = create_file(interpreter, Stream::Stdin);
+        let stdout = create_file(interpreter, Stream::Stdout);
+        let stderr = create_file(interpreter, Stream::Stderr);
+
+        // The functions that default to a standard file get it bound before their arguments
+        let bound: [(&str, RustFunc, &LuaData); 4] = [
+            ("read", read, &stdin),
+            ("write", write, &stdout),
+            ("lines", io_lines, &stdin),
+            ("close", io_close, &stdout),
+        ];
+        for (name, func, file) in bound{
+            let func_id = interpreter.func_manager.register_func(format!("io.{}", name), FunctionDef::Bound(func, vec![file.clone()]));
+            interpreter.get_table_mut(id).unwrap().assign_variable(name.to_string(), LuaData::Func(func_id));
+        }
+
+        let table = interpreter.get_table_mut(id).unwrap();
+        table.assign_variable("stdin".to_string(), stdin);
+        table.assign_variable("stdout".to_string(), stdout);
+        table.assign_variable("stderr".to_string(), stderr);
+    }
+
+}
+
+/// What a file reads from and writes to
+enum Stream{
+    File(Box<dyn FileHandle>),
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+/// A file opened by the io library
+pub struct LuaFile{
+    /// None once the file is closed
+    stream: Option<Stream>,
+    /// Bytes that were read from the stream but not by the script yet
+    buffer: VecDeque<u8>,
+}
+
+/// The longest numeral 'read("n")' accepts, like lua's L_MAXLENNUM
+const MAX_NUMERAL: usize = 200;
+
+impl LuaFile{
+
+    fn new(stream: Stream) -> LuaFile{
+        LuaFile {stream: Some(stream), buffer: VecDeque::new()}
+    }
+
+    fn is_closed(&self) -> bool{
+        self.stream.is_none()
+    }
+
+    fn is_standard(&self) -> bool{
+        matches!(self.stream, Some(Stream::Stdin) | Some(Stream::Stdout) | Some(Stream::Stderr))
+    }
+
+    /// Reads more bytes into the buffer, false at the end of the file
+    fn fill(&mut self) -> io::Result<bool>{
+        let mut chunk = [0; 4096];
+        let count = match self.stream{
+            Some(Stream::File(ref mut file)) => file.read(&mut chunk)?,
+            Some(Stream::Stdin) => io::stdin().read(&mut chunk)?,
+            _ => return Err(bad_descriptor()),
+        };
+
+        self.buffer.extend(&chunk[..count]);
+
+        Ok(count > 0)
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>>{
+        if self.buffer.is_empty() && !self.fill()?{
+            return Ok(None);
+        }
+
+        Ok(self.buffer.front().copied())
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>>{
+        self.peek()?;
+
+        Ok(self.buffer.pop_front())
+    }
+
+    /// The next line, None at the end of the file
+    fn read_line(&mut self, keep_newline: bool) -> io::Result<Option<Vec<u8>>>{
+        let mut line = Vec::new();
+
+        loop{
+            match self.next_byte()?{
+                Some(b'\n') => {
+                    if keep_newline{
+                        line.push(b'\n');
+                    }
+
+                    return Ok(Some(line));
+                },
+                Some(x) => line.push(x),
+                None if line.is_empty() => return Ok(None),
+                None => return Ok(Some(line)),
+            }
+        }
+    }
+
+    /// The rest of the file, empty at the end of the file
+    fn read_all(&mut self) -> io::Result<Vec<u8>>{
+        while self.fill()?{}
+
+        Ok(self.buffer.drain(..).collect())
+    }
+
+    /// Up to count bytes, None at the end of the file. A count of 0 only tests for the end
+    fn read_count(&mut self, count: usize) -> io::Result<Option<Vec<u8>>>{
+        if self.peek()?.is_none(){
+            return Ok(None);
+        }
+
+        let mut bytes = Vec::new();
+        while bytes.len() < count{
+            match self.next_byte()?{
+                Some(x) => bytes.push(x),
+                None => break,
+            }
+        }
+
+        Ok(Some(bytes))
+    }
+
+    /// Reads the longest prefix of a numeral like lua's 'read("n")', None if it isn't a valid number
+    fn read_number(&mut self) -> io::Result<Option<LuaData>>{
+        while let Some(x) = self.peek()?{
+            if !x.is_ascii_whitespace(){
+                break;
+            }
+            self.buffer.pop_front();
+        }
+
+        let mut numeral = String::new();
+        let mut hex = false;
+        let mut count = 0;
+
+        self.accept(&mut numeral, "+-")?;
+        if self.accept(&mut numeral, "0")?{
+            if self.accept(&mut numeral, "xX")?{
+                hex = true;
+            }else{
+                count = 1;
+            }
+        }
+
+        count += self.accept_digits(&mut numeral, hex)?;
+        if self.accept(&mut numeral, ".")?{
+            count += self.accept_digits(&mut numeral, hex)?;
+        }
+
+        if count > 0 && self.accept(&mut numeral, if hex {"pP"} else {"eE"})?{
+            self.accept(&mut numeral, "+-")?;
+            self.accept_digits(&mut numeral, false)?;
+        }
+
+        Ok(str_to_number(&numeral))
+    }
+
+    /// Moves the next byte to the numeral if it is one of the expected characters
+    fn accept(&mut self, numeral: &mut String, expected: &str) -> io::Result<bool>{
+        match self.peek()?{
+            Some(x) if numeral.len() < MAX_NUMERAL && expected.contains(x as char) => {
+                numeral.push(x as char);
+                self.buffer.pop_front();
+
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+
+    fn accept_digits(&mut self, numeral: &mut String, hex: bool) -> io::Result<usize>{
+        let digits = if hex {"0123456789abcdefABCDEF"} else {"0123456789"};
+        let mut count = 0;
+
+        while self.accept(numeral, digits)?{
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Drops the bytes that were read ahead, so the stream is at the position the script sees
+    fn discard_buffer(&mut self) -> io::Result<()>{
+        if let Some(Stream::File(ref mut file)) = self.stream{
+            if !self.buffer.is_empty(){
+                file.seek(SeekFrom::Current(-(self.buffer.len() as i64)))?;
+            }
+        }
+
+        self.buffer.clear();
+
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()>{
+        self.discard_buffer()?;
+
+        match self.stream{
+            Some(Stream::File(ref mut file)) => file.write_all(bytes),
+            Some(Stream::Stdout) => io::stdout().write_all(bytes),
+            Some(Stream::Stderr) => io::stderr().write_all(bytes),
+            _ => Err(bad_descriptor()),
+        }
+    }
+
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64>{
+        let position = match position{
+            SeekFrom::Current(offset) => SeekFrom::Current(offset - self.buffer.len() as i64),
+            x => x,
+        };
+
+        match self.stream{
+            Some(Stream::File(ref mut file)) => {
+                self.buffer.clear();
+                file.seek(position)
+            },
+            // ESPIPE, the standard streams can't be positioned
+            _ => Err(io::Error::from_raw_os_error(29)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()>{
+        match self.stream{
+            Some(Stream::File(ref mut file)) => file.flush(),
+            Some(Stream::Stdout) => io::stdout().flush(),
+            _ => Ok(()),
+        }
+    }
+
+    fn close(&mut self) -> io::Result<()>{
+        let result = self.flush();
+        self.stream = None;
+        self.buffer.clear();
+
+        result
+    }
+}
+
+/// EBADF, i.e. for reading a file that was only opened for writing
+fn bad_descriptor() -> io::Error{
+    io::Error::from_raw_os_error(9)
+}
+
+/// The results of a failed operation in lua's convention: nil, the message and the error number
+pub fn fail(error: &io::Error, filename: Option<&str>) -> Vec<LuaData>{
+    let message = error_message(error);
+    let message = match filename{
+        Some(filename) => format!("{}: {}", filename, message),
+        None => message,
+    };
+
+    let errno = error.raw_os_error().map(|x| x as i64).unwrap_or(match error.kind(){
+        io::ErrorKind::NotFound => 2,
+        io::ErrorKind::PermissionDenied => 13,
+        io::ErrorKind::AlreadyExists => 17,
+        _ => 5,
+    });
+
+    vec![LuaData::Nil, LuaData::Str(message.into_bytes()), LuaData::Int(errno)]
+}
+
+/// The message of an error without rust's " (os error 2)" suffix, like C's strerror
+pub fn error_message(error: &io::Error) -> String{
+    let message = error.to_string();
+
+    match message.find(" (os error "){
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
+}
+
+/// Registers functions as the fields of a table that isn't global
+fn register_methods(interpreter: &mut Interpreter, table: i64, funcs: &[(&str, RustFunc)]){
+    for (name, func) in funcs{
+        let func_id = interpreter.func_manager.register_func(format!("file:{}", name), FunctionDef::Rust(*func));
+        interpreter.get_table_mut(table).unwrap().assign_variable(name.to_string(), LuaData::Func(func_id));
+    }
+}
+
+fn create_file(interpreter: &mut Interpreter, stream: Stream) -> LuaData{
+    let file = interpreter.create_userdata(interpreter.file_metatable);
+    interpreter.files.insert(file.id(), LuaFile::new(stream));
+
+    LuaData::UserData(file)
+}
+
+/// The id of a file, None if the value isn't one
+fn file_id(interpreter: &Interpreter, value: Option<&LuaData>) -> Option<i64>{
+    match value{
+        Some(LuaData::UserData(x)) if interpreter.files.contains_key(&x.id()) => Some(x.id()),
+        _ => None,
+    }
+}
+
+/// The id of the open file passed as an argument
+fn check_file(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<i64, LuaError>{
+    match file_id(interpreter, args.get(index)){
+        Some(id) => {
+            if interpreter.files[&id].is_closed(){
+                return Err(interpreter.error("attempt to use a closed file".to_string()));
+            }
+
+            Ok(id)
+        },
+        None => Err(type_error(interpreter, args, index, func, "FILE*")),
+    }
+}
+
+fn file(interpreter: &mut Interpreter, id: i64) -> &mut LuaFile{
+    interpreter.files.get_mut(&id).unwrap()
+}
+
+/// What 'read' reads
+enum Format{
+    Number,
+    Line,
+    LineWithNewline,
+    All,
+    Count(usize),
+}
+
+/// Parses the formats of 'read' and 'lines', the first format is argument number 'first' + 1.
+/// Without formats a line is read
+fn check_formats(interpreter: &Interpreter, formats: &[LuaData], first: usize, func: &str) -> Result<Vec<Format>, LuaError>{
+    if formats.is_empty(){
+        return Ok(vec![Format::Line]);
+    }
+
+    let mut result = Vec::new();
+
+    for (index, format) in formats.iter().enumerate(){
+        let format = match format{
+            LuaData::Int(_) | LuaData::Number(_) => match format.to_int(){
+                Some(count) => Format::Count(count.max(0) as usize),
+                None => return Err(arg_error(interpreter, first + index, func, "number has no integer representation")),
+            },
+            // Lua 5.1 wrote the formats with a '*' in front
+            LuaData::Str(x) => match x.strip_prefix(b"*").unwrap_or(x).first(){
+                Some(b'n') => Format::Number,
+                Some(b'l') => Format::Line,
+                Some(b'L') => Format::LineWithNewline,
+                Some(b'a') => Format::All,
+                _ => return Err(arg_error(interpreter, first + index, func, "invalid format")),
+            },
+            _ => return Err(arg_error(interpreter, first + index, func, "invalid format")),
+        };
+
+        result.push(format);
+    }
+
+    Ok(result)
+}
+
+/// Reads a value for each format, stopping after the first one that fails with nil
+fn read_formats(file: &mut LuaFile, formats: &[Format]) -> io::Result<Vec<LuaData>>{
+    let mut results = Vec::new();
+
+    for format in formats{
+        let value = match format{
+            Format::Number => file.read_number()?,
+            Format::Line => file.read_line(false)?.map(LuaData::Str),
+            Format::LineWithNewline => file.read_line(true)?.map(LuaData::Str),
+            Format::All => Some(LuaData::Str(file.read_all()?)),
+            Format::Count(count) => file.read_count(*count)?.map(LuaData::Str),
+        };
+
+        match value{
+            Some(x) => results.push(x),
+            None => {
+                results.push(LuaData::Nil);
+                break;
+            },
+        }
+    }
+
+    Ok(results)
+}
+
+/// io.open(filename [, mode]), a file or nil, the message and the error number
+fn open(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let filename = check_text(interpreter, &args, 0, "open")?;
+    let mode = match args.get(1){
+        None | Some(LuaData::Nil) => "r".to_string(),
+        Some(_) => check_text(interpreter, &args, 1, "open")?,
+    };
+
+    let options = match OpenOptions::from_mode(&mode){
+        Some(x) => x,
+        None => return Err(arg_error(interpreter, 1, "open", "invalid mode")),
+    };
+
+    match interpreter.file_system().open(&filename, &options){
+        Ok(handle) => Ok(vec![create_file(interpreter, Stream::File(handle))]),
+        Err(e) => Ok(fail(&e, Some(&filename))),
+    }
+}
+
+/// io.type(obj), "file", "closed file", or nil if obj isn't a file
+fn type_(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    if args.is_empty(){
+        return Err(arg_error(interpreter, 0, "type", "value expected"));
+    }
+
+    let result = match file_id(interpreter, args.first()){
+        Some(id) if interpreter.files[&id].is_closed() => LuaData::Str("closed file".into()),
+        Some(_) => LuaData::Str("file".into()),
+        None => LuaData::Nil,
+    };
+
+    Ok(vec![result])
+}
+
+/// io.lines([filename, ...]), iterates over the lines of a file, closing it at the end.
+/// Without a filename it reads from stdin, which is bound before the arguments
+fn io_lines(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let stdin = args[0].clone();
+    let args = &args[1..];
+    let filename = match args.first(){
+        None | Some(LuaData::Nil) => None,
+        Some(_) => Some(check_text(interpreter, args, 0, "lines")?),
+    };
+    let formats = args.get(1..).unwrap_or_default();
+    check_formats(interpreter, formats, 1, "lines")?;
+
+    let (file, close_at_end) = match filename{
+        Some(filename) => match interpreter.file_system().open(&filename, &OpenOptions::read()){
+            Ok(handle) => (create_file(interpreter, Stream::File(handle)), true),
+            Err(e) => return Err(interpreter.error(format!("cannot open file '{}' ({})", filename, error_message(&e)))),
+        },
+        None => {
+            check_file(interpreter, std::slice::from_ref(&stdin), 0, "lines")?;
+            (stdin, false)
+        },
+    };
+
+    let iterator = lines_iterator(interpreter, file.clone(), close_at_end, formats);
+
+    Ok(vec![iterator, LuaData::Nil, LuaData::Nil, file])
+}
+
+/// io.close([file]), closes the default output without a file
+fn io_close(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match args.len(){
+        1 => close(args, interpreter),
+        _ => close(args[1..].to_vec(), interpreter),
+    }
+}
+
+/// file:read(...), a value for each format
+fn read(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = check_file(interpreter, &args, 0, "read")?;
+    let formats = check_formats(interpreter, &args[1..], 0, "read")?;
+
+    match read_formats(file(interpreter, id), &formats){
+        Ok(values) => Ok(values),
+        Err(e) => Ok(fail(&e, None)),
+    }
+}
+
+/// file:write(...), writes strings and numbers and returns the file
+fn write(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = check_file(interpreter, &args, 0, "write")?;
+    let values = &args[1..];
+
+    let mut output = Vec::new();
+    for (index, value) in values.iter().enumerate(){
+        match value{
+            LuaData::Str(x) => output.extend_from_slice(x),
+            LuaData::Int(x) => output.extend_from_slice(x.to_string().as_bytes()),
+            LuaData::Number(x) => output.extend_from_slice(format_general(*x, 14).as_bytes()),
+            _ => return Err(type_error(interpreter, values, index, "write", "string")),
+        }
+    }
+
+    match file(interpreter, id).write(&output){
+        Ok(()) => Ok(vec![args[0].clone()]),
+        Err(e) => Ok(fail(&e, None)),
+    }
+}
+
+/// file:lines(...), iterates with the formats of 'read' without closing the file
+fn lines(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    check_file(interpreter, &args, 0, "lines")?;
+    check_formats(interpreter, &args[1..], 0, "lines")?;
+
+    Ok(vec![lines_iterator(interpreter, args[0].clone(), false, &args[1..])])
+}
+
+fn lines_iterator(interpreter: &mut Interpreter, file: LuaData, close_at_end: bool, formats: &[LuaData]) -> LuaData{
+    let mut bound = vec![file, LuaData::Bool(close_at_end)];
+    bound.extend_from_slice(formats);
+
+    LuaData::Func(interpreter.func_manager.register_func("lines".to_string(), FunctionDef::Bound(lines_next, bound)))
+}
+
+fn lines_next(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = file_id(interpreter, args.first()).expect("lines iterators are bound to their file");
+    if file(interpreter, id).is_closed(){
+        return Err(interpreter.error("file is already closed".to_string()));
+    }
+
+    let formats = check_formats(interpreter, &args[2..], 0, "lines")?;
+    let values = match read_formats(file(interpreter, id), &formats){
+        Ok(values) => values,
+        Err(e) => return Err(interpreter.error(error_message(&e))),
+    };
+
+    if matches!(values[0], LuaData::Nil) && matches!(args[1], LuaData::Bool(true)){
+        // The file is done, errors when closing don't matter anymore
+        let _ = file(interpreter, id).close();
+    }
+
+    Ok(values)
+}
+
+/// file:seek([whence [, offset]]), moves relative to "set", "cur" or "end" and returns the position
+fn seek(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = check_file(interpreter, &args, 0, "seek")?;
+    let options = &args[1..];
+    let whence = match options.first(){
+        None | Some(LuaData::Nil) => "cur".to_string(),
+        Some(_) => check_text(interpreter, options, 0, "seek")?,
+    };
+    let offset = opt_int(interpreter, options, 1, "seek", 0)?;
+
+    let position = match whence.as_str(){
+        // EINVAL, positions before the start don't exist
+        "set" if offset < 0 => return Ok(fail(&io::Error::from_raw_os_error(22), None)),
+        "set" => SeekFrom::Start(offset as u64),
+        "cur" => SeekFrom::Current(offset),
+        "end" => SeekFrom::End(offset),
+        x => return Err(arg_error(interpreter, 0, "seek", &format!("invalid option '{}'", x))),
+    };
+
+    match file(interpreter, id).seek(position){
+        Ok(x) => Ok(vec![LuaData::Int(x as i64)]),
+        Err(e) => Ok(fail(&e, None)),
+    }
+}
+
+/// file:setvbuf(mode [, size]), files are written as soon as 'write' is called so the mode is only checked
+fn setvbuf(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    check_file(interpreter, &args, 0, "setvbuf")?;
+    let options = &args[1..];
+
+    match check_text(interpreter, options, 0, "setvbuf")?.as_str(){
+        "no" | "full" | "line" => (),
+        x => return Err(arg_error(interpreter, 0, "setvbuf", &format!("invalid option '{}'", x))),
+    }
+    opt_int(interpreter, options, 1, "setvbuf", 0)?;
+
+    Ok(vec![LuaData::Bool(true)])
+}
+
+fn flush(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = check_file(interpreter, &args, 0, "flush")?;
+
+    match file(interpreter, id).flush(){
+        Ok(()) => Ok(vec![LuaData::Bool(true)]),
+        Err(e) => Ok(fail(&e, None)),
+    }
+}
+
+/// file:close(), the standard files stay open
+fn close(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = check_file(interpreter, &args, 0, "close")?;
+    let file = file(interpreter, id);
+
+    if file.is_standard(){
+        return Ok(vec![LuaData::Nil, LuaData::Str("cannot close standard file".into())]);
+    }
+
+    match file.close(){
+        Ok(()) => Ok(vec![LuaData::Bool(true)]),
+        Err(e) => Ok(fail(&e, None)),
+    }
+}
+
+/// '__close', closes the file if it is still open
+fn close_variable(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    if let Some(id) = file_id(interpreter, args.first()){
+        let file = file(interpreter, id);
+
+        if !file.is_standard(){
+            let _ = file.close();
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+/// '__gc', closes a file that nothing refers to anymore and forgets it
+fn gc(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    if let Some(id) = file_id(interpreter, args.first()){
+        let mut file = interpreter.files.remove(&id).unwrap();
+
+        if !file.is_standard(){
+            let _ = file.close();
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+fn tostring(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let result = match file_id(interpreter, args.first()){
+        Some(id) if interpreter.files[&id].is_closed() => "file (closed)".to_string(),
+        Some(id) => format!("file (0x{:08x})", id),
+        None => return Err(type_error(interpreter, &args, 0, "tostring", "FILE*")),
+    };
+
+    Ok(vec![LuaData::Str(result.into_bytes())])
+}
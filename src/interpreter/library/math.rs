@@ -0,0 +1,215 @@
+use super::*;
+use std::f64::consts::PI;
+
+/// The 'math' table
+pub struct MathLib{}
+
+impl Library for MathLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        let id = interpreter.register_lib("math", &[
+            ("floor", floor),
+            ("ceil", ceil),
+            ("abs", abs),
+            ("sqrt", |args, interpreter| float_op(args, interpreter, "sqrt", f64::sqrt)),
+            ("sin", |args, interpreter| float_op(args, interpreter, "sin", f64::sin)),
+            ("cos", |args, interpreter| float_op(args, interpreter, "cos", f64::cos)),
+            ("tan", |args, interpreter| float_op(args, interpreter, "tan", f64::tan)),
+            ("asin", |args, interpreter| float_op(args, interpreter, "asin", f64::asin)),
+            ("acos", |args, interpreter| float_op(args, interpreter, "acos", f64::acos)),
+            ("exp", |args, interpreter| float_op(args, interpreter, "exp", f64::exp)),
+            ("atan", atan),
+            ("log", log),
+            ("fmod", fmod),
+            ("modf", modf),
+            ("min", |args, interpreter| extreme(args, interpreter, "min", false)),
+            ("max", |args, interpreter| extreme(args, interpreter, "max", true)),
+            ("tointeger", tointeger),
+            ("type", type_),
+            ("ult", ult),
+            ("random", random),
+            ("randomseed", randomseed),
+        ]);
+
+        let table = interpreter.get_table_mut(id).unwrap();
+        table.assign_variable("huge".to_string(), LuaData::Number(f64::INFINITY));
+        table.assign_variable("pi".to_string(), LuaData::Number(PI));
+        table.assign_variable("maxinteger".to_string(), LuaData::Int(i64::MAX));
+        table.assign_variable("mininteger".to_string(), LuaData::Int(i64::MIN));
+    }
+
+}
+
+/// Rounds down, the result is an integer if it fits
+fn floor(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match check_number(interpreter, &args, 0, "floor")?{
+        LuaData::Number(x) => Ok(vec![float_to_integer(x.floor())]),
+        x => Ok(vec![x]),
+    }
+}
+
+/// Rounds up, the result is an integer if it fits
+fn ceil(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match check_number(interpreter, &args, 0, "ceil")?{
+        LuaData::Number(x) => Ok(vec![float_to_integer(x.ceil())]),
+        x => Ok(vec![x]),
+    }
+}
+
+fn abs(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match check_number(interpreter, &args, 0, "abs")?{
+        LuaData::Int(x) => Ok(vec![LuaData::Int(x.wrapping_abs())]),
+        x => Ok(vec![LuaData::Number(x.to_num().unwrap().abs())]),
+    }
+}
+
+/// Applies a function that takes and returns a float
+fn float_op(args: Vec<LuaData>, interpreter: &mut Interpreter, func: &str, op: fn(f64) -> f64) -> Result<Vec<LuaData>, LuaError>{
+    let x = check_number(interpreter, &args, 0, func)?.to_num().unwrap();
+
+    Ok(vec![LuaData::Number(op(x))])
+}
+
+/// math.atan(y [, x]), the angle of the point (x, y)
+fn atan(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let y = check_number(interpreter, &args, 0, "atan")?.to_num().unwrap();
+    let x = match args.get(1){
+        None | Some(LuaData::Nil) => 1.0,
+        Some(_) => check_number(interpreter, &args, 1, "atan")?.to_num().unwrap(),
+    };
+
+    Ok(vec![LuaData::Number(y.atan2(x))])
+}
+
+/// math.log(x [, base]), the natural logarithm by default
+fn log(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let x = check_number(interpreter, &args, 0, "log")?.to_num().unwrap();
+
+    let result = match args.get(1){
+        None | Some(LuaData::Nil) => x.ln(),
+        Some(_) => match check_number(interpreter, &args, 1, "log")?.to_num().unwrap(){
+            2.0 => x.log2(),
+            10.0 => x.log10(),
+            base => x.ln() / base.ln(),
+        },
+    };
+
+    Ok(vec![LuaData::Number(result)])
+}
+
+/// math.fmod(x, y), the remainder of the division rounded towards zero
+fn fmod(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let x = check_number(interpreter, &args, 0, "fmod")?;
+    let y = check_number(interpreter, &args, 1, "fmod")?;
+
+    match (x, y){
+        (LuaData::Int(_), LuaData::Int(0)) => Err(arg_error(interpreter, 1, "fmod", "zero")),
+        // Avoids the overflow of mininteger % -1
+        (LuaData::Int(_), LuaData::Int(-1)) => Ok(vec![LuaData::Int(0)]),
+        (LuaData::Int(x), LuaData::Int(y)) => Ok(vec![LuaData::Int(x % y)]),
+        (x, y) => Ok(vec![LuaData::Number(x.to_num().unwrap() % y.to_num().unwrap())]),
+    }
+}
+
+/// math.modf(x), the integral part and the fractional part of a number
+fn modf(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let x = match check_number(interpreter, &args, 0, "modf")?{
+        LuaData::Int(x) => return Ok(vec![LuaData::Int(x), LuaData::Number(0.0)]),
+        x => x.to_num().unwrap(),
+    };
+
+    let integral = x.trunc();
+    let fraction = if x.is_infinite() {0.0} else {x - integral};
+
+    Ok(vec![LuaData::Number(integral), LuaData::Number(fraction)])
+}
+
+/// The smallest or largest argument, compared like '<'
+fn extreme(args: Vec<LuaData>, interpreter: &mut Interpreter, func: &str, largest: bool) -> Result<Vec<LuaData>, LuaError>{
+    let mut result = check_number(interpreter, &args, 0, func)?;
+
+    for index in 1..args.len(){
+        let x = check_number(interpreter, &args, index, func)?;
+        let is_better = if largest {interpreter.less_than(&result, &x)?} else {interpreter.less_than(&x, &result)?};
+
+        if is_better{
+            result = x;
+        }
+    }
+
+    Ok(vec![result])
+}
+
+/// math.tointeger(x), the integer with the value of x, or nil if there is none
+fn tointeger(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match args.first(){
+        Some(x) => Ok(vec![x.to_number().and_then(|x| x.to_int()).map(LuaData::Int).unwrap_or(LuaData::Nil)]),
+        None => Err(arg_error(interpreter, 0, "tointeger", "value expected")),
+    }
+}
+
+/// math.type(x), "integer", "float", or nil if x isn't a number
+fn type_(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    match args.first(){
+        Some(LuaData::Int(_)) => Ok(vec![LuaData::Str("integer".into())]),
+        Some(LuaData::Number(_)) => Ok(vec![LuaData::Str("float".into())]),
+        Some(_) => Ok(vec![LuaData::Nil]),
+        None => Err(arg_error(interpreter, 0, "type", "value expected")),
+    }
+}
+
+/// math.ult(m, n), whether m is less than n when they are compared as unsigned integers
+fn ult(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let m = check_int(interpreter, &args, 0, "ult")?;
+    let n = check_int(interpreter, &args, 1, "ult")?;
+
+    Ok(vec![LuaData::Bool((m as u64) < (n as u64))])
+}
+
+/// math.random([m [, n]]), a float in [0, 1), an integer in [1, m] or one in [m, n].
+/// math.random(0) gives an integer with all bits random
+fn random(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let (low, high) = match args.len(){
+        0 => return Ok(vec![LuaData::Number(interpreter.random().next_float())]),
+        1 => match check_int(interpreter, &args, 0, "random")?{
+            0 => return Ok(vec![LuaData::Int(interpreter.random().next_integer() as i64)]),
+            high => (1, high),
+        },
+        2 => (check_int(interpreter, &args, 0, "random")?, check_int(interpreter, &args, 1, "random")?),
+        _ => return Err(interpreter.error("wrong number of arguments".to_string()).with_kind(ErrorKind::ArityMismatch)),
+    };
+
+    if low > high{
+        return Err(arg_error(interpreter, 0, "random", "interval is empty"));
+    }
+
+    let offset = interpreter.random().project((high as u64).wrapping_sub(low as u64));
+
+    Ok(vec![LuaData::Int(offset.wrapping_add(low as u64) as i64)])
+}
+
+/// math.randomseed([x [, y]]), without arguments a seed from the time is used. Returns the seeds
+fn randomseed(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let (seed1, seed2) = if args.is_empty(){
+        super::super::random::time_seed()
+    }else{
+        let seed1 = match check_number(interpreter, &args, 0, "randomseed")?{
+            LuaData::Int(x) => x,
+            x => x.to_num().unwrap() as i64,
+        };
+
+        (seed1, opt_int(interpreter, &args, 1, "randomseed", 0)?)
+    };
+
+    interpreter.seed_random(seed1, seed2);
+
+    Ok(vec![LuaData::Int(seed1), LuaData::Int(seed2)])
+}
+
+/// An integer if the float has an exact integer representation
+fn float_to_integer(x: f64) -> LuaData{
+    match LuaData::Number(x).to_int(){
+        Some(x) => LuaData::Int(x),
+        None => LuaData::Number(x),
+    }
+}
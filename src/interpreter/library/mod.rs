@@ -0,0 +1,229 @@
+
+use std::io::Write;
+use super::*;
+
+pub mod base;
+pub mod coroutine;
+pub mod debug;
+pub mod io;
+pub mod math;
+pub mod os;
+pub mod string;
+pub mod table;
+pub mod utf8;
+
+pub trait Library{
+    fn load(&self, &mut Interpreter);
+}
+
+pub struct AuroraStdLib{}
+
+impl Library for AuroraStdLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        interpreter.register_func("print", FunctionDef::Rust(|args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            let mut line = Vec::new();
+            for arg in args{
+                line.extend(interpreter.tostring(&arg)?);
+                line.push(b'\t');
+            }
+
+            line.push(b'\n');
+            let _ = std::io::stdout().write_all(&line);
+            Ok(Vec::new())
+        }));
+
+        interpreter.register_func("fail", FunctionDef::Rust(|args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            if args.len() != 1{
+                return Err(interpreter.error(format!("Expected one argument, found {}", args.len())).with_kind(ErrorKind::ArityMismatch));
+            }
+
+            let message = match args.get(0).unwrap(){
+                LuaData::Str(x) => String::from_utf8_lossy(x),
+                x => return Err(interpreter.error(format!("Expected string, found {}", x)).with_kind(ErrorKind::TypeMismatch))
+            };
+
+            Err(interpreter.error(format!("{}", message)))
+        }));
+
+        interpreter.register_func("error", FunctionDef::Rust(|args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            let value = args.first().cloned().unwrap_or(LuaData::Nil);
+            let level = opt_int(interpreter, &args, 1, "error", 1)?;
+
+            Err(raise(interpreter, value, level))
+        }));
+
+        interpreter.register_func("pcall", FunctionDef::Rust(|mut args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            if args.is_empty(){
+                return Err(arg_error(interpreter, 0, "pcall", "value expected").with_kind(ErrorKind::ArityMismatch));
+            }
+
+            let func = args.remove(0);
+
+            let result = interpreter.protected_call(&func, args, None);
+            interpreter.check_exit()?;
+
+            Ok(protected_results(result))
+        }));
+
+        interpreter.register_func("xpcall", FunctionDef::Rust(|mut args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            if args.len() < 2{
+                return Err(arg_error(interpreter, 1, "xpcall", "value expected").with_kind(ErrorKind::ArityMismatch));
+            }
+
+            let func = args.remove(0);
+            let handler = args.remove(0);
+
+            let result = interpreter.protected_call(&func, args, Some(handler));
+            interpreter.check_exit()?;
+
+            Ok(protected_results(result))
+        }));
+
+        interpreter.register_func("setmetatable", FunctionDef::Rust(|args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            let table = args.first().cloned().unwrap_or(LuaData::Nil);
+            let metatable = args.get(1).cloned().unwrap_or(LuaData::Nil);
+
+            let id = match table{
+                LuaData::Table(id) => id,
+                _ => return Err(type_error(interpreter, &args, 0, "setmetatable", "table")),
+            };
+
+            let metatable = match metatable{
+                LuaData::Table(id) => Some(id),
+                LuaData::Nil => None,
+                _ => return Err(arg_error(interpreter, 1, "setmetatable", "nil or table expected")),
+            };
+
+            if interpreter.get_metamethod(&table, "__metatable").is_some(){
+                return Err(interpreter.error("cannot change a protected metatable".to_string()));
+            }
+
+            interpreter.get_table_mut(id).unwrap().metatable = metatable;
+
+            Ok(vec![table])
+        }));
+
+        interpreter.register_func("getmetatable", FunctionDef::Rust(|args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            let value = args.first().cloned().unwrap_or(LuaData::Nil);
+
+            if let Some(protected) = interpreter.get_metamethod(&value, "__metatable"){
+                return Ok(vec![protected]);
+            }
+
+            Ok(vec![interpreter.get_metatable(&value).map(LuaData::Table).unwrap_or(LuaData::Nil)])
+        }));
+
+        interpreter.load_library(base::BaseLib{});
+        interpreter.load_library(coroutine::CoroutineLib{});
+        interpreter.load_library(debug::DebugLib{});
+        interpreter.load_library(string::StringLib{});
+        interpreter.load_library(table::TableLib{});
+        interpreter.load_library(math::MathLib{});
+        interpreter.load_library(io::IoLib{});
+        interpreter.load_library(os::OsLib{});
+        interpreter.load_library(utf8::Utf8Lib{});
+
+        interpreter.register_func("require", FunctionDef::Rust(|args, interpreter| -> Result<Vec<LuaData>, LuaError>{
+            if args.len() != 1{
+                return Err(interpreter.error(format!("Expected one argument, found {}", args.len())).with_kind(ErrorKind::ArityMismatch));
+            }
+
+            let path = match args.get(0).unwrap(){
+                LuaData::Str(x) => String::from_utf8_lossy(x).into_owned(),
+                x => return Err(interpreter.error(format!("Expected string, found {}", x)).with_kind(ErrorKind::TypeMismatch))
+            };
+
+            let src = load_file(interpreter, &path)?;
+            let module = load_module(path, src, interpreter)?;
+    
+            Ok(vec![module])
+        }));
+    }
+
+}
+
+/// The results of a protected call such as 'pcall': true and the results, or false and the error value
+pub fn protected_results(result: Result<Vec<LuaData>, LuaData>) -> Vec<LuaData>{
+    match result{
+        Ok(mut values) => {
+            values.insert(0, LuaData::Bool(true));
+            values
+        },
+        Err(e) => vec![LuaData::Bool(false), e],
+    }
+}
+
+/// The error raised by 'error'. String messages get the position of the function at the level
+pub fn raise(interpreter: &Interpreter, value: LuaData, level: i64) -> LuaError{
+    let value = match (value, interpreter.error_position(level.max(0) as usize)){
+        (LuaData::Str(message), Some(position)) if level > 0 => LuaData::Str([format!("{}: ", position).into_bytes(), message].concat()),
+        (x, _) => x,
+    };
+
+    LuaError::create_value(value)
+}
+
+/// Creates the error for a bad argument, i.e. "bad argument #1 to 'resume' (coroutine expected, got nil)"
+pub fn arg_error(interpreter: &Interpreter, index: usize, func: &str, message: &str) -> LuaError{
+    interpreter.error(format!("bad argument #{} to '{}' ({})", index + 1, func, message)).with_kind(ErrorKind::TypeMismatch)
+}
+
+pub fn type_error(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str, expected: &str) -> LuaError{
+    let found = match args.get(index){
+        Some(x) => x.type_name(),
+        None => "no value",
+    };
+
+    arg_error(interpreter, index, func, &format!("{} expected, got {}", expected, found))
+}
+
+/// Any argument, only a missing one is an error. Nil counts as a value
+pub fn check_any(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<LuaData, LuaError>{
+    match args.get(index){
+        Some(x) => Ok(x.clone()),
+        None => Err(arg_error(interpreter, index, func, "value expected")),
+    }
+}
+
+/// A string argument, numbers are converted to strings like in lua
+pub fn check_string(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<Vec<u8>, LuaError>{
+    match args.get(index){
+        Some(LuaData::Str(x)) => Ok(x.clone()),
+        Some(x) if x.is_number() => Ok(x.to_bytes()),
+        _ => Err(type_error(interpreter, args, index, func, "string")),
+    }
+}
+
+/// A string argument that is used as text, i.e. a file name or a mode. Invalid UTF-8 is replaced
+pub fn check_text(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<String, LuaError>{
+    check_string(interpreter, args, index, func).map(|x| String::from_utf8_lossy(&x).into_owned())
+}
+
+/// A number argument, strings that contain a numeral are converted
+pub fn check_number(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<LuaData, LuaError>{
+    match args.get(index).and_then(|x| x.to_number()){
+        Some(x) => Ok(x),
+        None => Err(type_error(interpreter, args, index, func, "number")),
+    }
+}
+
+/// An integer argument, floats are only accepted if they have an exact integer representation
+pub fn check_int(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<i64, LuaError>{
+    match check_number(interpreter, args, index, func)?.to_int(){
+        Some(x) => Ok(x),
+        None => Err(arg_error(interpreter, index, func, "number has no integer representation")),
+    }
+}
+
+/// An optional integer argument, the default is used for nil or a missing argument
+pub fn opt_int(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str, default: i64) -> Result<i64, LuaError>{
+    match args.get(index){
+        None | Some(LuaData::Nil) => Ok(default),
+        Some(_) => check_int(interpreter, args, index, func),
+    }
+}
+
+pub fn new_std() -> AuroraStdLib{
+    AuroraStdLib{}
+}
\ No newline at end of file
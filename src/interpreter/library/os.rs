@@ -0,0 +1,349 @@
+use super::*;
+use super::super::fs::OpenOptions;
+use super::io::fail;
+use std::env;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The 'os' table. Time and environment variables come from the clock and
+/// environment of the interpreter, which hosts can replace through 'Config'
+pub struct OsLib{}
+
+impl Library for OsLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        interpreter.register_lib("os", &[
+            ("time", time),
+            ("clock", clock),
+            ("date", date),
+            ("difftime", difftime),
+            ("getenv", getenv),
+            ("remove", remove),
+            ("rename", rename),
+            ("tmpname", tmpname),
+            ("exit", exit),
+        ]);
+    }
+
+}
+
+const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July",
+    "August", "September", "October", "November", "December"];
+
+/// Counts the names 'tmpname' generated, so each one is different
+static TMP_NAMES: AtomicUsize = AtomicUsize::new(0);
+
+/// A broken down time, like C's 'struct tm' but with the month and day starting at 1
+struct Date{
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    min: i64,
+    sec: i64,
+    /// Days since Sunday
+    wday: i64,
+    /// Days since the first of January
+    yday: i64,
+}
+
+impl Date{
+
+    fn from_time(time: i64) -> Date{
+        let days = time.div_euclid(86400);
+        let seconds = time.rem_euclid(86400);
+        let (year, month, day) = civil_from_days(days);
+
+        Date {
+            year, month, day,
+            hour: seconds / 3600,
+            min: seconds / 60 % 60,
+            sec: seconds % 60,
+            // The first of January 1970 was a Thursday
+            wday: (days + 4).rem_euclid(7),
+            yday: days - days_from_civil(year, 1, 1),
+        }
+    }
+
+    /// The ISO 8601 year and week, weeks start on Monday and the first week has the first Thursday
+    fn iso_week(&self) -> (i64, i64){
+        let week = (self.yday - (self.wday + 6) % 7 + 10) / 7;
+
+        if week < 1{
+            (self.year - 1, iso_weeks_in_year(self.year - 1))
+        }else if week > iso_weeks_in_year(self.year){
+            (self.year + 1, 1)
+        }else{
+            (self.year, week)
+        }
+    }
+}
+
+/// The number of days from 1970-01-01 to a date of the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64{
+    let year = if month <= 2 {year - 1} else {year};
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// The year, month and day of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64){
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {shifted_month + 3} else {shifted_month - 9};
+    let year = year_of_era + era * 400;
+
+    (if month <= 2 {year + 1} else {year}, month, day)
+}
+
+fn iso_weeks_in_year(year: i64) -> i64{
+    let weekday = |year: i64| (year + year.div_euclid(4) - year.div_euclid(100) + year.div_euclid(400)).rem_euclid(7);
+
+    if weekday(year) == 4 || weekday(year - 1) == 3 {53} else {52}
+}
+
+/// os.time([t]), the current time or the time of a date table in local time.
+/// The fields of the table are normalized, i.e. a month of 13 becomes January of the next year
+fn time(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = match args.first(){
+        None | Some(LuaData::Nil) => return Ok(vec![LuaData::Int(interpreter.clock().now())]),
+        Some(LuaData::Table(id)) => *id,
+        Some(_) => return Err(type_error(interpreter, &args, 0, "time", "table")),
+    };
+
+    let year = date_field(interpreter, id, "year", None, 1900)?;
+    let month = date_field(interpreter, id, "month", None, 1)?;
+    let day = date_field(interpreter, id, "day", None, 0)?;
+    let hour = date_field(interpreter, id, "hour", Some(12), 0)?;
+    let min = date_field(interpreter, id, "min", Some(0), 0)?;
+    let sec = date_field(interpreter, id, "sec", Some(0), 0)?;
+
+    let months = year * 12 + month - 1;
+    let days = days_from_civil(months.div_euclid(12), months.rem_euclid(12) + 1, 1) + day - 1;
+    let local = days * 86400 + hour * 3600 + min * 60 + sec;
+    let time = local - interpreter.clock().utc_offset(local);
+
+    let normalized = Date::from_time(time + interpreter.clock().utc_offset(time));
+    set_date_fields(interpreter.get_table_mut(id).unwrap(), &normalized);
+
+    Ok(vec![LuaData::Int(time)])
+}
+
+/// An integer field of a date table, the delta is subtracted before checking that it fits in a C int
+fn date_field(interpreter: &Interpreter, id: i64, key: &str, default: Option<i64>, delta: i64) -> Result<i64, LuaError>{
+    let value = interpreter.get_table(id).unwrap().get_variable(key.to_string());
+
+    let value = match (value, default){
+        (Some(x), _) if x.is_number() || matches!(x, LuaData::Str(_)) => match x.to_number().and_then(|x| x.to_int()){
+            Some(x) => x,
+            None => return Err(interpreter.error(format!("field '{}' is not an integer", key))),
+        },
+        (None, Some(default)) | (Some(LuaData::Nil), Some(default)) => return Ok(default),
+        (None, None) | (Some(LuaData::Nil), None) => return Err(interpreter.error(format!("field '{}' missing in date table", key))),
+        (Some(_), _) => return Err(interpreter.error(format!("field '{}' is not an integer", key))),
+    };
+
+    if value.checked_sub(delta).is_none_or(|x| x < i32::MIN as i64 || x > i32::MAX as i64){
+        return Err(interpreter.error(format!("field '{}' is out-of-bound", key)));
+    }
+
+    Ok(value)
+}
+
+fn set_date_fields(table: &mut Table, date: &Date){
+    let fields = [("year", date.year), ("month", date.month), ("day", date.day), ("hour", date.hour),
+        ("min", date.min), ("sec", date.sec), ("wday", date.wday + 1), ("yday", date.yday + 1)];
+
+    for (name, value) in fields{
+        table.assign_variable(name.to_string(), LuaData::Int(value));
+    }
+    table.assign_variable("isdst".to_string(), LuaData::Bool(false));
+}
+
+/// os.clock(), seconds of processor time
+fn clock(_: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    Ok(vec![LuaData::Number(interpreter.clock().cpu_time())])
+}
+
+/// os.date([format [, time]]), formats a time like C's strftime. A leading '!' formats it in UTC,
+/// and "*t" returns a table with the fields of the date instead
+fn date(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let format = match args.first(){
+        None | Some(LuaData::Nil) => "%c".to_string(),
+        Some(_) => check_text(interpreter, &args, 0, "date")?,
+    };
+    let time = match args.get(1){
+        None | Some(LuaData::Nil) => interpreter.clock().now(),
+        Some(_) => check_int(interpreter, &args, 1, "date")?,
+    };
+
+    let (format, offset) = match format.strip_prefix('!'){
+        Some(format) => (format, None),
+        None => (format.as_str(), Some(interpreter.clock().utc_offset(time))),
+    };
+    let date = Date::from_time(time + offset.unwrap_or(0));
+
+    if format.starts_with("*t"){
+        let id = interpreter.create_table();
+        set_date_fields(interpreter.get_table_mut(id).unwrap(), &date);
+
+        return Ok(vec![LuaData::Table(id)]);
+    }
+
+    match format_date(format, &date, offset){
+        Ok(x) => Ok(vec![LuaData::Str(x.into_bytes())]),
+        Err(spec) => Err(arg_error(interpreter, 0, "date", &format!("invalid conversion specifier '%{}'", spec))),
+    }
+}
+
+/// Formats a date with the conversions of C99's strftime in the "C" locale.
+/// The offset is None for UTC, otherwise the seconds local time is ahead of UTC.
+/// Returns the invalid conversion as the error
+fn format_date(format: &str, date: &Date, offset: Option<i64>) -> Result<String, String>{
+    let mut result = String::new();
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next(){
+        if c != '%'{
+            result.push(c);
+            continue;
+        }
+
+        let conversion = chars.next().ok_or_else(String::new)?;
+        // The 'E' and 'O' modifiers select alternative representations, which the "C" locale doesn't have
+        let conversion = match conversion{
+            'E' | 'O' => {
+                let valid = if conversion == 'E' {"cCxXyY"} else {"deHImMSuUVwWy"};
+
+                match chars.next(){
+                    Some(x) if valid.contains(x) => x,
+                    Some(x) => return Err(format!("{}{}", conversion, x)),
+                    None => return Err(conversion.to_string()),
+                }
+            },
+            x => x,
+        };
+
+        let hour12 = if date.hour % 12 == 0 {12} else {date.hour % 12};
+        let text = match conversion{
+            'a' => WEEKDAYS[date.wday as usize][..3].to_string(),
+            'A' => WEEKDAYS[date.wday as usize].to_string(),
+            'b' | 'h' => MONTHS[date.month as usize - 1][..3].to_string(),
+            'B' => MONTHS[date.month as usize - 1].to_string(),
+            'c' => format_date("%a %b %e %H:%M:%S %Y", date, offset)?,
+            'C' => format!("{:02}", date.year.div_euclid(100)),
+            'd' => format!("{:02}", date.day),
+            'D' | 'x' => format_date("%m/%d/%y", date, offset)?,
+            'e' => format!("{:2}", date.day),
+            'F' => format_date("%Y-%m-%d", date, offset)?,
+            'g' => format!("{:02}", date.iso_week().0.rem_euclid(100)),
+            'G' => date.iso_week().0.to_string(),
+            'H' => format!("{:02}", date.hour),
+            'I' => format!("{:02}", hour12),
+            'j' => format!("{:03}", date.yday + 1),
+            'm' => format!("{:02}", date.month),
+            'M' => format!("{:02}", date.min),
+            'n' => "\n".to_string(),
+            'p' => if date.hour < 12 {"AM"} else {"PM"}.to_string(),
+            'r' => format_date("%I:%M:%S %p", date, offset)?,
+            'R' => format_date("%H:%M", date, offset)?,
+            'S' => format!("{:02}", date.sec),
+            't' => "\t".to_string(),
+            'T' | 'X' => format_date("%H:%M:%S", date, offset)?,
+            'u' => ((date.wday + 6) % 7 + 1).to_string(),
+            'U' => format!("{:02}", (date.yday + 7 - date.wday) / 7),
+            'V' => format!("{:02}", date.iso_week().1),
+            'w' => date.wday.to_string(),
+            'W' => format!("{:02}", (date.yday + 7 - (date.wday + 6) % 7) / 7),
+            'y' => format!("{:02}", date.year.rem_euclid(100)),
+            'Y' => date.year.to_string(),
+            'z' => {
+                let offset = offset.unwrap_or(0);
+                let sign = if offset < 0 {'-'} else {'+'};
+
+                format!("{}{:02}{:02}", sign, offset.abs() / 3600, offset.abs() / 60 % 60)
+            },
+            'Z' => match offset{
+                None | Some(0) => "UTC".to_string(),
+                Some(_) => format_date("%z", date, offset)?,
+            },
+            '%' => "%".to_string(),
+            x => return Err(x.to_string()),
+        };
+
+        result.push_str(&text);
+    }
+
+    Ok(result)
+}
+
+/// os.difftime(t2, t1), the seconds from t1 to t2
+fn difftime(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let t2 = check_int(interpreter, &args, 0, "difftime")?;
+    let t1 = check_int(interpreter, &args, 1, "difftime")?;
+
+    Ok(vec![LuaData::Number(t2 as f64 - t1 as f64)])
+}
+
+/// os.getenv(name), the value of an environment variable or nil
+fn getenv(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let name = check_text(interpreter, &args, 0, "getenv")?;
+
+    Ok(vec![interpreter.environment().var(&name).map(|x| LuaData::Str(x.into_bytes())).unwrap_or(LuaData::Nil)])
+}
+
+/// os.remove(filename), deletes a file or an empty directory
+fn remove(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let filename = check_text(interpreter, &args, 0, "remove")?;
+
+    match interpreter.file_system().remove(&filename){
+        Ok(()) => Ok(vec![LuaData::Bool(true)]),
+        Err(e) => Ok(fail(&e, Some(&filename))),
+    }
+}
+
+/// os.rename(oldname, newname)
+fn rename(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let from = check_text(interpreter, &args, 0, "rename")?;
+    let to = check_text(interpreter, &args, 1, "rename")?;
+
+    match interpreter.file_system().rename(&from, &to){
+        Ok(()) => Ok(vec![LuaData::Bool(true)]),
+        Err(e) => Ok(fail(&e, Some(&from))),
+    }
+}
+
+/// os.tmpname(), the name of a new empty file in the temporary directory
+fn tmpname(_: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let count = TMP_NAMES.fetch_add(1, Ordering::Relaxed);
+    let name = env::temp_dir().join(format!("lua_{:x}_{:x}", process::id(), count)).display().to_string();
+    let options = OpenOptions {write: true, truncate: true, create: true, ..OpenOptions::default()};
+
+    match interpreter.file_system().open(&name, &options){
+        Ok(_) => Ok(vec![LuaData::Str(name.into_bytes())]),
+        Err(_) => Err(interpreter.error("unable to generate a unique filename".to_string())),
+    }
+}
+
+/// os.exit([code [, close]]), stops the script. True is a successful exit and false a failed one.
+/// The host gets an error with the kind 'ErrorKind::Exit' instead of the process exiting
+fn exit(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let code = match args.first(){
+        None | Some(LuaData::Nil) | Some(LuaData::Bool(true)) => 0,
+        Some(LuaData::Bool(false)) => 1,
+        Some(_) => check_int(interpreter, &args, 0, "exit")? as i32,
+    };
+
+    Err(interpreter.exit(code))
+}
@@ -0,0 +1,699 @@
+use super::*;
+use super::super::super::pattern::{self, Match, Capture};
+
+/// The 'string' table, also installed as the '__index' of the string metatable so 's:upper()' works
+pub struct StringLib{}
+
+impl Library for StringLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        let id = interpreter.register_lib("string", &[
+            ("len", len),
+            ("sub", sub),
+            ("upper", upper),
+            ("lower", lower),
+            ("rep", rep),
+            ("reverse", reverse),
+            ("byte", byte),
+            ("char", char),
+            ("format", format),
+            ("find", find),
+            ("match", match_),
+            ("gmatch", gmatch),
+            ("gsub", gsub),
+        ]);
+
+        let metatable = interpreter.create_table();
+        interpreter.get_table_mut(metatable).unwrap().assign_variable("__index".to_string(), LuaData::Table(id));
+        interpreter.set_string_metatable(Some(metatable));
+    }
+
+}
+
+/// The longest string 'rep' is allowed to create
+const MAX_STRING_SIZE: usize = i32::MAX as usize;
+
+fn len(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "len")?;
+
+    Ok(vec![LuaData::Int(s.len() as i64)])
+}
+
+/// string.sub(s, i [, j]), negative positions count from the end of the string
+fn sub(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "sub")?;
+    let start = start_position(check_int(interpreter, &args, 1, "sub")?, s.len());
+    let end = end_position(opt_int(interpreter, &args, 2, "sub", -1)?, s.len());
+
+    if start > end{
+        return Ok(vec![LuaData::Str(Vec::new())]);
+    }
+
+    Ok(vec![LuaData::Str(s[start - 1..end].to_vec())])
+}
+
+fn upper(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "upper")?;
+
+    Ok(vec![LuaData::Str(s.to_ascii_uppercase())])
+}
+
+fn lower(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "lower")?;
+
+    Ok(vec![LuaData::Str(s.to_ascii_lowercase())])
+}
+
+/// string.rep(s, n [, sep]), the copies are separated by sep
+fn rep(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "rep")?;
+    let count = check_int(interpreter, &args, 1, "rep")?;
+    let separator = match args.get(2){
+        None | Some(LuaData::Nil) => Vec::new(),
+        Some(_) => check_string(interpreter, &args, 2, "rep")?,
+    };
+
+    if count <= 0{
+        return Ok(vec![LuaData::Str(Vec::new())]);
+    }
+
+    let size = (s.len() + separator.len()).checked_mul(count as usize);
+    if size.is_none_or(|size| size > MAX_STRING_SIZE){
+        return Err(interpreter.error("resulting string too large".to_string()));
+    }
+
+    Ok(vec![LuaData::Str(vec![s; count as usize].join(separator.as_slice()))])
+}
+
+fn reverse(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let mut s = check_string(interpreter, &args, 0, "reverse")?;
+    s.reverse();
+
+    Ok(vec![LuaData::Str(s)])
+}
+
+/// string.byte(s [, i [, j]]), the codes of the bytes from i to j
+fn byte(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "byte")?;
+    let first = opt_int(interpreter, &args, 1, "byte", 1)?;
+    let start = start_position(first, s.len());
+    let end = end_position(opt_int(interpreter, &args, 2, "byte", first)?, s.len());
+
+    if start > end{
+        return Ok(Vec::new());
+    }
+
+    Ok(s[start - 1..end].iter().map(|x| LuaData::Int(*x as i64)).collect())
+}
+
+/// string.char(...), a string made of the given bytes
+fn char(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let mut bytes = Vec::new();
+
+    for index in 0..args.len(){
+        match check_int(interpreter, &args, index, "char")?{
+            x @ 0..=255 => bytes.push(x as u8),
+            _ => return Err(arg_error(interpreter, index, "char", "value out of range")),
+        }
+    }
+
+    Ok(vec![LuaData::Str(bytes)])
+}
+
+/// string.find(s, pattern [, init [, plain]]), the start and end of the match followed by its captures
+fn find(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "find")?;
+    let pattern = check_string(interpreter, &args, 1, "find")?;
+    let init = start_position(opt_int(interpreter, &args, 2, "find", 1)?, s.len());
+
+    if init > s.len() + 1{
+        return Ok(vec![LuaData::Nil]);
+    }
+
+    let plain = args.get(3).is_some_and(|x| x.to_bool()) || pattern::is_plain(&pattern);
+    let m = if plain{
+        find_plain(&s, &pattern, init - 1)
+    }else{
+        pattern_result(interpreter, pattern::find(&s, &pattern, init - 1))?
+    };
+
+    match m{
+        Some(m) => {
+            let mut results = vec![LuaData::Int(m.start as i64 + 1), LuaData::Int(m.end as i64)];
+            results.extend(m.captures.iter().map(|capture| capture_value(&s, capture)));
+
+            Ok(results)
+        },
+        None => Ok(vec![LuaData::Nil]),
+    }
+}
+
+/// string.match(s, pattern [, init]), the captures of the first match
+fn match_(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "match")?;
+    let pattern = check_string(interpreter, &args, 1, "match")?;
+    let init = start_position(opt_int(interpreter, &args, 2, "match", 1)?, s.len());
+
+    if init > s.len() + 1{
+        return Ok(vec![LuaData::Nil]);
+    }
+
+    match pattern_result(interpreter, pattern::find(&s, &pattern, init - 1))?{
+        Some(m) => Ok(capture_values(&s, &m)),
+        None => Ok(vec![LuaData::Nil]),
+    }
+}
+
+/// string.gmatch(s, pattern [, init]), an iterator over the captures of each match.
+/// A '^' doesn't anchor the pattern since that would stop the iteration.
+/// The position of the iterator is kept in a table bound to it
+fn gmatch(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "gmatch")?;
+    let pattern = check_string(interpreter, &args, 1, "gmatch")?;
+    let init = start_position(opt_int(interpreter, &args, 2, "gmatch", 1)?, s.len()).min(s.len() + 1);
+
+    let state = interpreter.create_table();
+    interpreter.get_table_mut(state).unwrap().assign_variable("position".to_string(), LuaData::Int(init as i64 - 1));
+
+    let bound = vec![LuaData::Str(s), LuaData::Str(pattern), LuaData::Table(state)];
+    let id = interpreter.func_manager.register_func("gmatch".to_string(), FunctionDef::Bound(gmatch_next, bound));
+
+    Ok(vec![LuaData::Func(id)])
+}
+
+fn gmatch_next(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let (s, pattern, state) = match (&args[0], &args[1], &args[2]){
+        (LuaData::Str(s), LuaData::Str(pattern), LuaData::Table(state)) => (s.as_slice(), pattern.as_slice(), *state),
+        _ => unreachable!("gmatch iterators are bound to their subject, pattern and state"),
+    };
+
+    let table = interpreter.get_table_mut(state).unwrap();
+    let mut position = table.get_variable("position".to_string()).and_then(|x| x.to_int()).unwrap_or(0) as usize;
+    let last_match = table.get_variable("last".to_string()).and_then(|x| x.to_int()).map(|x| x as usize);
+
+    while position <= s.len(){
+        // An empty match right after the previous match is skipped
+        let m = pattern_result(interpreter, pattern::match_at(s, pattern, position))?;
+
+        if let Some(m) = m.filter(|m| Some(m.end) != last_match){
+            let table = interpreter.get_table_mut(state).unwrap();
+            table.assign_variable("position".to_string(), LuaData::Int(m.end as i64));
+            table.assign_variable("last".to_string(), LuaData::Int(m.end as i64));
+
+            return Ok(capture_values(s, &m));
+        }
+
+        position += 1;
+    }
+
+    interpreter.get_table_mut(state).unwrap().assign_variable("position".to_string(), LuaData::Int(position as i64));
+
+    Ok(vec![LuaData::Nil])
+}
+
+/// string.gsub(s, pattern, repl [, n]), replaces the first n matches. The replacement is a string
+/// where '%0' to '%9' stand for the captures, a table indexed by the first capture or a function
+/// called with the captures. Returns the new string and the number of matches
+fn gsub(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "gsub")?;
+    let pattern = check_string(interpreter, &args, 1, "gsub")?;
+    let replacement = match args.get(2){
+        Some(x @ LuaData::Table(_)) | Some(x @ LuaData::Func(_)) => x.clone(),
+        Some(_) => LuaData::Str(check_string(interpreter, &args, 2, "gsub")
+            .map_err(|_| type_error(interpreter, &args, 2, "gsub", "string/function/table"))?),
+        None => return Err(type_error(interpreter, &args, 2, "gsub", "string/function/table")),
+    };
+    let max_count = opt_int(interpreter, &args, 3, "gsub", i64::MAX)?;
+
+    let subject = s.as_slice();
+    let (anchored, pattern) = match pattern.strip_prefix(b"^"){
+        Some(pattern) => (true, pattern),
+        None => (false, pattern.as_slice()),
+    };
+    let mut result = Vec::new();
+    let mut position = 0;
+    let mut last_match = None;
+    let mut count = 0;
+
+    while count < max_count{
+        let m = pattern_result(interpreter, pattern::match_at(subject, pattern, position))?;
+
+        match m.filter(|m| Some(m.end) != last_match){
+            Some(m) => {
+                count += 1;
+                result.extend(replace(interpreter, subject, &m, &replacement)?);
+                position = m.end;
+                last_match = Some(m.end);
+            },
+            None if position < subject.len() => {
+                result.push(subject[position]);
+                position += 1;
+            },
+            None => break,
+        }
+
+        if anchored{
+            break;
+        }
+    }
+
+    result.extend(&subject[position..]);
+
+    Ok(vec![LuaData::Str(result), LuaData::Int(count)])
+}
+
+/// The text that replaces a match in 'gsub', false or nil keep the original text
+fn replace(interpreter: &mut Interpreter, subject: &[u8], m: &Match, replacement: &LuaData) -> Result<Vec<u8>, LuaError>{
+    let whole = &subject[m.start..m.end];
+    let values = capture_values(subject, m);
+
+    let value = match replacement{
+        LuaData::Str(_) => return expand_replacement(interpreter, replacement, whole, &values),
+        LuaData::Table(_) => interpreter.index(replacement, &values[0])
+            .map_err(|e| interpreter.operand_error(e, &[replacement], &[]))?,
+        _ => interpreter.call_metamethod(replacement, values)?,
+    };
+
+    match value{
+        LuaData::Nil | LuaData::Bool(false) => Ok(whole.to_vec()),
+        LuaData::Str(x) => Ok(x),
+        x if x.is_number() => Ok(x.to_bytes()),
+        x => Err(interpreter.error(format!("invalid replacement value (a {})", x.type_name()))),
+    }
+}
+
+/// Expands '%0' to '%9' and '%%' in a replacement string, '%1' is the whole match if there are no captures
+fn expand_replacement(interpreter: &Interpreter, replacement: &LuaData, whole: &[u8], captures: &[LuaData]) -> Result<Vec<u8>, LuaError>{
+    let replacement = replacement.to_bytes();
+    let mut result = Vec::new();
+    let mut bytes = replacement.iter();
+
+    while let Some(&byte) = bytes.next(){
+        if byte != b'%'{
+            result.push(byte);
+            continue;
+        }
+
+        match bytes.next(){
+            Some(b'%') => result.push(b'%'),
+            Some(b'0') => result.extend(whole),
+            Some(&x @ b'1'..=b'9') => match captures.get((x - b'1') as usize){
+                Some(capture) => result.extend(capture.to_bytes()),
+                None => return Err(interpreter.error(format!("invalid capture index %{} in replacement string", x as char))),
+            },
+            _ => return Err(interpreter.error("invalid use of '%' in replacement string".to_string())),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Finds the first occurrence of the text at or after the start, without any special characters
+fn find_plain(subject: &[u8], text: &[u8], start: usize) -> Option<Match>{
+    (start..=subject.len())
+        .find(|position| subject[*position..].starts_with(text))
+        .map(|position| Match {start: position, end: position + text.len(), captures: Vec::new()})
+}
+
+/// The captured values of a match, or the whole match if the pattern has no captures
+fn capture_values(subject: &[u8], m: &Match) -> Vec<LuaData>{
+    if m.captures.is_empty(){
+        vec![LuaData::Str(subject[m.start..m.end].to_vec())]
+    }else{
+        m.captures.iter().map(|capture| capture_value(subject, capture)).collect()
+    }
+}
+
+fn capture_value(subject: &[u8], capture: &Capture) -> LuaData{
+    match capture{
+        Capture::Text(start, end) => LuaData::Str(subject[*start..*end].to_vec()),
+        Capture::Position(position) => LuaData::Int(*position as i64),
+    }
+}
+
+/// Turns the errors of malformed patterns into lua errors
+fn pattern_result<T>(interpreter: &Interpreter, result: Result<T, String>) -> Result<T, LuaError>{
+    result.map_err(|message| interpreter.error(message))
+}
+
+/// The flags, width and precision of a conversion in 'format'
+#[derive(Default)]
+struct Spec{
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// string.format(format, ...), follows C's printf with lua's '%q' added
+fn format(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let format = check_string(interpreter, &args, 0, "format")?;
+    let mut bytes = format.iter().peekable();
+    let mut result = Vec::new();
+    let mut index = 0;
+
+    while let Some(&byte) = bytes.next(){
+        if byte != b'%'{
+            result.push(byte);
+            continue;
+        }
+
+        if bytes.peek() == Some(&&b'%'){
+            bytes.next();
+            result.push(b'%');
+            continue;
+        }
+
+        // Read the whole conversion first so errors can show it
+        let mut conversion = String::from("%");
+        while let Some(&&byte) = bytes.peek(){
+            conversion.push(byte as char);
+            bytes.next();
+
+            if !b"-+ #0123456789.".contains(&byte){
+                break;
+            }
+        }
+
+        index += 1;
+        if index >= args.len(){
+            return Err(arg_error(interpreter, index, "format", "no value"));
+        }
+
+        let (spec, kind) = match parse_spec(&conversion){
+            Some(x) => x,
+            None => return Err(interpreter.error(format!("invalid conversion '{}' to 'format'", conversion))),
+        };
+
+        let text = match kind{
+            'd' | 'i' => {
+                let x = check_int(interpreter, &args, index, "format")?;
+                let sign = sign(x < 0, &spec);
+
+                pad(sign, integer_digits(x.unsigned_abs(), 10, false, &spec).as_bytes(), &spec, spec.precision.is_none())
+            },
+            'u' | 'o' | 'x' | 'X' => {
+                let x = check_int(interpreter, &args, index, "format")? as u64;
+                let (radix, prefix) = match kind{
+                    'o' => (8, ""),
+                    'x' => (16, "0x"),
+                    'X' => (16, "0X"),
+                    _ => (10, ""),
+                };
+                let mut digits = integer_digits(x, radix, kind == 'X', &spec);
+
+                if spec.alternate && kind == 'o' && !digits.starts_with('0'){
+                    digits.insert(0, '0');
+                }
+                let prefix = if spec.alternate && x != 0 {prefix} else {""};
+
+                pad(prefix, digits.as_bytes(), &spec, spec.precision.is_none())
+            },
+            'c' => {
+                let x = check_int(interpreter, &args, index, "format")?;
+
+                pad("", &[x as u8], &spec, false)
+            },
+            'e' | 'E' | 'f' | 'F' | 'g' | 'G' | 'a' | 'A' => {
+                let x = check_number(interpreter, &args, index, "format")?.to_num().unwrap();
+                let sign = sign(x.is_sign_negative(), &spec);
+                let body = format_float_conversion(x.abs(), kind, &spec);
+
+                pad(sign, body.as_bytes(), &spec, x.is_finite())
+            },
+            's' => {
+                let mut s = interpreter.tostring(&args[index])?;
+                if let Some(precision) = spec.precision{
+                    s.truncate(precision);
+                }
+
+                pad("", &s, &spec, false)
+            },
+            'q' => quote(interpreter, &args, index)?,
+            _ => unreachable!("parse_spec only accepts known conversions"),
+        };
+
+        result.extend(text);
+    }
+
+    Ok(vec![LuaData::Str(result)])
+}
+
+/// Parses a conversion like "%-08.3f" into its spec and conversion character.
+/// Widths and precisions have at most 2 digits and '%q' takes no modifiers
+fn parse_spec(conversion: &str) -> Option<(Spec, char)>{
+    let kind = conversion.chars().last()?;
+    let body = &conversion[1..conversion.len() - kind.len_utf8()];
+
+    if !"diucoxXeEfFgGaAsq".contains(kind) || (kind == 'q' && !body.is_empty()){
+        return None;
+    }
+
+    let mut spec = Spec::default();
+    let flags_end = body.find(|c| !"-+ #0".contains(c)).unwrap_or(body.len());
+    for flag in body[..flags_end].chars(){
+        match flag{
+            '-' => spec.left = true,
+            '+' => spec.plus = true,
+            ' ' => spec.space = true,
+            '#' => spec.alternate = true,
+            _ => spec.zero = true,
+        }
+    }
+
+    let (width, precision) = match body[flags_end..].split_once('.'){
+        Some((width, precision)) => (width, Some(precision)),
+        None => (&body[flags_end..], None),
+    };
+
+    let parse_number = |digits: &str| -> Option<usize>{
+        match digits.len(){
+            0 => Some(0),
+            1 | 2 if digits.chars().all(|c| c.is_ascii_digit()) => digits.parse().ok(),
+            _ => None,
+        }
+    };
+
+    spec.width = parse_number(width)?;
+    spec.precision = match precision{
+        Some(x) => Some(parse_number(x)?),
+        None => None,
+    };
+
+    Some((spec, kind))
+}
+
+fn sign(negative: bool, spec: &Spec) -> &'static str{
+    if negative{
+        "-"
+    }else if spec.plus{
+        "+"
+    }else if spec.space{
+        " "
+    }else{
+        ""
+    }
+}
+
+/// The digits of an integer, with at least as many digits as the precision
+fn integer_digits(x: u64, radix: u32, upper: bool, spec: &Spec) -> String{
+    let digits = match (radix, upper){
+        (8, _) => format!("{:o}", x),
+        (16, false) => format!("{:x}", x),
+        (16, true) => format!("{:X}", x),
+        _ => x.to_string(),
+    };
+
+    match spec.precision{
+        // Like C, a precision of 0 prints nothing for 0
+        Some(0) if x == 0 => String::new(),
+        Some(precision) => format!("{:0>1$}", digits, precision),
+        None => digits,
+    }
+}
+
+/// Pads the text to the width of the spec, zeros go between the prefix and the body.
+/// Like C, the width counts bytes
+fn pad(prefix: &str, body: &[u8], spec: &Spec, zero_allowed: bool) -> Vec<u8>{
+    let padding = spec.width.saturating_sub(prefix.len() + body.len());
+    let prefix = prefix.as_bytes();
+
+    if spec.left{
+        [prefix, body, &b" ".repeat(padding)].concat()
+    }else if spec.zero && zero_allowed{
+        [prefix, &b"0".repeat(padding), body].concat()
+    }else{
+        [&b" ".repeat(padding), prefix, body].concat()
+    }
+}
+
+/// Formats a positive float for '%e', '%f', '%g' and '%a' and their upper case versions
+fn format_float_conversion(x: f64, kind: char, spec: &Spec) -> String{
+    let upper = kind.is_ascii_uppercase();
+
+    let text = if x.is_nan(){
+        "nan".to_string()
+    }else if x.is_infinite(){
+        "inf".to_string()
+    }else{
+        match kind.to_ascii_lowercase(){
+            'e' => format_exponent(x, spec.precision.unwrap_or(6), spec.alternate),
+            'f' => {
+                let text = format!("{:.*}", spec.precision.unwrap_or(6), x);
+                if spec.alternate && !text.contains('.') {format!("{}.", text)} else {text}
+            },
+            'g' => format_general_conversion(x, spec.precision.unwrap_or(6), spec.alternate),
+            _ => format_hex_float(x, spec.precision),
+        }
+    };
+
+    if upper {text.to_ascii_uppercase()} else {text}
+}
+
+/// Formats a float like C's "%.{precision}e", i.e. "1.500000e+02"
+fn format_exponent(x: f64, precision: usize, alternate: bool) -> String{
+    let scientific = format!("{:.*e}", precision, x);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let point = if alternate && precision == 0 {"."} else {""};
+
+    format!("{}{}e{}{:02}", mantissa, point, if exponent < 0 {'-'} else {'+'}, exponent.abs())
+}
+
+/// Formats a float like C's "%.{precision}g", the alternate form keeps trailing zeros
+fn format_general_conversion(x: f64, precision: usize, alternate: bool) -> String{
+    if !alternate{
+        return format_general(x, precision);
+    }
+
+    let precision = precision.max(1);
+    let exponent = if x == 0.0{
+        0
+    }else{
+        let scientific = format!("{:.*e}", precision - 1, x);
+        scientific.split_once('e').unwrap().1.parse().unwrap()
+    };
+
+    let text = if exponent < -4 || exponent >= precision as i32{
+        format_exponent(x, precision - 1, true)
+    }else{
+        format!("{:.*}", (precision as i32 - 1 - exponent) as usize, x)
+    };
+
+    if text.contains('.') {text} else {format!("{}.", text)}
+}
+
+/// Formats a positive float like C's "%a", i.e. "0x1.8p+1"
+fn format_hex_float(x: f64, precision: Option<usize>) -> String{
+    let bits = x.to_bits();
+    let biased_exponent = ((bits >> 52) & 0x7ff) as i64;
+    let mut fraction = bits & ((1 << 52) - 1);
+
+    if biased_exponent == 0 && fraction == 0{
+        return match precision{
+            Some(precision) if precision > 0 => format!("0x0.{}p+0", "0".repeat(precision)),
+            _ => "0x0p+0".to_string(),
+        };
+    }
+
+    // Subnormal numbers have no implicit leading 1
+    let (mut lead, exponent) = if biased_exponent == 0 {(0, -1022)} else {(1, biased_exponent - 1023)};
+
+    let digits = match precision{
+        None => format!("{:013x}", fraction).trim_end_matches('0').to_string(),
+        Some(precision) if precision >= 13 => format!("{:013x}{}", fraction, "0".repeat(precision - 13)),
+        Some(precision) => {
+            // Round half to even to the requested number of hex digits
+            let shift = (13 - precision) * 4;
+            let full = (lead << 52) | fraction;
+            let half = 1u64 << (shift - 1);
+            let remainder = full & ((1 << shift) - 1);
+            let mut rounded = full >> shift;
+
+            if remainder > half || (remainder == half && rounded & 1 == 1){
+                rounded += 1;
+            }
+
+            lead = rounded >> (precision * 4);
+            fraction = rounded & ((1 << (precision * 4)) - 1);
+
+            if precision == 0 {String::new()} else {format!("{:01$x}", fraction, precision)}
+        },
+    };
+
+    let point = if digits.is_empty() {""} else {"."};
+
+    format!("0x{:x}{}{}p{:+}", lead, point, digits, exponent)
+}
+
+/// Formats a value for '%q' so that lua can read it back
+fn quote(interpreter: &Interpreter, args: &[LuaData], index: usize) -> Result<Vec<u8>, LuaError>{
+    let text = match &args[index]{
+        LuaData::Str(x) => return Ok(quote_string(x)),
+        LuaData::Int(i64::MIN) => "0x8000000000000000".to_string(),
+        LuaData::Int(x) => x.to_string(),
+        LuaData::Number(x) if x.is_nan() => "(0/0)".to_string(),
+        LuaData::Number(x) if x.is_infinite() => if *x < 0.0 {"-1e9999"} else {"1e9999"}.to_string(),
+        LuaData::Number(x) => format!("{}{}", if x.is_sign_negative() {"-"} else {""}, format_hex_float(x.abs(), None)),
+        LuaData::Nil | LuaData::Bool(_) => args[index].to_string(),
+        _ => return Err(arg_error(interpreter, index, "format", "value has no literal form")),
+    };
+
+    Ok(text.into_bytes())
+}
+
+/// Quotes a string, other bytes than control characters are written as they are
+fn quote_string(bytes: &[u8]) -> Vec<u8>{
+    let mut result = vec![b'"'];
+
+    for (index, &byte) in bytes.iter().enumerate(){
+        let next_is_digit = bytes.get(index + 1).is_some_and(|x| x.is_ascii_digit());
+
+        match byte{
+            b'"' | b'\\' => result.extend([b'\\', byte]),
+            b'\n' => result.extend(b"\\\n"),
+            b'\r' => result.extend(b"\\r"),
+            // Control characters are written as decimal escapes, padded if a digit follows
+            x if x < 32 || x == 127 => {
+                let escape = if next_is_digit {format!("\\{:03}", x)} else {format!("\\{}", x)};
+                result.extend(escape.into_bytes());
+            },
+            x => result.push(x),
+        }
+    }
+
+    result.push(b'"');
+    result
+}
+
+/// Converts a lua start position to a 1-based position, negative positions count from the end
+fn start_position(position: i64, len: usize) -> usize{
+    let len = len as i64;
+
+    if position > 0{
+        position as usize
+    }else if position == 0 || position < -len{
+        1
+    }else{
+        (len + position + 1) as usize
+    }
+}
+
+/// Converts a lua end position to a 1-based position that is at most the length
+fn end_position(position: i64, len: usize) -> usize{
+    let len = len as i64;
+
+    if position > len{
+        len as usize
+    }else if position >= 0{
+        position as usize
+    }else if position < -len{
+        0
+    }else{
+        (len + position + 1) as usize
+    }
+}
+
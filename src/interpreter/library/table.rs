@@ -0,0 +1,336 @@
+use super::*;
+use super::super::super::UnOp;
+
+/// The 'table' table
+pub struct TableLib{}
+
+impl Library for TableLib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        interpreter.register_lib("table", &[
+            ("insert", insert),
+            ("remove", remove),
+            ("concat", concat),
+            ("sort", sort),
+            ("unpack", unpack),
+            ("pack", pack),
+            ("move", move_),
+        ]);
+    }
+
+}
+
+/// The most values 'unpack' can return, like lua's stack limit
+const MAX_UNPACK: i64 = 1_000_000;
+
+/// Sorts of arrays larger than this are refused
+const MAX_SORT: i64 = i32::MAX as i64;
+
+/// table.insert(t, [pos,] value), the elements from pos are moved up
+fn insert(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let table = check_table(interpreter, &args, 0, "insert")?;
+    let end = length(interpreter, &table)? + 1;
+
+    match args.len(){
+        2 => set(interpreter, &table, end, args[1].clone())?,
+        3 => {
+            let position = check_int(interpreter, &args, 1, "insert")?;
+            if position < 1 || position > end{
+                return Err(arg_error(interpreter, 1, "insert", "position out of bounds"));
+            }
+
+            for index in (position + 1..=end).rev(){
+                let value = get(interpreter, &table, index - 1)?;
+                set(interpreter, &table, index, value)?;
+            }
+
+            set(interpreter, &table, position, args[2].clone())?;
+        },
+        _ => return Err(interpreter.error("wrong number of arguments to 'insert'".to_string()).with_kind(ErrorKind::ArityMismatch)),
+    }
+
+    Ok(Vec::new())
+}
+
+/// table.remove(t [, pos]), returns the removed element and moves the ones after it down
+fn remove(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let table = check_table(interpreter, &args, 0, "remove")?;
+    let size = length(interpreter, &table)?;
+    let mut position = opt_int(interpreter, &args, 1, "remove", size)?;
+
+    // The position can be one past the end, or 0 for an empty table
+    if position != size && (position < 1 || position > size + 1){
+        return Err(arg_error(interpreter, 1, "remove", "position out of bounds"));
+    }
+
+    let removed = get(interpreter, &table, position)?;
+
+    while position < size{
+        let value = get(interpreter, &table, position + 1)?;
+        set(interpreter, &table, position, value)?;
+        position += 1;
+    }
+
+    set(interpreter, &table, position, LuaData::Nil)?;
+
+    Ok(vec![removed])
+}
+
+/// table.concat(t [, sep [, i [, j]]]), the elements from i to j joined by sep
+fn concat(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let table = check_table(interpreter, &args, 0, "concat")?;
+    let separator = match args.get(1){
+        None | Some(LuaData::Nil) => Vec::new(),
+        Some(_) => check_string(interpreter, &args, 1, "concat")?,
+    };
+    let start = opt_int(interpreter, &args, 2, "concat", 1)?;
+    let end = match args.get(3){
+        None | Some(LuaData::Nil) => length(interpreter, &table)?,
+        Some(_) => check_int(interpreter, &args, 3, "concat")?,
+    };
+
+    let mut parts = Vec::new();
+    let mut index = start;
+
+    while index <= end{
+        match get(interpreter, &table, index)?{
+            LuaData::Str(x) => parts.push(x),
+            x if x.is_number() => parts.push(x.to_bytes()),
+            x => return Err(interpreter.error(format!("invalid value (at index {}) in table for 'concat'", index))
+                .with_kind(misuse_kind(&x, None))),
+        }
+
+        // Stop before the index overflows when the range ends at the largest integer
+        if index == i64::MAX{
+            break;
+        }
+        index += 1;
+    }
+
+    Ok(vec![LuaData::Str(parts.join(separator.as_slice()))])
+}
+
+/// table.unpack(t [, i [, j]]), the elements from i to j
+pub fn unpack(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let table = args.first().cloned().unwrap_or(LuaData::Nil);
+    let start = opt_int(interpreter, &args, 1, "unpack", 1)?;
+    let end = match args.get(2){
+        None | Some(LuaData::Nil) => length(interpreter, &table)?,
+        Some(_) => check_int(interpreter, &args, 2, "unpack")?,
+    };
+
+    if start > end{
+        return Ok(Vec::new());
+    }
+
+    if end.checked_sub(start).is_none_or(|count| count >= MAX_UNPACK){
+        return Err(interpreter.error("too many results to unpack".to_string()));
+    }
+
+    let mut values = Vec::new();
+    for index in start..=end{
+        values.push(get(interpreter, &table, index)?);
+    }
+
+    Ok(values)
+}
+
+/// table.pack(...), a table with the arguments and their count in 'n'
+fn pack(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let id = interpreter.create_table();
+    let table = interpreter.get_table_mut(id).unwrap();
+    let count = args.len() as i64;
+
+    for (index, value) in args.into_iter().enumerate(){
+        table.set(LuaData::Int(index as i64 + 1), value).unwrap();
+    }
+    table.assign_variable("n".to_string(), LuaData::Int(count));
+
+    Ok(vec![LuaData::Table(id)])
+}
+
+/// table.move(a1, f, e, t [, a2]), copies a1[f..e] to a2[t..], the ranges can overlap
+fn move_(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let source = check_table(interpreter, &args, 0, "move")?;
+    let first = check_int(interpreter, &args, 1, "move")?;
+    let end = check_int(interpreter, &args, 2, "move")?;
+    let target = check_int(interpreter, &args, 3, "move")?;
+    let destination = match args.get(4){
+        None | Some(LuaData::Nil) => source.clone(),
+        Some(_) => check_table(interpreter, &args, 4, "move")?,
+    };
+
+    if end >= first{
+        if first <= 0 && end >= i64::MAX + first{
+            return Err(arg_error(interpreter, 2, "move", "too many elements to move"));
+        }
+
+        let count = end - first;
+        if target > i64::MAX - count{
+            return Err(arg_error(interpreter, 3, "move", "destination wrap around"));
+        }
+
+        // Copy backwards when the ranges overlap and the target is after the start
+        if target > end || target <= first || !source.raw_equals(&destination){
+            for offset in 0..=count{
+                let value = get(interpreter, &source, first + offset)?;
+                set(interpreter, &destination, target + offset, value)?;
+            }
+        }else{
+            for offset in (0..=count).rev(){
+                let value = get(interpreter, &source, first + offset)?;
+                set(interpreter, &destination, target + offset, value)?;
+            }
+        }
+    }
+
+    Ok(vec![destination])
+}
+
+/// table.sort(t [, comp]), sorts in place with '<' or a function that returns true if its first argument comes first
+fn sort(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let table = check_table(interpreter, &args, 0, "sort")?;
+    let comparator = match args.get(1){
+        None | Some(LuaData::Nil) => None,
+        Some(x @ LuaData::Func(_)) => Some(x.clone()),
+        Some(_) => return Err(type_error(interpreter, &args, 1, "sort", "function")),
+    };
+
+    let size = length(interpreter, &table)?;
+    if size > MAX_SORT{
+        return Err(arg_error(interpreter, 0, "sort", "array too big"));
+    }
+
+    if size > 1{
+        let mut values = Vec::new();
+        for index in 1..=size{
+            values.push(get(interpreter, &table, index)?);
+        }
+
+        let mut sorter = Sorter {interpreter, comparator};
+        sorter.sort(&mut values, 0, size as usize - 1)?;
+
+        for (index, value) in values.into_iter().enumerate(){
+            set(interpreter, &table, index as i64 + 1, value)?;
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+/// Lua's quicksort. Its bounds checks catch comparators that aren't consistent,
+/// which are reported as an "invalid order function" instead of reading past the array
+struct Sorter<'a>{
+    interpreter: &'a mut Interpreter,
+    comparator: Option<LuaData>,
+}
+
+impl<'a> Sorter<'a>{
+
+    fn less_than(&mut self, a: &LuaData, b: &LuaData) -> Result<bool, LuaError>{
+        match self.comparator{
+            Some(ref comparator) => {
+                let comparator = comparator.clone();
+                Ok(self.interpreter.call_metamethod(&comparator, vec![a.clone(), b.clone()])?.to_bool())
+            },
+            None => self.interpreter.less_than(a, b),
+        }
+    }
+
+    fn sort(&mut self, values: &mut [LuaData], mut low: usize, mut high: usize) -> Result<(), LuaError>{
+        while low < high{
+            if self.less_than(&values[high], &values[low])?{
+                values.swap(low, high);
+            }
+
+            if high - low == 1{
+                break;
+            }
+
+            // Put the median of the first, middle and last element in the middle
+            let mut pivot = low + (high - low) / 2;
+            if self.less_than(&values[pivot], &values[low])?{
+                values.swap(pivot, low);
+            }else if self.less_than(&values[high], &values[pivot])?{
+                values.swap(pivot, high);
+            }
+
+            if high - low == 2{
+                break;
+            }
+
+            values.swap(pivot, high - 1);
+            pivot = self.partition(values, low, high)?;
+
+            // Recurse into the smaller half and loop on the larger one
+            if pivot - low < high - pivot{
+                self.sort(values, low, pivot.saturating_sub(1))?;
+                low = pivot + 1;
+            }else{
+                self.sort(values, pivot + 1, high)?;
+                high = pivot - 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Partitions around the pivot at high - 1 and returns where the pivot ends up
+    fn partition(&mut self, values: &mut [LuaData], low: usize, high: usize) -> Result<usize, LuaError>{
+        let pivot = values[high - 1].clone();
+        let mut i = low;
+        let mut j = high - 1;
+
+        loop{
+            i += 1;
+            while self.less_than(&values[i], &pivot)?{
+                if i == high - 1{
+                    return Err(self.invalid_order());
+                }
+                i += 1;
+            }
+
+            j -= 1;
+            while self.less_than(&pivot, &values[j])?{
+                if j < i{
+                    return Err(self.invalid_order());
+                }
+                j -= 1;
+            }
+
+            if j < i{
+                values.swap(high - 1, i);
+                return Ok(i);
+            }
+
+            values.swap(i, j);
+        }
+    }
+
+    fn invalid_order(&self) -> LuaError{
+        self.interpreter.error("invalid order function for sorting".to_string())
+    }
+}
+
+fn check_table(interpreter: &Interpreter, args: &[LuaData], index: usize, func: &str) -> Result<LuaData, LuaError>{
+    match args.get(index){
+        Some(x @ LuaData::Table(_)) => Ok(x.clone()),
+        _ => Err(type_error(interpreter, args, index, func, "table")),
+    }
+}
+
+/// The length of a table, following the '__len' metamethod
+fn length(interpreter: &mut Interpreter, table: &LuaData) -> Result<i64, LuaError>{
+    match interpreter.unary_arith(&UnOp::Length, table)?.to_int(){
+        Some(x) => Ok(x),
+        None => Err(interpreter.error("object length is not an integer".to_string())),
+    }
+}
+
+fn get(interpreter: &mut Interpreter, table: &LuaData, index: i64) -> Result<LuaData, LuaError>{
+    interpreter.index(table, &LuaData::Int(index)).map_err(|e| interpreter.operand_error(e, &[table], &[]))
+}
+
+fn set(interpreter: &mut Interpreter, table: &LuaData, index: i64, value: LuaData) -> Result<(), LuaError>{
+    interpreter.set_index(table, LuaData::Int(index), value).map_err(|e| interpreter.operand_error(e, &[table], &[]))
+}
@@ -0,0 +1,316 @@
+use super::*;
+
+/// The 'utf8' table. Functions take a 'lax' flag that accepts code points up to 2^31 and surrogates
+pub struct Utf8Lib{}
+
+impl Library for Utf8Lib{
+
+    fn load(&self, interpreter: &mut Interpreter){
+        let id = interpreter.register_lib("utf8", &[
+            ("char", char),
+            ("codepoint", codepoint),
+            ("len", len),
+            ("offset", offset),
+        ]);
+
+        // 'codes' returns the same iterator each time, one for each mode
+        let strict = interpreter.func_manager.register_func("utf8.codes_iterator".to_string(), FunctionDef::Rust(|args, interpreter| codes_next(args, interpreter, true)));
+        let lax = interpreter.func_manager.register_func("utf8.codes_iterator".to_string(), FunctionDef::Rust(|args, interpreter| codes_next(args, interpreter, false)));
+        let codes = interpreter.func_manager.register_func("utf8.codes".to_string(), FunctionDef::Bound(codes, vec![LuaData::Func(strict), LuaData::Func(lax)]));
+
+        let table = interpreter.get_table_mut(id).unwrap();
+        table.assign_variable("codes".to_string(), LuaData::Func(codes));
+        // Matches one UTF-8 sequence
+        table.assign_variable("charpattern".to_string(), LuaData::Str(b"[\0-\x7F\xC2-\xFD][\x80-\xBF]*".to_vec()));
+    }
+
+}
+
+/// The largest code point 'utf8.char' and lax mode accept
+const MAX_UTF: i64 = 0x7FFF_FFFF;
+
+/// The largest code point that is valid unicode
+const MAX_UNICODE: u32 = 0x10FFFF;
+
+const INVALID: &str = "invalid UTF-8 code";
+
+/// Decodes the sequence at the start of the bytes like lua's utf8_decode, which allows sequences
+/// of up to 6 bytes. Returns the code point and the length, None for invalid or overlong sequences
+fn decode(s: &[u8], strict: bool) -> Option<(u32, usize)>{
+    const LIMITS: [u32; 6] = [!0, 0x80, 0x800, 0x10000, 0x200000, 0x4000000];
+
+    let mut c = *s.first()? as u32;
+    if c < 0x80{
+        return Some((c, 1));
+    }
+
+    let mut code = 0;
+    let mut count = 0;
+
+    while c & 0x40 != 0{
+        count += 1;
+        let next = *s.get(count)? as u32;
+        if next & 0xC0 != 0x80{
+            return None;
+        }
+
+        code = (code << 6) | (next & 0x3F);
+        c <<= 1;
+    }
+
+    if count > 5{
+        return None;
+    }
+
+    code |= (c & 0x7F) << (count * 5);
+    if code > MAX_UTF as u32 || code < LIMITS[count]{
+        return None;
+    }
+
+    if strict && (code > MAX_UNICODE || (0xD800..=0xDFFF).contains(&code)){
+        return None;
+    }
+
+    Some((code, count + 1))
+}
+
+/// Encodes a code point like lua's luaO_utf8esc. Surrogates and code points
+/// beyond unicode are encoded the same way, with up to 6 bytes
+fn encode(code: u32) -> Vec<u8>{
+    if code < 0x80{
+        return vec![code as u8];
+    }
+
+    let mut bytes = Vec::new();
+    let mut code = code;
+    // The largest value that fits in the first byte
+    let mut first_max = 0x3F;
+
+    loop{
+        bytes.push(0x80 | (code & 0x3F) as u8);
+        code >>= 6;
+        first_max >>= 1;
+
+        if code <= first_max{
+            break;
+        }
+    }
+
+    bytes.push(((!first_max << 1) | code) as u8);
+    bytes.reverse();
+
+    bytes
+}
+
+fn is_continuation(s: &[u8], index: usize) -> bool{
+    s.get(index).is_some_and(|x| x & 0xC0 == 0x80)
+}
+
+/// Converts a relative position, negative positions count from the end and ones before the start become 0
+fn relative_position(position: i64, len: usize) -> i64{
+    if position >= 0{
+        position
+    }else if position.unsigned_abs() > len as u64{
+        0
+    }else{
+        len as i64 + position + 1
+    }
+}
+
+/// utf8.char(...), the string with the code points
+fn char(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let mut bytes = Vec::new();
+
+    for index in 0..args.len(){
+        let code = check_int(interpreter, &args, index, "char")?;
+        if !(0..=MAX_UTF).contains(&code){
+            return Err(arg_error(interpreter, index, "char", "value out of range"));
+        }
+
+        bytes.extend(encode(code as u32));
+    }
+
+    Ok(vec![LuaData::Str(bytes)])
+}
+
+/// utf8.codepoint(s [, i [, j [, lax]]]), the code points of the characters that start between i and j
+fn codepoint(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "codepoint")?;
+    let s = s.as_slice();
+    let start = relative_position(opt_int(interpreter, &args, 1, "codepoint", 1)?, s.len());
+    let end = relative_position(opt_int(interpreter, &args, 2, "codepoint", start)?, s.len());
+    let strict = !args.get(3).is_some_and(|x| x.to_bool());
+
+    if start < 1{
+        return Err(arg_error(interpreter, 1, "codepoint", "out of bounds"));
+    }
+    if end > s.len() as i64{
+        return Err(arg_error(interpreter, 2, "codepoint", "out of bounds"));
+    }
+
+    let mut codes = Vec::new();
+    let mut position = start as usize - 1;
+
+    while (position as i64) < end{
+        match decode(&s[position..], strict){
+            Some((code, length)) => {
+                codes.push(LuaData::Int(code as i64));
+                position += length;
+            },
+            None => return Err(interpreter.error(INVALID.to_string())),
+        }
+    }
+
+    Ok(codes)
+}
+
+/// utf8.len(s [, i [, j [, lax]]]), the number of characters that start between i and j.
+/// Invalid input returns nil and the position of the first invalid byte
+fn len(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "len")?;
+    let s = s.as_slice();
+    let start = relative_position(opt_int(interpreter, &args, 1, "len", 1)?, s.len());
+    let end = relative_position(opt_int(interpreter, &args, 2, "len", -1)?, s.len());
+    let strict = !args.get(3).is_some_and(|x| x.to_bool());
+
+    if start < 1 || start - 1 > s.len() as i64{
+        return Err(arg_error(interpreter, 1, "len", "initial position out of bounds"));
+    }
+    if end > s.len() as i64{
+        return Err(arg_error(interpreter, 2, "len", "final position out of bounds"));
+    }
+
+    let mut count = 0;
+    let mut position = start as usize - 1;
+
+    while (position as i64) < end{
+        match decode(&s[position..], strict){
+            Some((_, length)) => position += length,
+            None => return Ok(vec![LuaData::Nil, LuaData::Int(position as i64 + 1)]),
+        }
+
+        count += 1;
+    }
+
+    Ok(vec![LuaData::Int(count)])
+}
+
+/// utf8.offset(s, n [, i]), the byte position where the n-th character counted from position i starts.
+/// n = 0 finds the start of the character that contains byte i
+fn offset(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "offset")?;
+    let s = s.as_slice();
+    let mut n = check_int(interpreter, &args, 1, "offset")?;
+    let default = if n >= 0 {1} else {s.len() as i64 + 1};
+    let position = relative_position(opt_int(interpreter, &args, 2, "offset", default)?, s.len());
+
+    if position < 1 || position - 1 > s.len() as i64{
+        return Err(arg_error(interpreter, 2, "offset", "position out of bounds"));
+    }
+
+    let mut position = position as usize - 1;
+
+    if n == 0{
+        while position > 0 && is_continuation(s, position){
+            position -= 1;
+        }
+    }else{
+        if is_continuation(s, position){
+            return Err(interpreter.error("initial position is a continuation byte".to_string()));
+        }
+
+        if n < 0{
+            while n < 0 && position > 0{
+                position -= 1;
+                while position > 0 && is_continuation(s, position){
+                    position -= 1;
+                }
+                n += 1;
+            }
+        }else{
+            n -= 1;
+            while n > 0 && position < s.len(){
+                position += 1;
+                while is_continuation(s, position){
+                    position += 1;
+                }
+                n -= 1;
+            }
+        }
+    }
+
+    if n == 0{
+        Ok(vec![LuaData::Int(position as i64 + 1)])
+    }else{
+        Ok(vec![LuaData::Nil])
+    }
+}
+
+/// utf8.codes(s [, lax]), an iterator, s and 0. Each call of the iterator returns the position and
+/// code point of the next character. The strict and lax iterators are bound before the arguments
+fn codes(args: Vec<LuaData>, interpreter: &mut Interpreter) -> Result<Vec<LuaData>, LuaError>{
+    let (iterators, args) = args.split_at(2);
+    let s = check_string(interpreter, args, 0, "codes")?;
+    let lax = args.get(1).is_some_and(|x| x.to_bool());
+
+    if is_continuation(s.as_slice(), 0){
+        return Err(arg_error(interpreter, 0, "codes", INVALID));
+    }
+
+    let iterator = iterators[if lax {1} else {0}].clone();
+
+    Ok(vec![iterator, LuaData::Str(s), LuaData::Int(0)])
+}
+
+fn codes_next(args: Vec<LuaData>, interpreter: &mut Interpreter, strict: bool) -> Result<Vec<LuaData>, LuaError>{
+    let s = check_string(interpreter, &args, 0, "for iterator")?;
+    let s = s.as_slice();
+    let mut position = match args.get(1).and_then(|x| x.to_int()){
+        Some(x) if x >= 0 => x as usize,
+        _ => return Ok(Vec::new()),
+    };
+
+    while position < s.len() && is_continuation(s, position){
+        position += 1;
+    }
+
+    if position >= s.len(){
+        return Ok(Vec::new());
+    }
+
+    match decode(&s[position..], strict){
+        Some((code, length)) if !is_continuation(s, position + length) => Ok(vec![LuaData::Int(position as i64 + 1), LuaData::Int(code as i64)]),
+        _ => Err(interpreter.error(INVALID.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod utf8_tests{
+
+    use super::{decode, encode};
+
+    #[test]
+    fn decode_test(){
+        assert_eq!(decode("é".as_bytes(), true), Some((233, 2)));
+        assert_eq!(decode("€x".as_bytes(), true), Some((8364, 3)));
+        assert_eq!(decode(&[0x80], true), None);
+        assert_eq!(decode(&[0xC3], true), None);
+        // Overlong encodings are never valid
+        assert_eq!(decode(&[0xC0, 0x80], false), None);
+    }
+
+    #[test]
+    fn lax_test(){
+        let surrogate = [0xED, 0xA0, 0x80];
+        let beyond_unicode = [0xF4, 0x90, 0x80, 0x80];
+        let largest = [0xFD, 0xBF, 0xBF, 0xBF, 0xBF, 0xBF];
+
+        assert_eq!(decode(&surrogate, true), None);
+        assert_eq!(decode(&surrogate, false), Some((0xD800, 3)));
+        assert_eq!(decode(&beyond_unicode, true), None);
+        assert_eq!(decode(&beyond_unicode, false), Some((0x110000, 4)));
+        assert_eq!(decode(&largest, false), Some((0x7FFFFFFF, 6)));
+        assert_eq!(encode(0x7FFFFFFF), largest.to_vec());
+        assert_eq!(encode(0xD800), surrogate.to_vec());
+    }
+}
@@ -0,0 +1,160 @@
+use super::Interpreter;
+use super::ops::OpError;
+use super::super::{data::LuaData, error::{LuaError, ErrorKind}};
+
+/// How many '__index' or '__newindex' handlers are followed before giving up
+const MAX_META_LOOP: usize = 2000;
+
+impl Interpreter{
+
+    /// Returns the id of a value's metatable, tables and userdata have their own and strings share one
+    pub fn get_metatable(&self, value: &LuaData) -> Option<i64>{
+        match value{
+            LuaData::Table(id) => self.get_table(*id)?.metatable,
+            LuaData::UserData(x) => self.get_userdata(x.id())?.metatable,
+            LuaData::Str(_) => self.string_metatable,
+            _ => None,
+        }
+    }
+
+    /// Sets the metatable shared by all strings
+    pub fn set_string_metatable(&mut self, metatable: Option<i64>){
+        self.string_metatable = metatable;
+    }
+
+    /// Looks up a metamethod such as '__eq' in the metatable of a value
+    pub fn get_metamethod(&self, value: &LuaData, event: &str) -> Option<LuaData>{
+        let metatable = self.get_table(self.get_metatable(value)?)?;
+
+        metatable.get_variable(event.to_string()).cloned()
+    }
+
+    /// Indexes a value, following the '__index' metamethod if the key isn't present.
+    /// Like in lua only function handlers are called, any other handler is indexed in turn
+    pub fn index(&mut self, value: &LuaData, key: &LuaData) -> Result<LuaData, OpError>{
+        let mut current = value.clone();
+
+        for _ in 0..MAX_META_LOOP{
+            let handler = if let LuaData::Table(id) = current{
+                let raw_value = self.get_table(id).unwrap().get(key);
+
+                if raw_value != LuaData::Nil{
+                    return Ok(raw_value);
+                }
+
+                match self.get_metamethod(&current, "__index"){
+                    Some(x) => x,
+                    None => return Ok(LuaData::Nil),
+                }
+            }else{
+                match self.get_metamethod(&current, "__index"){
+                    Some(x) => x,
+                    None => return Err(self.handler_index_error(value, &current)),
+                }
+            };
+
+            if let LuaData::Func(_) = handler{
+                return Ok(self.call_metamethod(&handler, vec![current, key.clone()])?);
+            }
+
+            current = handler;
+        }
+
+        Err(self.error("'__index' chain too long; possible loop".to_string()).into())
+    }
+
+    /// Assigns to a field of a value, following the '__newindex' metamethod if the key isn't present
+    pub fn set_index(&mut self, value: &LuaData, key: LuaData, new_value: LuaData) -> Result<(), OpError>{
+        let mut current = value.clone();
+
+        for _ in 0..MAX_META_LOOP{
+            let handler = if let LuaData::Table(id) = current{
+                let is_present = self.get_table(id).unwrap().get(&key) != LuaData::Nil;
+
+                match self.get_metamethod(&current, "__newindex"){
+                    Some(x) if !is_present => x,
+                    _ => return self.raw_set(id, key, new_value),
+                }
+            }else{
+                match self.get_metamethod(&current, "__newindex"){
+                    Some(x) => x,
+                    None => return Err(self.handler_index_error(value, &current)),
+                }
+            };
+
+            if let LuaData::Func(_) = handler{
+                self.call_metamethod(&handler, vec![current, key, new_value])?;
+                return Ok(());
+            }
+
+            current = handler;
+        }
+
+        Err(self.error("'__newindex' chain too long; possible loop".to_string()).into())
+    }
+
+    /// Sets a field of a table without invoking any metamethods
+    pub fn raw_set(&mut self, id: i64, key: LuaData, value: LuaData) -> Result<(), OpError>{
+        match self.get_table_mut(id).unwrap().set(key, value){
+            Ok(()) => Ok(()),
+            Err(message) => Err(self.error(message.to_string()).into()),
+        }
+    }
+
+    /// Calls a function, or the '__call' metamethod of a value with the value as the first argument
+    pub fn call(&mut self, func: &LuaData, args: Vec<LuaData>) -> Result<Vec<LuaData>, LuaError>{
+        self.try_call(func, args).map_err(|e| self.operand_error(e, &[func], &[]))
+    }
+
+    /// A '__call' handler that isn't a function is called the same way, with itself as another first argument
+    pub fn try_call(&mut self, func: &LuaData, mut args: Vec<LuaData>) -> Result<Vec<LuaData>, OpError>{
+        let mut current = func.clone();
+
+        for _ in 0..MAX_META_LOOP{
+            if let LuaData::Func(id) = current{
+                return Ok(self.call_function(id, args)?);
+            }
+
+            match self.get_metamethod(&current, "__call"){
+                Some(handler) => {
+                    args.insert(0, current);
+                    current = handler;
+                },
+                None => return Err(OpError::Operand("call", 0)),
+            }
+        }
+
+        Err(self.error("'__call' chain too long; possible loop".to_string()).into())
+    }
+
+    /// Calls a metamethod, only its first result is kept
+    pub fn call_metamethod(&mut self, handler: &LuaData, args: Vec<LuaData>) -> Result<LuaData, LuaError>{
+        Ok(self.call(handler, args)?.into_iter().next().unwrap_or(LuaData::Nil))
+    }
+
+    /// Converts a value to a string, using the '__tostring' and '__name' metafields of tables and userdata
+    pub fn tostring(&mut self, value: &LuaData) -> Result<Vec<u8>, LuaError>{
+        if let Some(handler) = self.get_metamethod(value, "__tostring"){
+            return match self.call_metamethod(&handler, vec![value.clone()])?{
+                LuaData::Str(x) => Ok(x),
+                _ => Err(self.error("'__tostring' must return a string".to_string())),
+            };
+        }
+
+        match (value, self.get_metamethod(value, "__name")){
+            (LuaData::Table(id), Some(LuaData::Str(name))) => Ok(format!("{}: 0x{:08x}", String::from_utf8_lossy(&name), id).into_bytes()),
+            (LuaData::UserData(x), Some(LuaData::Str(name))) => Ok(format!("{}: 0x{:08x}", String::from_utf8_lossy(&name), x.id()).into_bytes()),
+            _ => Ok(value.to_bytes()),
+        }
+    }
+
+    /// The error for indexing a value without '__index' or '__newindex'. When the value was
+    /// reached through a chain of handlers it has no name, so the type is reported directly
+    fn handler_index_error(&self, value: &LuaData, current: &LuaData) -> OpError{
+        if value.raw_equals(current){
+            OpError::Operand("index", 0)
+        }else{
+            self.error(format!("attempt to index a {} value", current.type_name())).with_kind(ErrorKind::TypeMismatch).into()
+        }
+    }
+}
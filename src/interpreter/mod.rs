@@ -1,17 +1,33 @@
 
-use std::io::prelude::*;
-use std::fs::File;
 use std::collections::{HashMap, HashSet};
-use super::{Token, Stmt, StmtType, Expr, BinOp, Keyword, parser};
-use super::{data::*, error::LuaError};
+use std::sync::{Arc, Mutex};
+use super::{Token, Span, Stmt, StmtType, Expr, ExprType, TableField, BinOp, Keyword, LocalAttrib};
+use super::{data::*, error::{LuaError, ErrorKind, SyntaxErrorKind}};
 
 use self::function::*;
 use self::table::Table;
 use self::library::*;
+use self::ops::{OpError, misuse_kind};
+use self::coroutine::Coroutine;
+use self::userdata::UserData;
+use self::callstack::Call;
+use self::random::Random;
+use self::fs::{FileSystem, StdFileSystem};
+use self::clock::{Clock, SystemClock, Environment, SystemEnvironment};
+use self::library::io::LuaFile;
 
 pub mod function;
 pub mod table;
 pub mod library;
+mod ops;
+mod meta;
+mod protected;
+pub mod coroutine;
+pub mod userdata;
+pub mod callstack;
+pub mod random;
+pub mod fs;
+pub mod clock;
 
 pub struct Interpreter{
     pub func_manager : FunctionManager,
@@ -20,8 +36,45 @@ pub struct Interpreter{
     table_count: i64,
     globals: HashMap<String, LuaData>,
     stack: Vec<HashMap<String, LuaData>>,
-    return_val: Option<LuaData>,
-    current_stmt_location: Option<String>
+    return_val: Option<Vec<LuaData>>,
+    current_stmt_location: Option<Span>,
+    coroutines: HashMap<i64, Coroutine>,
+    coroutine_count: i64,
+    /// Coroutines that nothing refers to anymore, removed the next time coroutines are collected
+    released_coroutines: Arc<Mutex<Vec<i64>>>,
+    /// Holds the place of the interpreter while a coroutine runs on it
+    placeholder: Option<Box<Interpreter>>,
+    current_coroutine: Option<i64>,
+    userdata: HashMap<i64, UserData>,
+    userdata_count: i64,
+    /// Userdata that nothing refers to anymore, finalized the next time userdata is collected
+    released_userdata: Arc<Mutex<Vec<i64>>>,
+    /// The message handlers of the active protected calls, None for 'pcall'
+    error_handlers: Vec<Option<LuaData>>,
+    /// The active function calls, the innermost last
+    calls: Vec<Call>,
+    /// Values of '<close>' variables, closed when their block exits
+    to_be_closed: Vec<LuaData>,
+    /// Set by 'break' until the loop exits
+    breaking: bool,
+    /// The source code of each chunk that was loaded, used to render errors
+    sources: HashMap<String, String>,
+    /// The metatable shared by all strings, lets 's:upper()' find the string library
+    string_metatable: Option<i64>,
+    /// The generator of 'math.random'
+    random: Random,
+    /// Where files are opened, replaced by hosts that sandbox scripts
+    file_system: Box<dyn FileSystem>,
+    /// The files opened by the io library, by the id of the userdata that represents them
+    files: HashMap<i64, LuaFile>,
+    /// The metatable of the files, holds the file methods
+    file_metatable: Option<i64>,
+    /// Where the os library gets the time from
+    clock: Box<dyn Clock>,
+    /// Where 'os.getenv' reads variables from
+    environment: Box<dyn Environment>,
+    /// Set by 'os.exit', the error it raises isn't caught by protected calls
+    exit_code: Option<i32>,
 }
 
 impl Interpreter{
@@ -36,14 +89,108 @@ impl Interpreter{
             stack: vec![HashMap::new()],
             return_val: None,
             current_stmt_location: None,
+            coroutines: HashMap::new(),
+            coroutine_count: 0,
+            released_coroutines: Arc::new(Mutex::new(Vec::new())),
+            placeholder: None,
+            current_coroutine: None,
+            userdata: HashMap::new(),
+            userdata_count: 0,
+            released_userdata: Arc::new(Mutex::new(Vec::new())),
+            error_handlers: Vec::new(),
+            calls: Vec::new(),
+            to_be_closed: Vec::new(),
+            breaking: false,
+            sources: HashMap::new(),
+            string_metatable: None,
+            random: Random::from_time(),
+            file_system: Box::new(StdFileSystem{}),
+            files: HashMap::new(),
+            file_metatable: None,
+            clock: Box::new(SystemClock::new()),
+            environment: Box::new(SystemEnvironment{}),
+            exit_code: None,
         }
     }
 
+    /// Remembers the source code of a chunk so errors in it can be rendered with snippets
+    pub fn add_source(&mut self, chunk: &str, src: &str){
+        self.sources.insert(chunk.to_string(), src.to_string());
+    }
+
+    pub fn source(&self, chunk: &str) -> Option<&str>{
+        self.sources.get(chunk).map(|src| src.as_str())
+    }
+
+    /// Seeds the generator of 'math.random', the same seeds always give the same numbers
+    pub fn seed_random(&mut self, seed1: i64, seed2: i64){
+        self.random.seed(seed1, seed2);
+    }
+
+    pub fn random(&mut self) -> &mut Random{
+        &mut self.random
+    }
+
+    /// Replaces the filesystem used by 'require' and the io and os libraries
+    pub fn set_file_system(&mut self, file_system: Box<dyn FileSystem>){
+        self.file_system = file_system;
+    }
+
+    pub fn file_system(&mut self) -> &mut dyn FileSystem{
+        self.file_system.as_mut()
+    }
+
+    /// Replaces the clock of the os library, i.e. with a 'FixedClock' to freeze time
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>){
+        self.clock = clock;
+    }
+
+    pub fn clock(&self) -> &dyn Clock{
+        self.clock.as_ref()
+    }
+
+    /// Replaces the environment variables 'os.getenv' sees
+    pub fn set_environment(&mut self, environment: Box<dyn Environment>){
+        self.environment = environment;
+    }
+
+    pub fn environment(&self) -> &dyn Environment{
+        self.environment.as_ref()
+    }
+
     pub fn load_library<T: Library>(&mut self, lib: T){
         lib.load(self);
     }
 
-    fn create_table(&mut self) -> i64{
+    /// Registers a function and stores it in a global variable
+    pub fn register_func(&mut self, name: &str, def: FunctionDef) -> i64{
+        let id = self.func_manager.register_func(name.to_string(), def);
+        self.globals.insert(name.to_string(), LuaData::Func(id));
+
+        id
+    }
+
+    /// Registers functions as the fields of a global table, i.e. 'coroutine.create'
+    pub fn register_lib(&mut self, lib: &str, funcs: &[(&str, RustFunc)]) -> i64{
+        let id = match self.globals.get(lib){
+            Some(LuaData::Table(id)) => *id,
+            _ => {
+                let id = self.create_table();
+                self.globals.insert(lib.to_string(), LuaData::Table(id));
+
+                id
+            },
+        };
+
+        for (name, func) in funcs{
+            let func_id = self.func_manager.register_func(format!("{}.{}", lib, name), FunctionDef::Rust(*func));
+            self.get_table_mut(id).unwrap().assign_variable(name.to_string(), LuaData::Func(func_id));
+        }
+
+        id
+    }
+
+    pub fn create_table(&mut self) -> i64{
         let id = self.table_count;
         self.table_count += 1;
         self.tables.insert(id, Table::new());
@@ -54,24 +201,13 @@ impl Interpreter{
     pub fn assign_variable(&mut self, name: String, data: LuaData, is_local: bool) -> Result<(), LuaError>{
         if name.contains('.'){
             let (path, variable_name) = split_name_path(name);
+            let table = self.read_variable(&path)?;
 
-            let table = self.get_variable(path)?.unwrap_or(&LuaData::Nil).clone();
-
-            match table{
-                LuaData::Table(id) => {
-                let table = self.get_table_mut(id);
-
-                    if let Some(table) = table{
-                        table.assign_variable(variable_name, data);
-                    }else{
-                        panic!("Error: found invalid table id: {}", id);
-                    }
-                }
-                x => return Err(self.error(format!("Expected table, found: {}", x))),
-            }
-            
-
-            return Ok(());
+            return match self.set_index(&table, LuaData::Str(variable_name.into_bytes()), data){
+                Ok(()) => Ok(()),
+                Err(OpError::Operand(_, _)) => Err(self.index_error(&table, &path)),
+                Err(OpError::Error(e)) => Err(e),
+            };
         }
 
         if is_local || self.stack.last().unwrap().contains_key(&name){
@@ -82,10 +218,52 @@ impl Interpreter{
             return Ok(());
         }
 
+        if let Some(env) = self.env(){
+            let env = LuaData::Table(env);
+
+            return match self.set_index(&env, LuaData::Str(name.clone().into_bytes()), data){
+                Ok(()) => Ok(()),
+                Err(OpError::Operand(_, _)) => Err(self.index_error(&env, &name)),
+                Err(OpError::Error(e)) => Err(e),
+            };
+        }
+
         self.globals.insert(name, data);
         Ok(())
     }
 
+    /// Reads a variable, fields of dotted names are looked up with metamethods
+    pub fn read_variable(&mut self, name: &str) -> Result<LuaData, LuaError>{
+        let mut fields = name.split('.');
+        let mut path = fields.next().unwrap().to_string();
+        let mut value = match self.env(){
+            // Globals in an environment table follow its metamethods
+            Some(env) if !self.stack.last().unwrap().contains_key(&path) => {
+                let env = LuaData::Table(env);
+
+                match self.index(&env, &LuaData::Str(path.clone().into_bytes())){
+                    Ok(x) => x,
+                    Err(OpError::Operand(_, _)) => return Err(self.index_error(&env, &path)),
+                    Err(OpError::Error(e)) => return Err(e),
+                }
+            },
+            _ => self.get_variable(path.clone())?.cloned().unwrap_or(LuaData::Nil),
+        };
+
+        for field in fields{
+            value = match self.index(&value, &LuaData::Str(field.as_bytes().to_vec())){
+                Ok(x) => x,
+                Err(OpError::Operand(_, _)) => return Err(self.index_error(&value, &path)),
+                Err(OpError::Error(e)) => return Err(e),
+            };
+
+            path.push('.');
+            path.push_str(field);
+        }
+
+        Ok(value)
+    }
+
     pub fn get_variable(&self, name: String) -> Result<Option<&LuaData>, LuaError>{
         if name.contains('.'){
             let (table, variable) = split_name_path(name);
@@ -97,27 +275,24 @@ impl Interpreter{
             return Ok(Some(var));
         }
 
-        Ok(self.globals.get(&name))
+        match self.env(){
+            Some(env) => Ok(self.get_table(env).unwrap().get_variable(name)),
+            None => Ok(self.globals.get(&name)),
+        }
     }
 
-     pub fn get_table_variable(&self, table: String, name: String) -> Result<Option<&LuaData>, LuaError>{
-        let table = self.get_variable(table)?;
-
-        if let Some(table) = table{
-            match table{
-                LuaData::Table(id) => {
-                    let table = self.get_table(*id);
+    pub fn get_table_variable(&self, path: String, name: String) -> Result<Option<&LuaData>, LuaError>{
+        match self.get_variable(path.clone())?{
+            Some(LuaData::Table(id)) => {
+                let table = self.get_table(*id);
 
-                    if let Some(table) = table{
-                        Ok(table.get_variable(name))
-                    }else{
-                        panic!("Error: found invalid table id: {}", id);
-                    }
+                if let Some(table) = table{
+                    Ok(table.get_variable(name))
+                }else{
+                    panic!("Error: found invalid table id: {}", id);
                 }
-                x => Err(self.error(format!("Expected table found: {}", x)))
             }
-        }else{
-            Ok(None)
+            x => Err(self.index_error(x.unwrap_or(&LuaData::Nil), &path))
         }
     }
 
@@ -136,15 +311,18 @@ impl Interpreter{
             return self.get_table_variable_mut(path, variable_name);
         }
 
-        if let Some(var) = self.stack.last_mut().unwrap().get_mut(name){
-            return Ok(Some(var));
+        if self.stack.last().unwrap().contains_key(name){
+            return Ok(self.stack.last_mut().unwrap().get_mut(name));
         }
 
-        Ok(self.globals.get_mut(name))
+        match self.env(){
+            Some(env) => Ok(self.get_table_mut(env).unwrap().get_variable_mut(name.to_string())),
+            None => Ok(self.globals.get_mut(name)),
+        }
     }
 
-    pub fn get_table_variable_mut(&mut self, table: String, name: String) -> Result<Option<&mut LuaData>, LuaError>{
-        let table = self.get_variable(table)?.unwrap_or(&LuaData::Nil).clone();
+    pub fn get_table_variable_mut(&mut self, path: String, name: String) -> Result<Option<&mut LuaData>, LuaError>{
+        let table = self.get_variable(path.clone())?.unwrap_or(&LuaData::Nil).clone();
 
         match table{
             LuaData::Table(id) => {
@@ -156,37 +334,93 @@ impl Interpreter{
                     panic!("Error: found invalid table id: {}", id);
                 }
             },
-            x => Err(self.error(format!("Expected table found: {}", x)))
+            x => Err(self.index_error(&x, &path))
         }
     }
     
     pub fn run_stmt(&mut self, stmt: &mut Stmt) -> Result<(), LuaError>{
-        if let Some(_) = self.return_val{
+        if self.return_val.is_some() || self.breaking{
             return Ok(());
         }
 
-        self.current_stmt_location = Some(stmt.location.clone());
+        self.current_stmt_location = Some(stmt.span.clone());
 
         match stmt.stmt_type{
             StmtType::FunctionDef(ref name, ref args, ref block) => self.handle_func_def(name, args, block),
             StmtType::If(ref expr, ref mut stmts, ref mut else_block) => self.run_if_stmt(expr, stmts, else_block),
-            StmtType::Assignment(ref name, ref expr, ref is_local) => self.handle_assignment(name, expr, *is_local),
-            StmtType::BinOp(_, _, _) | StmtType::Value(_) => panic!("Illegal Root Stmt: {:?}", stmt),
-            StmtType::Return(ref expr) => self.handle_return(expr),
+            StmtType::Assignment(ref names, ref exprs) => self.handle_assignment(names, exprs),
+            StmtType::Local(ref names, ref attribs, ref exprs) => self.handle_local(names, attribs, exprs),
+            StmtType::BinOp(_, _, _) | StmtType::UnOp(_, _) | StmtType::Value(_) | StmtType::Index(_, _) | 
+            StmtType::Table(_) => panic!("Illegal Root Stmt: {:?}", stmt),
+            StmtType::Return(ref exprs) => self.handle_return(exprs),
             StmtType::While(ref expr, ref mut stmts) => self.run_while_loop(expr, stmts),
+            StmtType::Do(ref mut block) => self.run_block(block),
             StmtType::For(ref name, ref init_val, ref end_val, ref incr, ref mut block) => self.run_for_loop(name, init_val, end_val, incr, block),
-            StmtType::FunctionCall(ref name, ref args) => {
-                match self.run_function_call(name, args.to_vec()){
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(e),
-                }
+            StmtType::FunctionCall(ref func, ref args) => self.run_function_call(func, args).map(|_| ()),
+            StmtType::MethodCall(ref object, ref name, ref args) => self.run_method_call(object, name, args).map(|_| ()),
+            StmtType::Break => {
+                self.breaking = true;
+                Ok(())
             },
             StmtType::EOF => Ok(()),
         }
     }
 
-    fn handle_return(&mut self, expr: &Expr) -> Result<(), LuaError>{
-        self.return_val = Some(self.evaluate_expr(expr)?);
+    /// Runs the statements of a block, then closes the '<close>' variables declared in it.
+    /// They are closed in reverse order, even if the block exits because of an error
+    pub fn run_block(&mut self, stmts: &mut [Stmt]) -> Result<(), LuaError>{
+        let to_be_closed_len = self.to_be_closed.len();
+        let mut result = Ok(());
+
+        for stmt in stmts.iter_mut(){
+            if self.return_val.is_some() || self.breaking{
+                break;
+            }
+
+            result = self.run_stmt(stmt);
+
+            if result.is_err(){
+                break;
+            }
+        }
+
+        // The message handler of 'xpcall' sees the error before the variables are closed
+        let mut result = result.map_err(|e| self.handle_error(e));
+
+        while self.to_be_closed.len() > to_be_closed_len{
+            let value = self.to_be_closed.pop().unwrap();
+            let error = match result{
+                Ok(()) => LuaData::Nil,
+                Err(ref e) => e.value(),
+            };
+
+            if let Err(e) = self.close_value(&value, error){
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// Calls the '__close' metamethod of a value, without disturbing a pending return or break
+    pub fn close_value(&mut self, value: &LuaData, error: LuaData) -> Result<(), LuaError>{
+        let return_val = self.return_val.take();
+        let breaking = self.breaking;
+        self.breaking = false;
+
+        let result = match self.get_metamethod(value, "__close"){
+            Some(handler) => self.call(&handler, vec![value.clone(), error]).map(|_| ()),
+            None => Err(self.error("metamethod 'close' is not callable".to_string())),
+        };
+
+        self.return_val = return_val;
+        self.breaking = breaking;
+
+        result
+    }
+
+    fn handle_return(&mut self, exprs: &[Expr]) -> Result<(), LuaError>{
+        self.return_val = Some(self.evaluate_exprs(exprs)?);
         Ok(())
     }
 
@@ -196,31 +430,12 @@ impl Interpreter{
             x => return Err(self.error(format!("Expected identifer but found {:?}", x))),
         };
 
-        let func = LuaFunc::new(args.to_vec(), stmts.to_vec());
+        // Functions share the environment of the chunk they are defined in
+        let func = LuaFunc::new(args.to_vec(), stmts.to_vec(), self.env());
 
         let id = self.func_manager.register_func(name.to_string(), FunctionDef::Lua(func));
 
-        if name.contains('.'){
-            let (path, variable_name) = split_name_path(name.to_string());
-
-            let table = self.get_variable(path)?.unwrap_or(&LuaData::Nil).clone();
-
-            //TODO do we need this?
-            match table{
-                LuaData::Table(table_id) => {
-                    let table = self.get_table_mut(table_id);
-
-                    if let Some(table) = table{
-                        table.assign_variable(variable_name, LuaData::Func(id));
-                    }else{
-                        panic!("Error: found invalid table id: {}", id);
-                    }
-                },
-                x => return Err(self.error(format!("Expected table, found {:?}", x))),
-            }
-        }
-
-        Ok(())
+        self.assign_variable(name.to_string(), LuaData::Func(id), false)
     }
 
     fn run_for_loop(&mut self, variable_name: &Token, init_val: &Expr, end_val: &Expr, incr: &Expr, block: &mut Vec<Stmt>) -> Result<(), LuaError>{
@@ -231,25 +446,34 @@ impl Interpreter{
 
         let init_val = self.evaluate_expr(init_val)?;
         let end_val = self.evaluate_expr(end_val)?;
-        let incr = self.expr_to_num(incr)?;
+        let incr = self.evaluate_expr(incr)?;
+
+        for (value, description) in &[(&init_val, "initial value"), (&end_val, "limit"), (&incr, "step")]{
+            if !value.is_number(){
+                return Err(self.error(format!("'for' {} must be a number", description)).with_kind(ErrorKind::TypeMismatch));
+            }
+        }
+
         self.assign_variable(name.to_string(), init_val, true)?;
         
         while !self.check_for_loop(&name, &end_val)?{
-            for stmt in block.iter_mut(){
-                self.run_stmt(stmt)?;
+            self.run_block(block)?;
+
+            if self.exit_loop(){
+                break;
             }
 
-            self.handle_for_incr(&name, incr)?;
+            self.handle_for_incr(&name, &incr)?;
         }
         
         Ok(())
     }
 
-    fn handle_for_incr(&mut self, var_name: &String, incr: f64) -> Result<(), LuaError>{
+    fn handle_for_incr(&mut self, var_name: &String, incr: &LuaData) -> Result<(), LuaError>{
         let curr_var = self.get_variable(var_name.to_string())?.unwrap_or(&LuaData::Nil).clone();
 
-        let new_value = curr_var.to_num() + incr;
-        self.assign_variable(var_name.to_string(), LuaData::Number(new_value), true)?;
+        let new_value = self.arith(&BinOp::Plus, &curr_var, incr)?;
+        self.assign_variable(var_name.to_string(), new_value, true)?;
 
         Ok(())
     }
@@ -257,30 +481,36 @@ impl Interpreter{
     fn check_for_loop(&mut self, var_name: &String, end_val: &LuaData) -> Result<bool, LuaError>{
         let curr_var = self.get_variable(var_name.to_string())?.unwrap_or(&LuaData::Nil).clone();
 
-        Ok(curr_var == *end_val)
+        Ok(curr_var.to_num() == end_val.to_num())
     }
 
     fn run_while_loop(&mut self, expr: &Expr, stmts: &mut Vec<Stmt>) -> Result<(), LuaError>{
         while self.should_run(expr)?{
-            for stmt in stmts.iter_mut(){
-                self.run_stmt(stmt)?;
+            self.run_block(stmts)?;
+
+            if self.exit_loop(){
+                break;
             }
         }
 
         Ok(())
     }
 
+    /// Returns true if the loop should stop because of 'break' or 'return'
+    fn exit_loop(&mut self) -> bool{
+        let breaking = self.breaking;
+        self.breaking = false;
+
+        breaking || self.return_val.is_some()
+    }
+
     fn run_if_stmt(&mut self, expr: &Expr, stmts: &mut Vec<Stmt>, else_block: &mut Option<Vec<Stmt>>) -> Result<(), LuaError>{
         let should_run = self.should_run(expr)?;
 
         if should_run{
-            for stmt in stmts{
-                self.run_stmt(stmt)?;
-            }
+            self.run_block(stmts)?;
         }else if let Some(else_block) = else_block{
-            for stmt in else_block{
-                self.run_stmt(stmt)?;
-            }
+            self.run_block(else_block)?;
         }
 
         Ok(())
@@ -290,15 +520,59 @@ impl Interpreter{
         Ok(self.evaluate_expr(expr)?.to_bool())
     }
 
-    fn handle_assignment(&mut self, name: &Token, expr: &Expr, is_local: bool) -> Result<(), LuaError>{
-         let name = match name{
-            Token::Identifier(n) => n,
-            _ => return Err(self.error(format!("Illegal Token: expected identifier but found {:?}", name))),
-        };
+    /// All values are evaluated before any are assigned, missing values are nil
+    fn handle_assignment(&mut self, targets: &[Expr], exprs: &[Expr]) -> Result<(), LuaError>{
+        let mut values = self.evaluate_exprs(exprs)?.into_iter();
+
+        for target in targets{
+            let value = values.next().unwrap_or(LuaData::Nil);
 
-        let value = self.evaluate_expr(expr)?;
+            match target.stmts[0].stmt_type{
+                StmtType::Value(ref tokens) => {
+                    let name = self.identifier_name(&tokens[0])?;
 
-        self.assign_variable(name.to_string(), value, is_local)
+                    self.assign_variable(name, value, false)?;
+                },
+                StmtType::Index(ref table_expr, ref key_expr) => {
+                    let table = self.evaluate_expr(table_expr)?;
+                    let key = self.evaluate_expr(key_expr)?;
+
+                    self.set_index(&table, key, value).map_err(|e| self.operand_error(e, &[&table], &[table_expr]))?;
+                },
+                ref x => return Err(self.error(format!("Cannot assign to {:?}", x))),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_local(&mut self, names: &[Token], attribs: &[LocalAttrib], exprs: &[Expr]) -> Result<(), LuaError>{
+        let mut values = self.evaluate_exprs(exprs)?.into_iter();
+
+        for (name, attrib) in names.iter().zip(attribs){
+            let name = self.identifier_name(name)?;
+            let value = values.next().unwrap_or(LuaData::Nil);
+
+            // nil and false don't need to be closed
+            if *attrib == LocalAttrib::Close && value.to_bool(){
+                if self.get_metamethod(&value, "__close").is_none(){
+                    return Err(self.error(format!("variable '{}' got a non-closable value", name)).with_kind(ErrorKind::TypeMismatch));
+                }
+
+                self.to_be_closed.push(value.clone());
+            }
+
+            self.assign_variable(name, value, true)?;
+        }
+
+        Ok(())
+    }
+
+    fn identifier_name(&self, token: &Token) -> Result<String, LuaError>{
+        match token{
+            Token::Identifier(name) => Ok(name.to_string()),
+            _ => Err(self.error(format!("Illegal Token: expected identifier but found {:?}", token))),
+        }
     }
 
     fn evaluate_expr(&mut self, expr: &Expr) -> Result<LuaData, LuaError>{
@@ -306,160 +580,195 @@ impl Interpreter{
         
         match stmt.stmt_type{
             StmtType::BinOp(ref operator, ref left, ref right) => Ok(self.evaluate_bin_op(operator, left, right)?),
+            StmtType::UnOp(ref operator, ref operand_expr) => {
+                let operand = self.evaluate_expr(operand_expr)?;
+
+                self.try_unary_arith(operator, &operand).map_err(|e| self.operand_error(e, &[&operand], &[operand_expr]))
+            },
             StmtType::Value(ref tokens) => Ok(self.evaluate_value_expr(tokens)?),
+            StmtType::Index(ref table_expr, ref key_expr) => {
+                let table = self.evaluate_expr(table_expr)?;
+                let key = self.evaluate_expr(key_expr)?;
+
+                self.index(&table, &key).map_err(|e| self.operand_error(e, &[&table], &[table_expr]))
+            },
+            StmtType::Table(ref fields) => self.evaluate_table(fields),
+            StmtType::FunctionCall(_, _) | StmtType::MethodCall(_, _, _) => {
+                Ok(self.evaluate_multiple(expr)?.into_iter().next().unwrap_or(LuaData::Nil))
+            },
             ref x => Err(self.error(format!("Couldn't evaluate expression: {:?}", x))),
         }
     }
 
+    /// Evaluates an expression to all of its values, only calls can have more than one
+    fn evaluate_multiple(&mut self, expr: &Expr) -> Result<Vec<LuaData>, LuaError>{
+        match expr.stmts[0].stmt_type{
+            StmtType::FunctionCall(ref func, ref args) => self.run_function_call(func, args),
+            StmtType::MethodCall(ref object, ref name, ref args) => self.run_method_call(object, name, args),
+            _ => Ok(vec![self.evaluate_expr(expr)?]),
+        }
+    }
+
+    /// Creates a table from a constructor. Positional fields are stored at 1, 2, 3..., 
+    /// a call in the last field adds all of its results
+    fn evaluate_table(&mut self, fields: &[TableField]) -> Result<LuaData, LuaError>{
+        let id = self.create_table();
+        let mut index = 1;
+
+        for (field_index, field) in fields.iter().enumerate(){
+            match field{
+                TableField::Positional(expr) => {
+                    let values = if field_index == fields.len() - 1 && expr.expr_type == ExprType::Call{
+                        self.evaluate_multiple(expr)?
+                    }else{
+                        vec![self.evaluate_expr(expr)?]
+                    };
+
+                    for value in values{
+                        self.table_set(id, LuaData::Int(index), value)?;
+                        index += 1;
+                    }
+                },
+                TableField::Keyed(key, value) => {
+                    let key = self.evaluate_expr(key)?;
+                    let value = self.evaluate_expr(value)?;
+
+                    self.table_set(id, key, value)?;
+                },
+            }
+        }
+
+        Ok(LuaData::Table(id))
+    }
+
+    fn table_set(&mut self, id: i64, key: LuaData, value: LuaData) -> Result<(), LuaError>{
+        let result = self.get_table_mut(id).unwrap().set(key, value);
+
+        result.map_err(|message| self.error(message.to_string()))
+    }
+
     fn evaluate_value_expr(&mut self, tokens: &Vec<Token>) -> Result<LuaData, LuaError>{
         let first_token = tokens.get(0).unwrap();
 
         Ok(match first_token{
-            Token::NumberLiteral(x) => LuaData::Number(x.clone()),
-            Token::StringLiteral(x) => LuaData::Str(x.clone()),
+            Token::NumberLiteral(x) => LuaData::Number(*x),
+            Token::IntegerLiteral(x) => LuaData::Int(*x),
+            Token::StringLiteral(x) => LuaData::Str(x.clone().into_bytes()),
             Token::Keyword(Keyword::True) => LuaData::Bool(true),
             Token::Keyword(Keyword::False) => LuaData::Bool(false),
-            Token::LeftBrace =>{
-                if tokens.len() == 2{
-                    let next = tokens.get(1);
-
-                    if next == Some(&Token::RightBrace){
-                        LuaData::Table(self.create_table())
-                    }else{
-                        return Err(self.error(format!("Expected right curly brace but found: {:?}", next)))
-                    }
-                }else{
-                    return Err(self.error(format!("Failed to parse value for left curly brace!")))
-                }
-            },
-            Token::Identifier(x) => {
-                match tokens.get(1){
-                    Some(Token::LeftParenthesis) => {
-                        let mut stmts = match parser::parse(tokens.to_vec()){
-                            Ok(x) => x,
-                            Err(e) => return Err(e),
-                        };
-
-                        match stmts.remove(0).stmt_type{
-                            StmtType::FunctionCall(ref name, ref args) => self.run_function_call(name, args.to_vec())?,
-                            x => return Err(self.error(format!("Expected to find function but found {:?}", x))),
-                        }
-                    },
-                    None => {
-                        if let Some(val) = self.get_variable(x.to_string())?{
-                            val.clone()
-                        }else{
-                            LuaData::Nil
-                        }
-                    },
-                    x => return Err(self.error(format!("Unexpected token: {:?}", x))),
-                }
-            },
+            Token::Identifier(x) => self.read_variable(x)?,
             _ => return Err(self.error(format!("Illegal Token: {:?} isn't a value", first_token))),
         })
     }
 
+    /// Evaluates a list of expressions. A function call at the end of the
+    /// list adds all of its results, every other expression adds one value
+    fn evaluate_exprs(&mut self, exprs: &[Expr]) -> Result<Vec<LuaData>, LuaError>{
+        let mut values = Vec::new();
+
+        for (index, expr) in exprs.iter().enumerate(){
+            if index == exprs.len() - 1 && expr.expr_type == ExprType::Call{
+                values.extend(self.evaluate_multiple(expr)?);
+            }else{
+                values.push(self.evaluate_expr(expr)?);
+            }
+        }
+
+        Ok(values)
+    }
+
     fn evaluate_bin_op(&mut self, operator: &BinOp, left: &Expr, right: &Expr) -> Result<LuaData, LuaError>{   
         Ok(match operator{
             BinOp::Concat => self.evaluate_str_binop(left, right)?,
-            BinOp::EqualEqual => self.evaluate_equallity_expr(left, right)?,
+            BinOp::EqualEqual | BinOp::NotEqual | BinOp::LessThan | BinOp::LessEqualThan | 
+            BinOp::GreaterThan | BinOp::GreaterEqualThan => self.evaluate_comparison_expr(operator, left, right)?,
             _ => self.evaluate_num_binop(operator, left, right)?,
         })
     }
 
-    fn evaluate_num_binop(&mut self, operator: &BinOp, left: &Expr, right: &Expr) -> Result<LuaData, LuaError>{
-        let left_num = self.expr_to_num(left)?;
+    fn evaluate_num_binop(&mut self, operator: &BinOp, left_expr: &Expr, right_expr: &Expr) -> Result<LuaData, LuaError>{
+        let left = self.evaluate_expr(left_expr)?;
+        let right = self.evaluate_expr(right_expr)?;
 
-        let right_num = self.expr_to_num(right)?;
-
-        Ok(match operator{
-            BinOp::Plus => LuaData::Number(left_num + right_num),
-            BinOp::Minus => LuaData::Number(left_num - right_num),
-            BinOp::Multiply => LuaData::Number(left_num * right_num),
-            BinOp::Divide => LuaData::Number(left_num / right_num),
-            BinOp::LessThan => LuaData::Bool(left_num < right_num),
-            BinOp::LessEqualThan => LuaData::Bool(left_num <= right_num),
-            BinOp::GreaterThan => LuaData::Bool(left_num > right_num),
-            BinOp::GreaterEqualThan => LuaData::Bool(left_num >= right_num),
-            BinOp::EqualEqual => LuaData::Bool(left_num == right_num),
-            _ => return Err(self.error(format!("Unknown num operator: {:?}!", operator))),
-        })
+        self.try_arith(operator, &left, &right).map_err(|e| self.operand_error(e, &[&left, &right], &[left_expr, right_expr]))
     }
 
-    fn evaluate_str_binop(&mut self, left: &Expr, right: &Expr) -> Result<LuaData, LuaError>{
-        let left_string = self.expr_to_string(left)?;
-        let right_string = self.expr_to_string(right)?;
+    fn evaluate_str_binop(&mut self, left_expr: &Expr, right_expr: &Expr) -> Result<LuaData, LuaError>{
+        let left = self.evaluate_expr(left_expr)?;
+        let right = self.evaluate_expr(right_expr)?;
 
-        Ok(LuaData::Str(format!("{}{}", left_string, right_string)))
+        self.concat(&left, &right).map_err(|e| self.operand_error(e, &[&left, &right], &[left_expr, right_expr]))
     }
 
-    fn evaluate_equallity_expr(&mut self, left: &Expr, right: &Expr) -> Result<LuaData, LuaError>{
+    fn evaluate_comparison_expr(&mut self, operator: &BinOp, left: &Expr, right: &Expr) -> Result<LuaData, LuaError>{
         let left = self.evaluate_expr(left)?;
         let right = self.evaluate_expr(right)?;
 
-        return Ok(LuaData::Bool(right.to_string() == left.to_string()));
+        Ok(LuaData::Bool(self.compare(operator, &left, &right)?))
     }
 
-    fn expr_to_string(&mut self, expr: &Expr) -> Result<String, LuaError>{
-        let value = self.evaluate_expr(expr)?;
+    fn run_function_call(&mut self, func_expr: &Expr, args: &[Expr]) -> Result<Vec<LuaData>, LuaError>{
+        let func = self.evaluate_expr(func_expr)?;
+        let arg_data = self.evaluate_exprs(args)?;
 
-        Ok(value.to_string())
+        self.try_call(&func, arg_data).map_err(|e| self.operand_error(e, &[&func], &[func_expr]))
     }
 
-     fn expr_to_num(&mut self, expr: &Expr) -> Result<f64, LuaError>{
-        let value = self.evaluate_expr(expr)?;
+    /// Calls 'object:name(args)', the object is passed as the first argument
+    fn run_method_call(&mut self, object_expr: &Expr, name: &str, args: &[Expr]) -> Result<Vec<LuaData>, LuaError>{
+        let object = self.evaluate_expr(object_expr)?;
+        let method = self.index(&object, &LuaData::Str(name.as_bytes().to_vec()))
+            .map_err(|e| self.operand_error(e, &[&object], &[object_expr]))?;
+
+        let mut arg_data = vec![object];
+        arg_data.extend(self.evaluate_exprs(args)?);
 
-        Ok(value.to_num())
+        match self.try_call(&method, arg_data){
+            Ok(x) => Ok(x),
+            Err(OpError::Operand(_, _)) => {
+                Err(self.error(format!("attempt to call a {} value (method '{}')", method.type_name(), name)).with_kind(ErrorKind::TypeMismatch))
+            },
+            Err(OpError::Error(e)) => Err(e),
+        }
     }
 
-    fn run_function_call(&mut self, name: &Token, args: Vec<Expr>) -> Result<LuaData, LuaError>{        
-        let name = match name{
-            Token::Identifier(string) => string,
-            _ => return Err(self.error(format!("Illegal Token: expected identifier but found {:?}", name))),
-        };
-        
-        let func_id = self.get_function_id_from_identifier(name)?;
-        let arg_data = self.evaluate_args(args)?;
+    pub fn call_function(&mut self, func_id: i64, arg_data: Vec<LuaData>) -> Result<Vec<LuaData>, LuaError>{
         let func = match self.func_manager.get_func(func_id){
             Some(x) => x,
-            None => return Err(self.error(format!("Unable to find function with name: {}", name))),   
+            None => return Err(self.error(format!("Unable to find function with id: {}", func_id))),
         }.clone();
 
+        let env = match func.def{
+            FunctionDef::Lua(ref x) => x.env,
+            _ => None,
+        };
+
         self.stack.push(HashMap::new());
-      
+        self.push_call(Some(func.name), !matches!(func.def, FunctionDef::Lua(_)), env);
+
         let result = match func.def{
-            FunctionDef::Rust(func) => func(arg_data, self)?,
-            FunctionDef::Lua(mut func) => func.execute(arg_data, self)?,
-        }.unwrap_or(LuaData::Nil);
+            FunctionDef::Rust(func) => func(arg_data, self),
+            FunctionDef::Lua(mut func) => func.execute(arg_data, self),
+            FunctionDef::Bound(func, mut bound) => {
+                bound.extend(arg_data);
+                func(bound, self)
+            },
+        };
+
+        // The traceback and the message handler of 'xpcall' see the stack where the error happened
+        let result = result.map_err(|e| {
+            let e = self.attach_traceback(e);
+            self.handle_error(e)
+        });
 
         self.stack.pop();
+        self.pop_call();
         self.return_val = None;
 
-        Ok(result)
-    }
-
-    fn get_function_id_from_identifier(&self, id: &String) -> Result<i64, LuaError>{
-        if id.contains('.'){
-            let(path, variable) = split_name_path(id.to_string());
-
-            if let Some(LuaData::Func(id)) = self.get_table_variable(path, variable)?{
-                Ok(*id)
-            }else{
-                Ok(-1)
-            }
-        }else{
-            Ok(self.func_manager.get_func_id(id))
-        }
-    }
-
-    fn evaluate_args(&mut self, exprs: Vec<Expr>) -> Result<Vec<LuaData>, LuaError>{
-        let mut data = Vec::new();
-
-        for expr in exprs{
-            data.push(self.evaluate_expr(&expr)?);
-        }
+        // The locals of the call are gone, so userdata only they referred to can be finalized
+        self.collect_userdata();
 
-        Ok(data)
+        result
     }
 
     pub fn load_module(&mut self, path: String, mut stmts: Vec<Stmt>) -> Result<LuaData, LuaError>{
@@ -469,30 +778,65 @@ impl Interpreter{
 
         self.modules_loaded.insert(path);
         self.stack.push(HashMap::new());
+        self.push_call(None, false, None);
 
-        for stmt in stmts.iter_mut(){
-            self.run_stmt(stmt)?;
+        let result = self.run_block(&mut stmts).map_err(|e| self.attach_traceback(e));
+        self.pop_call();
+        result?;
 
-            match self.return_val{
-                Some(LuaData::Table(_)) => break,
-                _ => (),
-            }
-        }
-
-        let return_value = self.return_val.clone().unwrap_or(LuaData::Nil);
-        self.return_val = None;
+        let return_value = self.return_val.take().unwrap_or_default().into_iter().next().unwrap_or(LuaData::Nil);
         self.stack.pop();
 
         Ok(return_value)
     }
 
+    /// Describes where a variable is stored for error messages, i.e. "global 'x'" or "field 'y'"
+    fn describe_variable(&self, name: &str) -> String{
+        if name.contains('.'){
+            let (_, field) = split_name_path(name.to_string());
+
+            format!("field '{}'", field)
+        }else if self.stack.last().unwrap().contains_key(name){
+            format!("local '{}'", name)
+        }else{
+            format!("global '{}'", name)
+        }
+    }
+
+    /// Describes where the value of an expression came from, if it is a variable or a constant
+    fn describe_expr(&self, expr: &Expr) -> Option<String>{
+        match expr.stmts[0].stmt_type{
+            StmtType::Value(ref tokens) if tokens.len() == 1 => match tokens[0]{
+                Token::Identifier(ref name) => Some(self.describe_variable(name)),
+                Token::StringLiteral(ref string) => Some(format!("constant '{}'", string)),
+                _ => None,
+            },
+            StmtType::Index(_, ref key) => match key.stmts[0].stmt_type{
+                StmtType::Value(ref tokens) => match tokens[0]{
+                    Token::StringLiteral(ref field) => Some(format!("field '{}'", field)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Creates the error for indexing a value that isn't a table, the path is the name of the value
+    fn index_error(&self, value: &LuaData, path: &str) -> LuaError{
+        let description = self.describe_variable(path);
+        let kind = misuse_kind(value, Some(&description));
+
+        self.error(format!("attempt to index a {} value ({})", value.type_name(), description)).with_kind(kind)
+    }
+
     fn error(&self, message: String) -> LuaError{
         LuaError::create_runtime(&message, self.current_stmt_location.clone())
     }   
 }
 
-fn error(message: String, location: Option<String>) -> LuaError{
-        LuaError::create_runtime(&message, location)
+fn error(message: String, span: Option<Span>) -> LuaError{
+        LuaError::create_runtime(&message, span)
 } 
 
 /// Splits a variable name multiple parts
@@ -523,24 +867,22 @@ pub fn split_name_path(input: String) -> (String, String){
     (path, variable)
 }
 
-fn load_file(name: &str) -> Result<String, LuaError>{
+fn load_file(interpreter: &mut Interpreter, name: &str) -> Result<String, LuaError>{
     let path = format!("assets/{}.lua", name);
 
-    let mut file = match File::open(&path){
-        Ok(x) => x,
-        Err(e) => return Err(error(format!("Failed to load file {}.lua: {}", name, e), Some(name.to_string())))
-    };
-    let mut contents = String::new();
-    match file.read_to_string(&mut contents){
-        Ok(_) => (),
-        Err(e) => return Err(error(format!("Failed to load file {}.lua: {}", name, e), Some(name.to_string())))
+    match interpreter.file_system().read_to_string(&path){
+        Ok(contents) => Ok(contents),
+        Err(e) => Err(io_error(name, e)),
     }
-    
-    Ok(contents)
+}
+
+fn io_error(name: &str, e: ::std::io::Error) -> LuaError{
+    error(format!("Failed to load file {}.lua: {}", name, e), Some(Span::chunk(name))).with_kind(ErrorKind::IoError).with_source(e)
 }
 
 fn load_module(name: String, src: String, interpreter: &mut Interpreter) -> Result<LuaData, LuaError>{
-    let tokens = match super::parser::scanner::scan(src){
+    interpreter.add_source(&name, &src);
+    let tokens = match super::parser::scanner::scan_chunk(src, &name){
         Ok(x) => x,
         Err(errors) => {
             let mut message = String::new();
@@ -550,10 +892,10 @@ fn load_module(name: String, src: String, interpreter: &mut Interpreter) -> Resu
                 message.push_str("\n")
             }
 
-            return Err(error(message, Some(name)));
+            return Err(error(message, Some(Span::chunk(&name))).with_kind(ErrorKind::SyntaxError(SyntaxErrorKind::InvalidToken)));
         },
     };
-    let stmts = super::parser::parse(tokens)?;
+    let stmts = super::parser::parse(tokens).map_err(|mut errors| errors.remove(0))?;
 
     Ok(interpreter.load_module(name, stmts)?)
 }
@@ -562,12 +904,7 @@ pub fn run(stmts: &mut Vec<Stmt>) -> Result<Interpreter, LuaError>{
     let mut interpreter = Interpreter::new();
     interpreter.load_library(library::new_std());
 
-    for mut stmt in stmts.iter_mut(){
-        match interpreter.run_stmt(&mut stmt){
-            Err(e) => return Err(e),
-            _ => (),
-        };
-    }
+    interpreter.run_block(stmts)?;
 
     Ok(interpreter)
 }
\ No newline at end of file
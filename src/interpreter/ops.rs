@@ -0,0 +1,339 @@
+use std::cmp::Ordering;
+use super::Interpreter;
+use super::super::{BinOp, UnOp, Expr, data::*, error::{LuaError, ErrorKind}};
+
+/// An error from applying an operator. Type errors are kept apart
+/// so that the caller can name the variable the bad operand came from
+pub enum OpError{
+    /// The action that failed (i.e. "perform arithmetic on") and the index of the bad operand
+    Operand(&'static str, usize),
+    Error(LuaError),
+}
+
+impl From<LuaError> for OpError{
+
+    fn from(error: LuaError) -> OpError{
+        OpError::Error(error)
+    }
+
+}
+
+impl Interpreter{
+
+    /// Applies an arithmetic or bitwise operator to two values
+    pub fn arith(&mut self, operator: &BinOp, left: &LuaData, right: &LuaData) -> Result<LuaData, LuaError>{
+        self.try_arith(operator, left, right).map_err(|e| self.operand_error(e, &[left, right], &[]))
+    }
+
+    pub fn unary_arith(&mut self, operator: &UnOp, operand: &LuaData) -> Result<LuaData, LuaError>{
+        self.try_unary_arith(operator, operand).map_err(|e| self.operand_error(e, &[operand], &[]))
+    }
+
+    /// Applies an arithmetic or bitwise operator. If the operands aren't numbers
+    /// the metamethod of the operator is called instead, i.e. '__add' for '+'
+    pub fn try_arith(&mut self, operator: &BinOp, left: &LuaData, right: &LuaData) -> Result<LuaData, OpError>{
+        if let Some(result) = self.raw_arith(operator, left, right)?{
+            return Ok(result);
+        }
+
+        if let Some(result) = self.call_binary_metamethod(arith_event(operator), left, right)?{
+            return Ok(result);
+        }
+
+        let bad_operand = if left.to_number().is_some() {1} else {0};
+
+        if is_bitwise(operator){
+            if left.to_number().is_some() && right.to_number().is_some(){
+                return Err(self.error("number has no integer representation".to_string()).into());
+            }
+
+            return Err(OpError::Operand("perform bitwise operation on", bad_operand));
+        }
+
+        Err(OpError::Operand("perform arithmetic on", bad_operand))
+    }
+
+    pub fn try_unary_arith(&mut self, operator: &UnOp, operand: &LuaData) -> Result<LuaData, OpError>{
+        match (operator, operand.to_number()){
+            (UnOp::Minus, Some(LuaData::Int(x))) => return Ok(LuaData::Int(x.wrapping_neg())),
+            (UnOp::Minus, Some(LuaData::Number(x))) => return Ok(LuaData::Number(-x)),
+            (UnOp::BitNot, Some(x)) => if let Some(x) = x.to_int(){
+                return Ok(LuaData::Int(!x));
+            },
+            (UnOp::Length, _) => if let LuaData::Str(x) = operand{
+                return Ok(LuaData::Int(x.len() as i64));
+            },
+            _ => (),
+        }
+
+        let event = match operator{
+            UnOp::Minus => "__unm",
+            UnOp::BitNot => "__bnot",
+            UnOp::Length => "__len",
+        };
+
+        // Like lua, unary metamethods get the operand twice
+        if let Some(handler) = self.get_metamethod(operand, event){
+            return Ok(self.call_metamethod(&handler, vec![operand.clone(), operand.clone()])?);
+        }
+
+        match (operator, operand){
+            (UnOp::Length, LuaData::Table(id)) => Ok(LuaData::Int(self.get_table(*id).unwrap().border())),
+            (UnOp::Length, _) => Err(OpError::Operand("get length of", 0)),
+            (UnOp::BitNot, x) if x.to_number().is_some() => Err(self.error("number has no integer representation".to_string()).into()),
+            (UnOp::BitNot, _) => Err(OpError::Operand("perform bitwise operation on", 0)),
+            (UnOp::Minus, _) => Err(OpError::Operand("perform arithmetic on", 0)),
+        }
+    }
+
+    /// Concatenates two strings or numbers, other values need the '__concat' metamethod
+    pub fn concat(&mut self, left: &LuaData, right: &LuaData) -> Result<LuaData, OpError>{
+        if is_concatable(left) && is_concatable(right){
+            let mut bytes = left.to_bytes();
+            bytes.extend(right.to_bytes());
+
+            return Ok(LuaData::Str(bytes));
+        }
+
+        match self.call_binary_metamethod("__concat", left, right)?{
+            Some(result) => Ok(result),
+            None => Err(OpError::Operand("concatenate", if is_concatable(left) {1} else {0})),
+        }
+    }
+
+    /// Applies an operator to two numbers (or numeric strings) without metamethods.
+    /// Returns None if an operand can't be converted
+    fn raw_arith(&self, operator: &BinOp, left: &LuaData, right: &LuaData) -> Result<Option<LuaData>, LuaError>{
+        if is_bitwise(operator){
+            let left = left.to_number().and_then(|x| x.to_int());
+            let right = right.to_number().and_then(|x| x.to_int());
+
+            return Ok(match (left, right){
+                (Some(left), Some(right)) => Some(LuaData::Int(bitwise(operator, left, right))),
+                _ => None,
+            });
+        }
+
+        match (left.to_number(), right.to_number()){
+            (Some(left), Some(right)) => self.num_arith(operator, &left, &right).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn num_arith(&self, operator: &BinOp, left: &LuaData, right: &LuaData) -> Result<LuaData, LuaError>{
+        // Integer operands stay integers, except for '/' and '^' which always give a float
+        if let (LuaData::Int(left), LuaData::Int(right)) = (left, right){
+            let (left, right) = (*left, *right);
+
+            match operator{
+                BinOp::Plus => return Ok(LuaData::Int(left.wrapping_add(right))),
+                BinOp::Minus => return Ok(LuaData::Int(left.wrapping_sub(right))),
+                BinOp::Multiply => return Ok(LuaData::Int(left.wrapping_mul(right))),
+                BinOp::Modulo if right == 0 => return Err(self.error("attempt to perform 'n%0'".to_string())),
+                BinOp::Modulo => return Ok(LuaData::Int(int_modulo(left, right))),
+                BinOp::FloorDivide if right == 0 => return Err(self.error("attempt to perform 'n//0'".to_string())),
+                BinOp::FloorDivide => return Ok(LuaData::Int(int_floor_divide(left, right))),
+                _ => (),
+            }
+        }
+
+        // Both operands are numbers at this point
+        let left_num = left.to_num().unwrap();
+        let right_num = right.to_num().unwrap();
+
+        Ok(LuaData::Number(match operator{
+            BinOp::Plus => left_num + right_num,
+            BinOp::Minus => left_num - right_num,
+            BinOp::Multiply => left_num * right_num,
+            BinOp::Divide => left_num / right_num,
+            BinOp::FloorDivide => (left_num / right_num).floor(),
+            BinOp::Modulo => float_modulo(left_num, right_num),
+            BinOp::Power => left_num.powf(right_num),
+            _ => return Err(self.error(format!("Unknown num operator: {:?}!", operator))),
+        }))
+    }
+
+    /// Turns an operator error into a lua error. If the expressions of the 
+    /// operands are given, type errors name the variable the bad operand came from
+    pub fn operand_error(&self, error: OpError, operands: &[&LuaData], exprs: &[&Expr]) -> LuaError{
+        match error{
+            OpError::Operand(action, index) => {
+                let description = exprs.get(index).and_then(|expr| self.describe_expr(expr));
+                let kind = misuse_kind(operands[index], description.as_deref());
+                let info = match description{
+                    Some(x) => format!(" ({})", x),
+                    None => String::new(),
+                };
+
+                self.error(format!("attempt to {} a {} value{}", action, operands[index].type_name(), info)).with_kind(kind)
+            },
+            OpError::Error(e) => e,
+        }
+    }
+
+    /// Applies a comparison operator, '>' and '>=' are evaluated by swapping the operands
+    pub fn compare(&mut self, operator: &BinOp, left: &LuaData, right: &LuaData) -> Result<bool, LuaError>{
+        match operator{
+            BinOp::EqualEqual => self.equals(left, right),
+            BinOp::NotEqual => Ok(!self.equals(left, right)?),
+            BinOp::LessThan => self.less_than(left, right),
+            BinOp::LessEqualThan => self.less_equal(left, right),
+            BinOp::GreaterThan => self.less_than(right, left),
+            BinOp::GreaterEqualThan => self.less_equal(right, left),
+            _ => Err(self.error(format!("Unknown comparison operator: {:?}!", operator))),
+        }
+    }
+
+    /// Lua equality, the '__eq' metamethod is only tried for two different tables
+    pub fn equals(&mut self, left: &LuaData, right: &LuaData) -> Result<bool, LuaError>{
+        if left.raw_equals(right){
+            return Ok(true);
+        }
+
+        if let (LuaData::Table(_), LuaData::Table(_)) = (left, right){
+            if let Some(result) = self.call_binary_metamethod("__eq", left, right)?{
+                return Ok(result.to_bool());
+            }
+        }
+
+        Ok(false)
+    }
+
+    pub fn less_than(&mut self, left: &LuaData, right: &LuaData) -> Result<bool, LuaError>{
+        match self.compare_primitives(left, right){
+            Some(ordering) => Ok(ordering == Some(Ordering::Less)),
+            None => self.compare_with_metamethod("__lt", left, right),
+        }
+    }
+
+    pub fn less_equal(&mut self, left: &LuaData, right: &LuaData) -> Result<bool, LuaError>{
+        match self.compare_primitives(left, right){
+            Some(ordering) => Ok(ordering == Some(Ordering::Less) || ordering == Some(Ordering::Equal)),
+            None => self.compare_with_metamethod("__le", left, right),
+        }
+    }
+
+    /// Orders two numbers or two strings (byte by byte), the inner option is None if a number is NaN.
+    /// Returns None if the values can't be compared without a metamethod
+    fn compare_primitives(&self, left: &LuaData, right: &LuaData) -> Option<Option<Ordering>>{
+        match (left, right){
+            (LuaData::Str(x), LuaData::Str(y)) => Some(Some(x.cmp(y))),
+            (x, y) if x.is_number() && y.is_number() => Some(compare_numbers(x, y)),
+            _ => None,
+        }
+    }
+
+    fn compare_with_metamethod(&mut self, event: &str, left: &LuaData, right: &LuaData) -> Result<bool, LuaError>{
+        match self.call_binary_metamethod(event, left, right)?{
+            Some(result) => Ok(result.to_bool()),
+            None if left.type_name() == right.type_name() => {
+                Err(self.error(format!("attempt to compare two {} values", left.type_name())).with_kind(ErrorKind::TypeMismatch))
+            },
+            None => Err(self.error(format!("attempt to compare {} with {}", left.type_name(), right.type_name())).with_kind(ErrorKind::TypeMismatch)),
+        }
+    }
+
+    /// Calls the metamethod of the first operand, or the second if the first doesn't have one.
+    /// Returns None if neither operand has the metamethod
+    fn call_binary_metamethod(&mut self, event: &str, left: &LuaData, right: &LuaData) -> Result<Option<LuaData>, LuaError>{
+        let handler = match self.get_metamethod(left, event){
+            Some(x) => x,
+            None => match self.get_metamethod(right, event){
+                Some(x) => x,
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some(self.call_metamethod(&handler, vec![left.clone(), right.clone()])?))
+    }
+}
+
+fn arith_event(operator: &BinOp) -> &'static str{
+    match operator{
+        BinOp::Plus => "__add",
+        BinOp::Minus => "__sub",
+        BinOp::Multiply => "__mul",
+        BinOp::Divide => "__div",
+        BinOp::FloorDivide => "__idiv",
+        BinOp::Modulo => "__mod",
+        BinOp::Power => "__pow",
+        BinOp::BitAnd => "__band",
+        BinOp::BitOr => "__bor",
+        BinOp::BitXor => "__bxor",
+        BinOp::ShiftLeft => "__shl",
+        BinOp::ShiftRight => "__shr",
+        _ => unreachable!("{:?} is not an arithmetic operator", operator),
+    }
+}
+
+fn is_bitwise(operator: &BinOp) -> bool{
+    matches!(operator, BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::ShiftLeft | BinOp::ShiftRight)
+}
+
+fn is_concatable(value: &LuaData) -> bool{
+    matches!(value, LuaData::Str(_) | LuaData::Int(_) | LuaData::Number(_))
+}
+
+/// Integer modulo, the result has the sign of the divisor
+fn int_modulo(left: i64, right: i64) -> i64{
+    let remainder = left.wrapping_rem(right);
+
+    if remainder != 0 && (remainder ^ right) < 0{
+        remainder + right
+    }else{
+        remainder
+    }
+}
+
+/// Float modulo, the result has the sign of the divisor
+fn float_modulo(left: f64, right: f64) -> f64{
+    let remainder = left % right;
+
+    if (remainder > 0.0 && right < 0.0) || (remainder < 0.0 && right > 0.0){
+        remainder + right
+    }else{
+        remainder
+    }
+}
+
+/// Integer division rounding towards minus infinity
+fn int_floor_divide(left: i64, right: i64) -> i64{
+    let quotient = left.wrapping_div(right);
+
+    if left.wrapping_rem(right) != 0 && (left ^ right) < 0{
+        quotient - 1
+    }else{
+        quotient
+    }
+}
+
+fn bitwise(operator: &BinOp, left: i64, right: i64) -> i64{
+    match operator{
+        BinOp::BitAnd => left & right,
+        BinOp::BitOr => left | right,
+        BinOp::BitXor => left ^ right,
+        BinOp::ShiftLeft => shift_left(left, right),
+        BinOp::ShiftRight => shift_left(left, right.wrapping_neg()),
+        _ => unreachable!("{:?} is not a bitwise operator", operator),
+    }
+}
+
+/// Logical shift, negative displacements shift to the right
+/// and shifting by 64 or more bits always results in zero
+fn shift_left(value: i64, displacement: i64) -> i64{
+    if displacement <= -64 || displacement >= 64{
+        0
+    }else if displacement >= 0{
+        ((value as u64) << displacement) as i64
+    }else{
+        ((value as u64) >> -displacement) as i64
+    }
+}
+
+/// The kind of error for misusing a value, nil variables are most likely undefined
+pub fn misuse_kind(value: &LuaData, description: Option<&str>) -> ErrorKind{
+    match (value, description){
+        (LuaData::Nil, Some(x)) if x.starts_with("global ") || x.starts_with("local ") => ErrorKind::UndefinedVariable,
+        _ => ErrorKind::TypeMismatch,
+    }
+}
@@ -0,0 +1,80 @@
+use super::Interpreter;
+use super::super::{data::LuaData, error::{LuaError, ErrorKind}};
+
+impl Interpreter{
+
+    /// Calls a function and catches its errors, returning the error value instead.
+    /// The message handler is called with the error value before the stack unwinds
+    pub fn protected_call(&mut self, func: &LuaData, args: Vec<LuaData>, handler: Option<LuaData>) -> Result<Vec<LuaData>, LuaData>{
+        let stack_len = self.stack.len();
+        let calls_len = self.calls.len();
+        let location = self.current_stmt_location.clone();
+
+        self.error_handlers.push(handler);
+        let result = self.call(func, args).map_err(|e| self.handle_error(e));
+        self.error_handlers.pop();
+
+        result.map_err(|e| {
+            self.stack.truncate(stack_len);
+            self.calls.truncate(calls_len);
+            self.current_stmt_location = location;
+            self.return_val = None;
+
+            e.value()
+        })
+    }
+
+    /// Calls the message handler of the innermost protected call, if it has one, and
+    /// replaces the error value with its result. Each error is only handled once
+    pub fn handle_error(&mut self, mut error: LuaError) -> LuaError{
+        let handler = match self.error_handlers.last(){
+            Some(Some(handler)) if !error.handled && self.exit_code.is_none() => handler.clone(),
+            _ => return error,
+        };
+
+        // Errors in the message handler aren't handled again
+        self.error_handlers.push(None);
+        let value = match self.call(&handler, vec![error.value()]){
+            Ok(values) => values.into_iter().next().unwrap_or(LuaData::Nil),
+            Err(_) => LuaData::Str("error in error handling".into()),
+        };
+        self.error_handlers.pop();
+
+        error.set_value(value);
+        error.handled = true;
+
+        error
+    }
+
+    /// Stops the script with an exit code, the error unwinds through protected calls to the host
+    pub fn exit(&mut self, code: i32) -> LuaError{
+        self.exit_code = Some(code);
+
+        self.exit_error(code)
+    }
+
+    /// The code passed to 'os.exit', if the script exited
+    pub fn exit_code(&self) -> Option<i32>{
+        self.exit_code
+    }
+
+    /// Raises the exit error again after a protected call caught it
+    pub fn check_exit(&self) -> Result<(), LuaError>{
+        match self.exit_code{
+            Some(code) => Err(self.exit_error(code)),
+            None => Ok(()),
+        }
+    }
+
+    fn exit_error(&self, code: i32) -> LuaError{
+        self.error(format!("exited with code {}", code)).with_kind(ErrorKind::Exit(code))
+    }
+
+    /// The position of the function at a level of the call stack, level 1 is the function
+    /// that called the current rust function. Used to add positions to error messages
+    pub fn error_position(&self, level: usize) -> Option<String>{
+        let index = self.calls.len().checked_sub(level)?;
+
+        self.calls.get(index)?.call_site.as_ref().map(|span| span.location())
+    }
+}
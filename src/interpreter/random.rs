@@ -0,0 +1,87 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The xoshiro256** generator used by 'math.random', seeded the same way as lua 5.4
+/// so a seed gives the same numbers as in lua
+pub struct Random{
+    state: [u64; 4],
+}
+
+impl Random{
+
+    pub fn new(seed1: i64, seed2: i64) -> Random{
+        let mut random = Random {state: [0; 4]};
+        random.seed(seed1, seed2);
+
+        random
+    }
+
+    /// A generator seeded from the current time
+    pub fn from_time() -> Random{
+        let (seed1, seed2) = time_seed();
+
+        Random::new(seed1, seed2)
+    }
+
+    pub fn seed(&mut self, seed1: i64, seed2: i64){
+        // The 0xff keeps the state from being all zeros
+        self.state = [seed1 as u64, 0xff, seed2 as u64, 0];
+
+        // Discard the first values to spread the seed over the state
+        for _ in 0..16{
+            self.next_integer();
+        }
+    }
+
+    pub fn next_integer(&mut self) -> u64{
+        let state = &mut self.state;
+        let result = state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let shifted = state[1] << 17;
+
+        state[2] ^= state[0];
+        state[3] ^= state[1];
+        state[1] ^= state[2];
+        state[0] ^= state[3];
+        state[2] ^= shifted;
+        state[3] = state[3].rotate_left(45);
+
+        result
+    }
+
+    /// A float in [0, 1) made from the top 53 bits
+    pub fn next_float(&mut self) -> f64{
+        (self.next_integer() >> 11) as f64 * (0.5f64).powi(53)
+    }
+
+    /// An integer in [0, n], without bias
+    pub fn project(&mut self, n: u64) -> u64{
+        let mut value = self.next_integer();
+
+        // If n + 1 is a power of 2 the low bits can be used directly
+        if n & n.wrapping_add(1) == 0{
+            return value & n;
+        }
+
+        // Otherwise take the bits of the smallest 2^b - 1 above n and retry when the value is too big
+        let mut limit = n;
+        for shift in [1, 2, 4, 8, 16, 32]{
+            limit |= limit >> shift;
+        }
+
+        loop{
+            value &= limit;
+            if value <= n{
+                return value;
+            }
+
+            value = self.next_integer();
+        }
+    }
+}
+
+/// Seeds that change between runs, from the time and the address of a local
+pub fn time_seed() -> (i64, i64){
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_nanos() as i64).unwrap_or(0);
+    let local = 0;
+
+    (time, &local as *const i32 as i64)
+}
@@ -1,28 +1,193 @@
 use std::collections::{HashMap};
-use super::super::data::LuaData;
+use super::super::data::{LuaData, ObjectRef};
 
+/// A lua value that can be used as a table key. Floats with an integer
+/// value are stored as integers so that `t[1]` and `t[1.0]` are the same entry
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TableKey{
+    Str(Vec<u8>),
+    Int(i64),
+    /// The bits of a float without an integer value
+    Float(u64),
+    Bool(bool),
+    Func(i64),
+    Table(i64),
+    /// Keeps the coroutine alive like any other reference
+    Thread(ObjectRef),
+    UserData(ObjectRef),
+}
+
+impl TableKey{
+
+    /// Returns None for values that can't be keys (nil and NaN)
+    pub fn from_data(data: &LuaData) -> Option<TableKey>{
+        Some(match data{
+            LuaData::Str(x) => TableKey::Str(x.clone()),
+            LuaData::Int(x) => TableKey::Int(*x),
+            LuaData::Number(x) if x.is_nan() => return None,
+            LuaData::Number(x) => match data.to_int(){
+                Some(x) => TableKey::Int(x),
+                None => TableKey::Float(x.to_bits()),
+            },
+            LuaData::Bool(x) => TableKey::Bool(*x),
+            LuaData::Func(x) => TableKey::Func(*x),
+            LuaData::Table(x) => TableKey::Table(*x),
+            LuaData::Thread(x) => TableKey::Thread(x.clone()),
+            LuaData::UserData(x) => TableKey::UserData(x.clone()),
+            LuaData::Nil => return None,
+        })
+    }
+
+    pub fn to_data(&self) -> LuaData{
+        match self{
+            TableKey::Str(x) => LuaData::Str(x.clone()),
+            TableKey::Int(x) => LuaData::Int(*x),
+            TableKey::Float(x) => LuaData::Number(f64::from_bits(*x)),
+            TableKey::Bool(x) => LuaData::Bool(*x),
+            TableKey::Func(x) => LuaData::Func(*x),
+            TableKey::Table(x) => LuaData::Table(*x),
+            TableKey::Thread(x) => LuaData::Thread(x.clone()),
+            TableKey::UserData(x) => LuaData::UserData(x.clone()),
+        }
+    }
+}
+
+/// A lua table. Entries are kept in insertion order so that they can be traversed with `next`.
+/// Assigning nil to a key leaves a dead entry behind, which keeps traversals valid while
+/// fields are cleared. Dead entries are removed when new keys are added
 #[derive(Clone, Debug, PartialEq)]
 pub struct Table{
-    vars: HashMap<String, LuaData>,
+    entries: Vec<(TableKey, LuaData)>,
+    indices: HashMap<TableKey, usize>,
+    dead_count: usize,
+    pub metatable: Option<i64>,
 }
 
 impl Table{
 
     pub fn new() -> Table{
-        let data = Table{vars : HashMap::new()};
-        
-        data
+        Table{entries: Vec::new(), indices: HashMap::new(), dead_count: 0, metatable: None}
     }
 
     pub fn assign_variable(&mut self, name: String, data: LuaData){
-        self.vars.insert(name, data);
+        self.set_key(TableKey::Str(name.into_bytes()), data);
     }
 
     pub fn get_variable(&self, name: String) -> Option<&LuaData>{
-        self.vars.get(&name)
+        self.get_key(&TableKey::Str(name.into_bytes()))
     }
 
     pub fn get_variable_mut(&mut self, name: String) -> Option<&mut LuaData>{
-        self.vars.get_mut(&name)
+        match self.indices.get(&TableKey::Str(name.into_bytes())){
+            Some(index) if self.entries[*index].1 != LuaData::Nil => Some(&mut self.entries[*index].1),
+            _ => None,
+        }
+    }
+
+    /// Gets the value of a key, nil if the key isn't in the table
+    pub fn get(&self, key: &LuaData) -> LuaData{
+        match TableKey::from_data(key){
+            Some(key) => self.get_key(&key).cloned().unwrap_or(LuaData::Nil),
+            None => LuaData::Nil,
+        }
     }
-}
\ No newline at end of file
+
+    /// Sets the value of a key, fails if the key is nil or NaN
+    pub fn set(&mut self, key: LuaData, value: LuaData) -> Result<(), &'static str>{
+        match TableKey::from_data(&key){
+            Some(key) => {
+                self.set_key(key, value);
+                Ok(())
+            },
+            None if key == LuaData::Nil => Err("index is nil"),
+            None => Err("index is NaN"),
+        }
+    }
+
+    /// Returns the entry after the given key, or the first entry if the key is nil.
+    /// The outer option is None if the key isn't in the table
+    pub fn next(&self, key: &LuaData) -> Option<Option<(LuaData, LuaData)>>{
+        let start = match key{
+            LuaData::Nil => 0,
+            x => *self.indices.get(&TableKey::from_data(x)?)? + 1,
+        };
+
+        Some(self.entries[start..].iter()
+            .find(|(_, value)| *value != LuaData::Nil)
+            .map(|(key, value)| (key.to_data(), value.clone())))
+    }
+
+    /// Finds a border of the table: an index n where t[n] isn't nil and t[n + 1] is nil (or 0 if t[1] is nil)
+    pub fn border(&self) -> i64{
+        let is_present = |index: i64| self.get_key(&TableKey::Int(index)).is_some();
+
+        if !is_present(1){
+            return 0;
+        }
+
+        // Find an upper bound that is nil, then binary search between the bounds
+        let mut lower = 1;
+        let mut upper = 2;
+
+        while is_present(upper){
+            lower = upper;
+
+            if upper > i64::MAX / 2{
+                return lower;
+            }
+
+            upper *= 2;
+        }
+
+        while upper - lower > 1{
+            let middle = lower + (upper - lower) / 2;
+
+            if is_present(middle){
+                lower = middle;
+            }else{
+                upper = middle;
+            }
+        }
+
+        lower
+    }
+
+    fn get_key(&self, key: &TableKey) -> Option<&LuaData>{
+        match self.indices.get(key){
+            Some(index) if self.entries[*index].1 != LuaData::Nil => Some(&self.entries[*index].1),
+            _ => None,
+        }
+    }
+
+    fn set_key(&mut self, key: TableKey, value: LuaData){
+        if let Some(index) = self.indices.get(&key){
+            let entry = &mut self.entries[*index].1;
+
+            if *entry != LuaData::Nil && value == LuaData::Nil{
+                self.dead_count += 1;
+            }else if *entry == LuaData::Nil && value != LuaData::Nil{
+                self.dead_count -= 1;
+            }
+
+            *entry = value;
+            return;
+        }
+
+        if value == LuaData::Nil{
+            return;
+        }
+
+        if self.dead_count > 16 && self.dead_count * 2 > self.entries.len(){
+            self.remove_dead_entries();
+        }
+
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+    }
+
+    fn remove_dead_entries(&mut self){
+        self.entries.retain(|(_, value)| *value != LuaData::Nil);
+        self.indices = self.entries.iter().enumerate().map(|(index, (key, _))| (key.clone(), index)).collect();
+        self.dead_count = 0;
+    }
+}
@@ -0,0 +1,75 @@
+use std::mem;
+
+use super::Interpreter;
+use super::super::data::{LuaData, ObjectRef};
+
+/// A value defined by the host, scripts can only use it through its metatable
+pub struct UserData{
+    pub metatable: Option<i64>,
+    /// Set once the '__gc' metamethod was called, it is only called once
+    finalized: bool,
+}
+
+impl Interpreter{
+
+    /// Creates a userdata. Once the returned reference and all of its copies are dropped
+    /// the '__gc' metamethod of the metatable is called with it, then it is removed
+    pub fn create_userdata(&mut self, metatable: Option<i64>) -> ObjectRef{
+        self.collect_userdata();
+
+        let id = self.userdata_count;
+        self.userdata_count += 1;
+
+        self.userdata.insert(id, UserData {metatable, finalized: false});
+
+        ObjectRef::new(id, self.released_userdata.clone())
+    }
+
+    pub fn get_userdata(&self, id: i64) -> Option<&UserData>{
+        self.userdata.get(&id)
+    }
+
+    /// The number of userdata that haven't been removed yet
+    pub fn live_userdata(&self) -> usize{
+        self.userdata.len()
+    }
+
+    /// Finalizes and removes the userdata that nothing refers to anymore. Like in lua, errors
+    /// in a '__gc' metamethod are ignored, and a pending return or break isn't disturbed
+    pub(super) fn collect_userdata(&mut self){
+        loop{
+            let released = mem::take(&mut *self.released_userdata.lock().unwrap_or_else(|e| e.into_inner()));
+            if released.is_empty(){
+                break;
+            }
+
+            for id in released{
+                let metatable = match self.userdata.get_mut(&id){
+                    Some(x) if !x.finalized => {
+                        x.finalized = true;
+                        x.metatable
+                    },
+                    _ => None,
+                };
+                let handler = metatable.and_then(|x| self.get_table(x)?.get_variable("__gc".to_string()).cloned());
+
+                match handler{
+                    // The reference given to the handler releases the userdata again once it is dropped
+                    Some(handler) => {
+                        let value = LuaData::UserData(ObjectRef::new(id, self.released_userdata.clone()));
+                        let return_val = self.return_val.take();
+                        let breaking = mem::replace(&mut self.breaking, false);
+
+                        let _ = self.call(&handler, vec![value]);
+
+                        self.return_val = return_val;
+                        self.breaking = breaking;
+                    },
+                    None => {
+                        self.userdata.remove(&id);
+                    },
+                }
+            }
+        }
+    }
+}
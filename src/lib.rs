@@ -3,10 +3,13 @@ pub mod parser;
 pub mod interpreter;
 pub mod data;
 pub mod error;
+pub mod diagnostic;
+pub mod pattern;
 pub mod config;
 
 use config::{Config, LogLevel};
 use error::LuaError;
+use diagnostic::Renderer;
 use interpreter::Interpreter;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -17,22 +20,38 @@ pub enum BinOp{
     GreaterThan,
     GreaterEqualThan,
     EqualEqual,
+    NotEqual,
     Plus,
     Minus, 
     Multiply,
-    Divide
+    Divide,
+    FloorDivide,
+    Modulo,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum UnOp{
+    Minus,
+    BitNot,
+    Length
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Keyword{
-    True, False, If, Then, Else, End, Function, Return, Local, While, Do, For
+    True, False, If, Then, Else, Elseif, End, Function, Return, Local, While, Do, For, Break
 }
 
 impl Keyword{
 
     pub fn vec() -> Vec<String>{
-        vec!["true", "false", "if", "else", "then", "end", "function", 
-            "return", "local", "while", "do", "for"].iter().map(|x| x.to_string()).collect()
+        vec!["true", "false", "if", "else", "elseif", "then", "end", "function", 
+            "return", "local", "while", "do", "for", "break"].iter().map(|x| x.to_string()).collect()
     }
 
     pub fn is_keyword(string: &str) -> bool{
@@ -49,6 +68,7 @@ impl Keyword{
             "false" => Keyword::False,
             "if" => Keyword::If,
             "else" => Keyword::Else,
+            "elseif" => Keyword::Elseif,
             "then" => Keyword::Then,
             "end" => Keyword::End,
             "function" => Keyword::Function,
@@ -57,10 +77,16 @@ impl Keyword{
             "while" => Keyword::While,
             "do" => Keyword::Do,
             "for" => Keyword::For,
+            "break" => Keyword::Break,
             _ => panic!("Couldn't convert string to keyword: {}", string),
         }
     }
 
+    /// The keyword as it is written in the source
+    pub fn name(&self) -> String{
+        format!("{:?}", self).to_lowercase()
+    }
+
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -68,16 +94,22 @@ pub enum Token{
     Identifier(String), 
     StringLiteral(String),
     NumberLiteral(f64),
+    IntegerLiteral(i64),
     Operator(BinOp),
     Keyword(Keyword),
     LeftParenthesis,
     RightParenthesis,
     Semicolon,
-    Newline,
     Comma,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    /// A '.' that isn't part of an identifier, i.e. 'f().x'
+    Dot,
+    Colon,
     Equal,
+    Hash,
     EOF 
 }
 
@@ -92,40 +124,122 @@ impl Token{
 
 } 
 
+/// A token and where it is in the source
+#[derive(Debug, PartialEq, Clone)]
+pub struct TokenInfo{
+    pub token: Token,
+    pub span: Span,
+}
+
+/// A range of source code in a chunk. Lines and columns start at 1, the end is the last character of the range
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Span{
+    pub chunk: String,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span{
+
+    pub fn new(chunk: &str, start: (usize, usize), end: (usize, usize)) -> Span{
+        Span {chunk: chunk.to_string(), start_line: start.0, start_column: start.1, end_line: end.0, end_column: end.1}
+    }
+
+    /// A span for a whole chunk, used when there is no better position
+    pub fn chunk(chunk: &str) -> Span{
+        Span {chunk: chunk.to_string(), ..Span::default()}
+    }
+
+    /// The span from the start of this span to the end of another
+    pub fn to(&self, end: &Span) -> Span{
+        Span {end_line: end.end_line, end_column: end.end_column, ..self.clone()}
+    }
+
+    /// The position used at the start of error messages like in lua, i.e. 'main:5'
+    pub fn location(&self) -> String{
+        if self.start_line == 0{
+            return self.chunk.clone();
+        }
+
+        format!("{}:{}", self.chunk, self.start_line)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Stmt{
     stmt_type: StmtType,
-    location: String
+    span: Span
+}
+
+impl Stmt{
+
+    pub fn span(&self) -> &Span{
+        &self.span
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum StmtType{
-    ///Name, Arguments
-    FunctionCall(Token, Vec<Expr>),
+    ///Function, Arguments
+    FunctionCall(Expr, Vec<Expr>),
+    ///Object, Method Name, Arguments
+    MethodCall(Expr, String, Vec<Expr>),
     ///Name, Arguments, Stmts
     FunctionDef(Token, Vec<Token>, Vec<Stmt>),
-    ///Name, Assignment, Is Local,
-    Assignment(Token, Expr, bool),
+    ///Targets, Values
+    Assignment(Vec<Expr>, Vec<Expr>),
+    ///Names, Attributes, Values
+    Local(Vec<Token>, Vec<LocalAttrib>, Vec<Expr>),
     ///Operator, Left Token, Right Token
     BinOp(BinOp, Expr, Expr),
+    ///Operator, Operand
+    UnOp(UnOp, Expr),
     ///A single token value
     Value(Vec<Token>),
+    ///Table, Key
+    Index(Expr, Expr),
+    ///Fields of a table constructor
+    Table(Vec<TableField>),
     ///Condition, Stmts, Else
     If(Expr, Vec<Stmt>, Option<Vec<Stmt>>),
     //Condition, Block
     While(Expr, Vec<Stmt>),
+    ///A 'do ... end' block
+    Do(Vec<Stmt>),
     //Variable Name, initial value, min/max, step, block
     For(Token, Expr, Expr, Expr, Vec<Stmt>),
-    Return(Expr),
+    Return(Vec<Expr>),
+    Break,
     EOF
 }
 
+/// The attribute of a local variable, i.e. 'local x <const> = 5'
+#[derive(Debug, PartialEq, Clone)]
+pub enum LocalAttrib{
+    Plain,
+    Const,
+    /// The '__close' metamethod of the value is called when the block exits
+    Close,
+}
+
+/// A field of a table constructor, i.e. '{1, x = 2, [y] = 3}'
+#[derive(Debug, PartialEq, Clone)]
+pub enum TableField{
+    /// Stored at the next integer key
+    Positional(Expr),
+    ///Key, Value
+    Keyed(Expr, Expr),
+}
+
 impl StmtType{
 
     fn stmt_count_recursive(&self) -> u32{
         match self{
-            StmtType::Return(_) | StmtType::Assignment(_, _, _) | StmtType::BinOp(_, _, _) | 
-            StmtType::FunctionCall(_, _) | StmtType::EOF | StmtType::Value(_) => 1,
+            StmtType::Return(_) | StmtType::Assignment(_, _) | StmtType::Local(_, _, _) | StmtType::Break | StmtType::BinOp(_, _, _) | 
+            StmtType::UnOp(_, _) | StmtType::FunctionCall(_, _) | StmtType::MethodCall(_, _, _) | StmtType::EOF | 
+            StmtType::Value(_) | StmtType::Index(_, _) | StmtType::Table(_) => 1,
             StmtType::If(_, block, else_block) => {
                 let mut count = 1 + count_stmts_recur(block);
 
@@ -135,7 +249,7 @@ impl StmtType{
 
                 count
             },
-            StmtType::FunctionDef(_, _, block) | StmtType::While(_, block) | StmtType::For(_, _, _, _, block) => {
+            StmtType::FunctionDef(_, _, block) | StmtType::While(_, block) | StmtType::For(_, _, _, _, block) | StmtType::Do(block) => {
                 1 + count_stmts_recur(block)
             } 
         }
@@ -162,7 +276,9 @@ enum ExprType{
     // Contains a bool bin op (==, <, <=, etc.)
     Bool,
     // A single value i.e. '55' or '"Hello World"'
-    SingleValue
+    SingleValue,
+    // A function call that isn't in parentheses, it can produce several values
+    Call
 }
 
 
@@ -172,6 +288,13 @@ pub struct Expr{
     expr_type: ExprType
 }
 
+impl Expr{
+
+    pub fn span(&self) -> &Span{
+        &self.stmts[0].span
+    }
+}
+
 pub struct Aurora{
     interpreter: Interpreter,
     config: Config
@@ -179,25 +302,51 @@ pub struct Aurora{
 
 impl Aurora{
     
-    pub fn new(config: Config) -> Aurora{
+    pub fn new(mut config: Config) -> Aurora{
         let mut interpreter = Interpreter::new();
 
         interpreter.load_library(interpreter::library::new_std());
+
+        if let Some(seed) = config.random_seed{
+            interpreter.seed_random(seed, 0);
+        }
+
+        if let Some(clock) = config.clock.take(){
+            interpreter.set_clock(clock);
+        }
+
+        if let Some(environment) = config.environment.take(){
+            interpreter.set_environment(environment);
+        }
+
         Aurora{interpreter: interpreter, config: config}
     }
 
     pub fn register_function(&mut self, name: String, function: interpreter::function::FunctionDef){
-        self.interpreter.func_manager.register_func(name, function);
+        self.interpreter.register_func(&name, function);
+    }
+
+    /// Replaces the filesystem scripts can reach through 'require' and the io and os libraries
+    pub fn set_file_system(&mut self, file_system: Box<dyn interpreter::fs::FileSystem>){
+        self.interpreter.set_file_system(file_system);
+    }
+
+    /// The code passed to 'os.exit', if the script exited. The error of the run has the kind 'ErrorKind::Exit'
+    pub fn exit_code(&self) -> Option<i32>{
+        self.interpreter.exit_code()
     }
 
     pub fn run(&mut self, src: String) -> Result<(), Vec<LuaError>>{
-        let tokens = parser::scanner::scan(src)?;
+        self.run_chunk(src, parser::scanner::DEFAULT_CHUNK)
+    }
+
+    /// Runs source code, the chunk name is used in the spans of its tokens, i.e. the file name
+    pub fn run_chunk(&mut self, src: String, chunk: &str) -> Result<(), Vec<LuaError>>{
+        self.interpreter.add_source(chunk, &src);
+        let tokens = parser::scanner::scan_chunk(src, chunk)?;
         self.print_token_info(&tokens);
 
-        let mut stmts = match parser::parse(tokens){
-            Ok(x) => x,
-            Err(e) => return Err(vec![e])
-        };
+        let mut stmts = parser::parse(tokens)?;
         self.print_stmt_info(&stmts);
 
         match self.run_stmts(&mut stmts){
@@ -208,14 +357,19 @@ impl Aurora{
         Ok(())
     }
 
+    /// Renders an error with a snippet of the code it happened in, colored if the config allows it
+    pub fn render_error(&self, error: &LuaError) -> String{
+        let source = error.span().and_then(|span| self.interpreter.source(&span.chunk));
+
+        Renderer::new(self.config.color).render(error, source)
+    }
+
     pub fn run_stmts(&mut self, stmts: &mut Vec<Stmt>) -> Result<(), LuaError>{
         if self.config.log_level == LogLevel::Verbose{
             println!("\n---------- Running -------");
         }
 
-        for stmt in stmts.iter_mut(){
-            self.interpreter.run_stmt(stmt)?;
-        }
+        self.interpreter.run_block(stmts).map_err(|e| self.interpreter.attach_traceback(e))?;
 
         if self.config.log_level == LogLevel::Verbose{
             println!("\n---------- Finished -------");
@@ -224,7 +378,7 @@ impl Aurora{
         Ok(())
     }
 
-    fn print_token_info(&self, tokens: &Vec<Token>){
+    fn print_token_info(&self, tokens: &Vec<TokenInfo>){
         if self.config.log_level != LogLevel::Verbose{
             return;
         }
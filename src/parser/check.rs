@@ -0,0 +1,117 @@
+use super::super::{Token, Stmt, StmtType, Expr, LocalAttrib};
+use super::super::error::{LuaError, ErrorKind, SyntaxErrorKind};
+
+/// Checks that need to know which local variables are in scope:
+/// assignments to const variables and breaks outside of loops
+struct Checker{
+    /// The local variables of each block and whether they are const
+    scopes: Vec<Vec<(String, bool)>>,
+    /// How many loops enclose the current statement in the current function
+    loop_depth: usize,
+    errors: Vec<LuaError>,
+}
+
+impl Checker{
+
+    fn check_block(&mut self, stmts: &[Stmt]){
+        self.scopes.push(Vec::new());
+
+        for stmt in stmts{
+            self.check_stmt(stmt);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt){
+        match stmt.stmt_type{
+            StmtType::Local(ref names, ref attribs, _) => {
+                for (name, attrib) in names.iter().zip(attribs){
+                    let is_const = *attrib != LocalAttrib::Plain;
+
+                    self.scopes.last_mut().unwrap().push((name_of(name), is_const));
+                }
+            },
+            StmtType::Assignment(ref targets, _) => {
+                for name in targets.iter().filter_map(variable_name){
+                    self.check_assignment(&name, stmt);
+                }
+            },
+            StmtType::FunctionDef(ref name, ref args, ref block) => {
+                self.check_assignment(&name_of(name), stmt);
+
+                let loop_depth = self.loop_depth;
+                self.loop_depth = 0;
+                self.scopes.push(args.iter().map(|arg| (name_of(arg), false)).collect());
+                self.check_block(block);
+                self.scopes.pop();
+                self.loop_depth = loop_depth;
+            },
+            StmtType::If(_, ref block, ref else_block) => {
+                self.check_block(block);
+
+                if let Some(else_block) = else_block{
+                    self.check_block(else_block);
+                }
+            },
+            StmtType::While(_, ref block) => self.check_loop(None, block),
+            StmtType::Do(ref block) => self.check_block(block),
+            StmtType::For(ref name, _, _, _, ref block) => self.check_loop(Some(name), block),
+            StmtType::Break if self.loop_depth == 0 => self.errors.push(error("break outside a loop", stmt)),
+            _ => (),
+        }
+    }
+
+    fn check_loop(&mut self, variable: Option<&Token>, block: &[Stmt]){
+        self.loop_depth += 1;
+        self.scopes.push(variable.map(|name| (name_of(name), false)).into_iter().collect());
+        self.check_block(block);
+        self.scopes.pop();
+        self.loop_depth -= 1;
+    }
+
+    /// Fails if the innermost variable with the name is const, fields of tables can always be assigned
+    fn check_assignment(&mut self, name: &str, stmt: &Stmt){
+        if name.contains('.'){
+            return;
+        }
+
+        let variable = self.scopes.iter().rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(variable, _)| variable == name);
+
+        if let Some((_, true)) = variable{
+            self.errors.push(error(&format!("attempt to assign to const variable '{}'", name), stmt));
+        }
+    }
+}
+
+fn name_of(token: &Token) -> String{
+    match token{
+        Token::Identifier(x) => x.clone(),
+        _ => String::new(),
+    }
+}
+
+/// The name of an assignment target that is a variable, fields are None
+fn variable_name(target: &Expr) -> Option<String>{
+    match target.stmts[0].stmt_type{
+        StmtType::Value(ref tokens) => tokens.first().map(name_of),
+        _ => None,
+    }
+}
+
+fn error(message: &str, stmt: &Stmt) -> LuaError{
+    LuaError::create_parse(message, Some(stmt.span.clone())).with_kind(ErrorKind::SyntaxError(SyntaxErrorKind::InvalidStatement))
+}
+
+pub fn check_chunk(stmts: &[Stmt]) -> Result<(), Vec<LuaError>>{
+    let mut checker = Checker{scopes: Vec::new(), loop_depth: 0, errors: Vec::new()};
+    checker.check_block(stmts);
+
+    if checker.errors.is_empty(){
+        Ok(())
+    }else{
+        Err(checker.errors)
+    }
+}
@@ -1,196 +1,253 @@
+use super::{Parser, Token, BinOp, Stmt, StmtType, Expr, Keyword};
+use super::super::{UnOp, ExprType, TableField, Span, error::LuaError};
 
-use std::collections::VecDeque;
+/// Priority of the unary operators, only '^' binds tighter
+const UNARY_PRIORITY: u8 = 12;
 
-use super::{Token, BinOp, Stmt, StmtType, Expr, error, Keyword};
-use super::super::{ExprType, error::LuaError};
+impl Parser{
 
-struct ExprParser{
-    expr_type: ExprType,
-    tokens: VecDeque<Token>,
-    line: usize
-}
-
-impl ExprParser{
-
-    fn new(tokens: Vec<Token>, line: usize) -> ExprParser{
-        let mut tokens_deque: VecDeque<Token> = VecDeque::new();
-        let mut expr_type = ExprType::SingleValue;
-
-        for token in tokens{
-            tokens_deque.push_back(token);
-        }
+    pub(super) fn scan_expr(&mut self) -> Result<Expr, LuaError>{
+        self.scan_sub_expr(0)
+    }
 
-        for token in &tokens_deque{
+    /// Parses an expression where all binary operators bind tighter than the limit
+    fn scan_sub_expr(&mut self, limit: u8) -> Result<Expr, LuaError>{
+        let mut left = match self.peek().and_then(unary_operator){
+            Some(operator) => {
+                self.next_token();
+                let start = self.last_span.clone();
+                let operand = self.scan_sub_expr(UNARY_PRIORITY)?;
+
+                self.create_expr(&start, ExprType::Number, StmtType::UnOp(operator, operand))
+            },
+            None => self.scan_value()?,
+        };
 
-            // If the expression has a operator it have more than value 
-            let token_type = match token{
-                Token::Operator(BinOp::Concat) => Some(ExprType::Str),
-                Token::Operator(BinOp::LessThan) | Token::Operator(BinOp::LessEqualThan) | Token::Operator(BinOp::EqualEqual) |
-                Token::Operator(BinOp::GreaterThan) | Token::Operator(BinOp::GreaterEqualThan) => Some(ExprType::Bool),
-                Token::Operator(_) => Some(ExprType::Number),
-                _ => None,
-            };
+        while let Some(Token::Operator(operator)) = self.peek().cloned(){
+            let (left_priority, right_priority) = priority(&operator);
 
-            if let Some(token_type) = token_type{
-                expr_type = token_type;
+            if left_priority <= limit{
                 break;
             }
-        }
-
-        ExprParser {tokens: tokens_deque, expr_type: expr_type, line}
-    }
-    
-    fn parse(mut self) -> Result<Vec<Stmt>, LuaError>{
-        let mut stmts: Vec<Stmt> = Vec::new();
-
-        loop{
-            let stmt = self.scan_stmt()?;
 
-            if stmt.stmt_type == StmtType::EOF{
-                stmts.push(stmt);
-                break;
-            }
+            self.next_token();
+            let right = self.scan_sub_expr(right_priority)?;
+            let expr_type = match operator{
+                BinOp::Concat => ExprType::Str,
+                BinOp::LessThan | BinOp::LessEqualThan | BinOp::GreaterThan | BinOp::GreaterEqualThan |
+                BinOp::EqualEqual | BinOp::NotEqual => ExprType::Bool,
+                _ => ExprType::Number,
+            };
 
-            stmts.push(stmt);
+            let start = left.span().clone();
+            left = self.create_expr(&start, expr_type, StmtType::BinOp(operator, left, right));
         }
 
-        Ok(stmts)
+        Ok(left)
     }
 
-    fn scan_stmt(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-
-        if let Some(token) = self.peek(){
-            if token == &Token::Newline || token == &Token::Semicolon{
-                return error("Expressions cannot have newlines or semicolons!".to_string(), self.line);
-            }
-        }else{
-            return Ok(Stmt {location, stmt_type: StmtType::EOF});
-        }
-
-        match self.expr_type{
-            ExprType::Number | ExprType::Bool => self.scan_num_expr(),
-            ExprType::Str => self.scan_string_expr(),
-            ExprType::SingleValue => self.scan_value(),
+    fn scan_value(&mut self) -> Result<Expr, LuaError>{
+        match self.peek(){
+            Some(x) if is_literal_value(x) => {
+                let token = self.next_token().unwrap();
+
+                Ok(self.create_expr(&self.last_span, ExprType::SingleValue, StmtType::Value(vec![token])))
+            },
+            Some(Token::LeftBrace) => {
+                self.next_token();
+                self.scan_table()
+            },
+            Some(_) => self.scan_prefix_expr(),
+            None => Err(self.parse_error("Expected value but found end of expression".to_string())),
         }
     }
 
-    fn scan_value(&mut self) -> Result<Stmt, LuaError>{
-        let token = self.next_token().unwrap();
-        let mut tokens = vec![token];
-        let location = format!("Line {}", self.line);
+    /// Parses a name or an expression in parentheses, followed by any number of field accesses and calls
+    pub(super) fn scan_prefix_expr(&mut self) -> Result<Expr, LuaError>{
+        let mut expr = match self.next_token(){
+            Some(Token::Identifier(name)) => self.create_expr(&self.last_span, ExprType::SingleValue, StmtType::Value(vec![Token::Identifier(name)])),
+            Some(Token::LeftParenthesis) => {
+                let start = self.last_span.clone();
+                let mut expr = self.scan_sub_expr(0)?;
+                self.expect(Token::RightParenthesis)?;
+                expr.stmts[0].span = start.to(&self.last_span);
+
+                // Parentheses truncate a call to its first value
+                if expr.expr_type == ExprType::Call{
+                    expr.expr_type = ExprType::SingleValue;
+                }
 
-        if is_literal_value(&tokens[0]){
-            return Ok(Stmt{location, stmt_type: StmtType::Value(tokens)});
-        }
+                expr
+            },
+            x => return Err(self.parse_error(format!("Illegal Token: {:?} isn't a value", x))),
+        };
 
         loop{
-            let next_token = self.next_token();
+            let start = expr.span().clone();
+
+            expr = match self.peek(){
+                Some(Token::Dot) => {
+                    self.next_token();
+
+                    let name = match self.next_token(){
+                        Some(Token::Identifier(name)) => name,
+                        x => return Err(self.parse_error(format!("Expected field name but found {:?}", x))),
+                    };
+
+                    // The scanner keeps 'x.y' together, so every part is a separate field
+                    for field in name.split('.'){
+                        let key = self.string_expr(self.last_span.clone(), field);
+                        expr = self.create_expr(&start, ExprType::SingleValue, StmtType::Index(expr, key));
+                    }
+
+                    expr
+                },
+                Some(Token::LeftBracket) => {
+                    self.next_token();
+                    let key = self.scan_sub_expr(0)?;
+                    self.expect(Token::RightBracket)?;
+
+                    self.create_expr(&start, ExprType::SingleValue, StmtType::Index(expr, key))
+                },
+                Some(Token::Colon) => {
+                    self.next_token();
+
+                    let name = match self.next_token(){
+                        Some(Token::Identifier(ref name)) if !name.contains('.') => name.clone(),
+                        x => return Err(self.parse_error(format!("Expected method name but found {:?}", x))),
+                    };
+                    let args = self.scan_call_args()?;
+
+                    self.create_expr(&start, ExprType::Call, StmtType::MethodCall(expr, name, args))
+                },
+                // A '(' on a new line could also start a new statement
+                Some(Token::LeftParenthesis) if self.peek_line() != self.line => {
+                    let message = "ambiguous syntax (function call x new statement)";
+
+                    let note = "add a ';' before the '(' if it starts a new statement";
+
+                    return Err(LuaError::create_parse(message, Some(self.peek_span())).with_note(note));
+                },
+                Some(Token::LeftParenthesis) | Some(Token::StringLiteral(_)) | Some(Token::LeftBrace) => {
+                    let args = self.scan_call_args()?;
+
+                    self.create_expr(&start, ExprType::Call, StmtType::FunctionCall(expr, args))
+                },
+                _ => return Ok(expr),
+            };
+        }
+    }
 
-            if let Some(token) = next_token{
-                 if token == Token::EOF || token == Token::Newline{
-                    tokens.push(token);
-                    break;
+    /// Parses the arguments of a call: a list in parentheses, a string literal or a table constructor
+    fn scan_call_args(&mut self) -> Result<Vec<Expr>, LuaError>{
+        match self.next_token(){
+            Some(Token::StringLiteral(x)) => Ok(vec![self.string_expr(self.last_span.clone(), &x)]),
+            Some(Token::LeftBrace) => Ok(vec![self.scan_table()?]),
+            Some(Token::LeftParenthesis) => {
+                if self.peek() == Some(&Token::RightParenthesis){
+                    self.next_token();
+                    return Ok(Vec::new());
                 }
 
-                tokens.push(token);
-            }else{
-                break;
-            }
-        }
+                let args = self.scan_expr_list()?;
+                self.expect(Token::RightParenthesis)?;
 
-        Ok(Stmt{location, stmt_type: StmtType::Value(tokens)})
+                Ok(args)
+            },
+            x => Err(self.parse_error(format!("Expected function arguments but found {:?}", x))),
+        }
     }
 
-    fn scan_num_expr(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-        let (left, operator_token) = self.scan_until(|x| is_operator(x))?;
+    /// Parses the fields of a table constructor, the '{' has already been consumed
+    fn scan_table(&mut self) -> Result<Expr, LuaError>{
+        let start = self.last_span.clone();
+        let mut fields = Vec::new();
+
+        while self.peek() != Some(&Token::RightBrace){
+            let field = match (self.peek().cloned(), self.tokens.get(1).map(|info| &info.token)){
+                (Some(Token::LeftBracket), _) => {
+                    self.next_token();
+                    let key = self.scan_sub_expr(0)?;
+                    self.expect(Token::RightBracket)?;
+                    self.expect(Token::Equal)?;
+
+                    TableField::Keyed(key, self.scan_sub_expr(0)?)
+                },
+                (Some(Token::Identifier(ref name)), Some(Token::Equal)) if !name.contains('.') => {
+                    let key = self.string_expr(self.peek_span(), name);
+                    self.next_token();
+                    self.next_token();
+
+                    TableField::Keyed(key, self.scan_sub_expr(0)?)
+                },
+                _ => TableField::Positional(self.scan_sub_expr(0)?),
+            };
 
-        let operator = match operator_token{
-            Token::Operator(operator) => operator,
-            x => return error(format!("Expected binary operator but found {:?}; {:?}", x, left), self.line),
-        };
+            fields.push(field);
 
-        let right = self.scan_until(|x| {
-            match x{
-                Token::Semicolon | Token::Newline | Token::RightParenthesis => false,
-                _ => false,
+            match self.peek(){
+                Some(Token::Comma) | Some(Token::Semicolon) => {
+                    self.next_token();
+                },
+                _ => break,
             }
-        })?.0;
+        }
 
-        Ok(Stmt{location, stmt_type: StmtType::BinOp(operator, parse(left, self.line)?, parse(right, self.line)?)})
-    }
+        self.expect(Token::RightBrace)?;
 
-    fn scan_string_expr(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-        let (left, operator_token) = self.scan_until(|x| is_operator(x))?;
+        Ok(self.create_expr(&start, ExprType::SingleValue, StmtType::Table(fields)))
+    }
 
-        let operator = match operator_token{
-            Token::Operator(BinOp::Concat) => BinOp::Concat,
-            x => return error(format!("Expected string concat operator but found {:?}", x), self.line),
-        };
+    pub(super) fn scan_expr_list(&mut self) -> Result<Vec<Expr>, LuaError>{
+        let mut exprs = vec![self.scan_expr()?];
 
-        let right = self.scan_until(|x| {
-            match x{
-                Token::Semicolon | Token::Newline | Token::RightParenthesis => false,
-                _ => false,
-            }
-        })?.0;
+        while self.peek() == Some(&Token::Comma){
+            self.next_token();
+            exprs.push(self.scan_expr()?);
+        }
 
-        Ok(Stmt{location, stmt_type: StmtType::BinOp(operator, parse(left, self.line)?, parse(right, self.line)?)})
+        Ok(exprs)
     }
 
-    fn scan_until<F>(&mut self, mut f: F) -> Result<(Vec<Token>, Token), LuaError> where F: FnMut(&Token) -> bool{
-        let mut tokens = Vec::new();
-        let mut level = 0;
-
-        loop{
-            let token = self.next_token().unwrap_or(Token::EOF);
-
-            if token == Token::LeftParenthesis{
-                level += 1;
-                continue;
-            }else if token == Token::RightParenthesis{
-                level -= 1;
-                continue;
-            }
-
-            if (f(&token) && level == 0)|| token == Token::EOF{
-                break Ok((tokens, token));
-            }
+    fn string_expr(&self, span: Span, string: &str) -> Expr{
+        let stmt_type = StmtType::Value(vec![Token::StringLiteral(string.to_string())]);
 
-            tokens.push(token);
-        }
+        Expr{expr_type: ExprType::SingleValue, stmts: vec![Stmt{span, stmt_type}]}
     }
 
-    fn peek(&self) -> Option<&Token>{
-        self.tokens.get(0)
+    /// Creates an expression that spans from the start to the last token that was consumed
+    pub(super) fn create_expr(&self, start: &Span, expr_type: ExprType, stmt_type: StmtType) -> Expr{
+        Expr{expr_type, stmts: vec![Stmt{span: start.to(&self.last_span), stmt_type}]}
     }
+}
 
-    fn next_token(&mut self) -> Option<Token>{
-        let token = self.tokens.pop_front();
-        token
+/// Returns the left and right priority of a binary operator,
+/// right associative operators have a lower right priority
+fn priority(operator: &BinOp) -> (u8, u8){
+    match operator{
+        BinOp::Power => (14, 13),
+        BinOp::Multiply | BinOp::Divide | BinOp::FloorDivide | BinOp::Modulo => (11, 11),
+        BinOp::Plus | BinOp::Minus => (10, 10),
+        BinOp::Concat => (9, 8),
+        BinOp::ShiftLeft | BinOp::ShiftRight => (7, 7),
+        BinOp::BitAnd => (6, 6),
+        BinOp::BitXor => (5, 5),
+        BinOp::BitOr => (4, 4),
+        BinOp::LessThan | BinOp::LessEqualThan | BinOp::GreaterThan | BinOp::GreaterEqualThan |
+        BinOp::EqualEqual | BinOp::NotEqual => (3, 3),
     }
 }
 
-fn is_operator(token: &Token) -> bool{
+fn unary_operator(token: &Token) -> Option<UnOp>{
     match token{
-        Token::Operator(_) => true,
-        _ => false,
+        Token::Operator(BinOp::Minus) => Some(UnOp::Minus),
+        Token::Operator(BinOp::BitXor) => Some(UnOp::BitNot),
+        Token::Hash => Some(UnOp::Length),
+        _ => None,
     }
 }
 
 fn is_literal_value(token: &Token) -> bool{
-    match token{
-        Token::NumberLiteral(_) | Token::StringLiteral(_) | 
-        Token::Keyword(Keyword::False) | Token::Keyword(Keyword::True) => true,
-        _ => false
-    }
+    matches!(token, Token::NumberLiteral(_) | Token::IntegerLiteral(_) | Token::StringLiteral(_) |
+        Token::Keyword(Keyword::False) | Token::Keyword(Keyword::True))
 }
-
-pub fn parse(tokens: Vec<Token>, line: usize) -> Result<Expr, LuaError>{
-    let parser = ExprParser::new(tokens, line);
-    let expr_type = parser.expr_type.clone();
-    
-    Ok(Expr{expr_type: expr_type, stmts: parser.parse()?})
-}
\ No newline at end of file
@@ -1,345 +1,541 @@
 
 pub mod scanner;
 pub mod expr;
+mod check;
 
 use std::collections::VecDeque;
-use super::{Token, BinOp, Stmt, StmtType, Expr, Keyword};
-use super::error::LuaError;
+use std::mem;
+use super::{Token, TokenInfo, Span, BinOp, Stmt, StmtType, Expr, ExprType, Keyword, LocalAttrib};
+use super::error::{LuaError, ErrorKind, SyntaxErrorKind};
 
 pub struct Parser{
-    tokens: VecDeque<Token>,
-    pub line: usize
+    tokens: VecDeque<TokenInfo>,
+    /// The line of the last token that was consumed
+    pub line: usize,
+    /// The span of the last token that was consumed
+    last_span: Span,
+    /// Syntax errors found so far, parsing continues after each one
+    errors: Vec<LuaError>,
+    /// The tokens consumed so far, used to find how deeply nested a statement was when it failed
+    consumed: Vec<Token>,
 }
 
-impl Parser{
+/// A block or a bracket that the tokens of a statement opened
+#[derive(PartialEq)]
+enum Open{
+    Block,
+    Bracket,
+}
+
+/// The blocks and brackets the tokens of a statement have opened, the innermost last
+#[derive(Default)]
+struct Nesting{
+    open: Vec<Open>,
+    /// Loops whose 'do' hasn't been seen yet, the 'do' belongs to the loop instead of opening a block
+    loop_headers: usize,
+    /// Whether the last token can end an expression
+    after_value: bool,
+}
+
+impl Nesting{
 
-    pub fn new(tokens: Vec<Token>) -> Parser{
-        let mut tokens_deque: VecDeque<Token> = VecDeque::new();
+    fn track(&mut self, token: &Token){
+        if self.starts_stmt(token){
+            self.close_brackets();
+        }
 
-        for token in tokens{
-            tokens_deque.push_back(token);
+        match token{
+            Token::LeftParenthesis | Token::LeftBracket | Token::LeftBrace => self.open.push(Open::Bracket),
+            Token::RightParenthesis | Token::RightBracket | Token::RightBrace if self.open.last() == Some(&Open::Bracket) => {
+                self.open.pop();
+            },
+            Token::Keyword(Keyword::While) | Token::Keyword(Keyword::For) => {
+                self.open.push(Open::Block);
+                self.loop_headers += 1;
+            },
+            Token::Keyword(Keyword::Do) if self.loop_headers > 0 => self.loop_headers -= 1,
+            Token::Keyword(Keyword::Do) | Token::Keyword(Keyword::If) | Token::Keyword(Keyword::Function) => self.open.push(Open::Block),
+            Token::Keyword(Keyword::End) => {
+                self.open.pop();
+            },
+            _ => (),
         }
 
-        Parser {tokens: tokens_deque, line: 1}
+        self.after_value = ends_value(token);
+    }
+
+    fn blocks(&self) -> usize{
+        self.open.iter().filter(|x| **x == Open::Block).count()
+    }
+
+    fn brackets(&self) -> usize{
+        self.open.iter().filter(|x| **x == Open::Bracket).count()
+    }
+
+    /// Whether a token can only start a statement. 'function' is also a value, unless it follows one
+    fn starts_stmt(&self, token: &Token) -> bool{
+        match token{
+            Token::Keyword(Keyword::End) | Token::Keyword(Keyword::Else) | Token::Keyword(Keyword::Elseif) |
+            Token::Keyword(Keyword::Local) | Token::Keyword(Keyword::If) | Token::Keyword(Keyword::While) |
+            Token::Keyword(Keyword::For) | Token::Keyword(Keyword::Return) | Token::Keyword(Keyword::Break) => true,
+            Token::Keyword(Keyword::Do) => self.loop_headers == 0,
+            Token::Keyword(Keyword::Function) => self.after_value,
+            _ => false,
+        }
+    }
+
+    /// Statements can't be inside brackets, so the brackets left open in the innermost block
+    /// when a statement starts were never closed by the statement that failed
+    fn close_brackets(&mut self){
+        while self.open.last() == Some(&Open::Bracket){
+            self.open.pop();
+        }
+    }
+
+    /// Whether a token starts a new statement when nothing is left open.
+    /// 'function' can also start a value inside brackets
+    fn is_boundary(&self, token: &Token) -> bool{
+        match token{
+            Token::EOF | Token::Keyword(Keyword::End) | Token::Keyword(Keyword::Else) | Token::Keyword(Keyword::Elseif) |
+            Token::Keyword(Keyword::Local) | Token::Keyword(Keyword::If) | Token::Keyword(Keyword::While) |
+            Token::Keyword(Keyword::For) | Token::Keyword(Keyword::Return) | Token::Keyword(Keyword::Break) => true,
+            Token::Keyword(Keyword::Do) => self.loop_headers == 0,
+            Token::Keyword(Keyword::Function) | Token::Semicolon => self.brackets() == 0,
+            _ => false,
+        }
+    }
+}
+
+impl Parser{
+
+    pub fn new(tokens: Vec<TokenInfo>) -> Parser{
+        let last_span = tokens.first().map(|info| info.span.clone()).unwrap_or_default();
+
+        Parser {line: last_span.start_line, tokens: tokens.into_iter().collect(), last_span, errors: Vec::new(), consumed: Vec::new()}
     }
     
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, LuaError>{
+    /// Parses and checks the whole chunk, returns every syntax error if there are any
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LuaError>>{
+        let stmts = self.scan_chunk();
+        let mut errors = mem::take(&mut self.errors);
+
+        // The statements that did parse are still checked, so all errors are found in one pass
+        if let Err(check_errors) = check::check_chunk(&stmts){
+            errors.extend(check_errors);
+        }
+
+        if errors.is_empty(){
+            Ok(stmts)
+        }else{
+            errors.sort_by_key(|e| e.span().map(|span| (span.start_line, span.start_column)));
+            Err(errors)
+        }
+    }
+
+    /// Parses the whole chunk, the statements that had syntax errors are left out
+    fn scan_chunk(&mut self) -> Vec<Stmt>{
+        let mut stmts = self.scan_block();
+
+        // A stray 'end', 'else' or 'elseif' ends the chunk early, so it is skipped to parse the rest
+        loop{
+            let error = match self.next_token(){
+                None | Some(Token::EOF) => break,
+                Some(Token::Keyword(keyword)) => self.parse_error(format!("'<eof>' expected near '{}'", keyword.name())),
+                Some(x) => self.parse_error(format!("'<eof>' expected near {:?}", x)),
+            };
+
+            self.errors.push(error);
+            stmts.extend(self.scan_block());
+        }
+
+        stmts
+    }
+
+    /// Parses statements up to the 'end', 'else' or 'elseif' that ends the block, which isn't consumed.
+    /// Every block ends with an EOF statement. Statements with syntax errors are skipped
+    fn scan_block(&mut self) -> Vec<Stmt>{
         let mut stmts: Vec<Stmt> = Vec::new();
 
         loop{
-            let stmt = self.scan_stmt()?;
+            match self.peek(){
+                None | Some(Token::EOF) | Some(Token::Keyword(Keyword::End)) | Some(Token::Keyword(Keyword::Else)) |
+                Some(Token::Keyword(Keyword::Elseif)) => break,
+                Some(Token::Semicolon) => {
+                    self.next_token();
+                },
+                _ => {
+                    let start = self.consumed.len();
 
-            if stmt.stmt_type == StmtType::EOF{
-                stmts.push(stmt);
-                break;
+                    match self.scan_stmt(){
+                        Ok(stmt) => stmts.push(stmt),
+                        Err(e) => {
+                            self.errors.push(e);
+
+                            if self.consumed.len() == start{
+                                self.next_token();
+                            }
+
+                            self.synchronize(start);
+                        },
+                    }
+                },
             }
+        }
+
+        stmts.push(Stmt {span: self.peek_span(), stmt_type: StmtType::EOF});
+
+        stmts
+    }
+
+    /// Skips the rest of a statement that failed to parse, up to where the next statement starts at the same depth.
+    /// The tokens the statement consumed from the start index tell which blocks and brackets it left open,
+    /// so a bad expression inside a call or a function body is skipped as a whole
+    fn synchronize(&mut self, start: usize){
+        let mut nesting = Nesting::default();
 
-            stmts.push(stmt);
+        for token in &self.consumed[start..]{
+            nesting.track(token);
         }
 
-        Ok(stmts)
+        // Whether the last skipped token can end an expression, so a name on the next line starts a statement
+        nesting.after_value = self.consumed.last().is_some_and(ends_value);
+
+        loop{
+            let on_new_line = self.peek_line() != self.line;
+
+            // A statement keyword closes the brackets the failed statement left open
+            if let Some(x) = self.peek(){
+                if nesting.starts_stmt(x){
+                    nesting.close_brackets();
+                }
+            }
+
+            match self.peek(){
+                None => return,
+                Some(x) if nesting.blocks() == 0 && nesting.is_boundary(x) => return,
+                Some(Token::Identifier(_)) | Some(Token::LeftParenthesis) if nesting.open.is_empty() && nesting.after_value && on_new_line => return,
+                _ => (),
+            }
+
+            let token = self.next_token().unwrap();
+            nesting.track(&token);
+
+            // The statement ends with the 'end' of the block it opened
+            if token == Token::Keyword(Keyword::End) && nesting.open.is_empty(){
+                return;
+            }
+        }
     }
 
     fn scan_stmt(&mut self) -> Result<Stmt, LuaError>{
-        let token = self.next_token();
-        let location = format!("Line {}", self.line);
-
-        if token == None{
-            return Ok(Stmt {location, stmt_type: StmtType::EOF});
+        if let Some(Token::Identifier(_)) | Some(Token::LeftParenthesis) = self.peek(){
+            return self.handle_expr_stmt();
         }
 
-        let token = token.unwrap();
+        let token = self.next_token();
+        let start = self.last_span.clone();
+
+        let token = match token{
+            Some(x) => x,
+            None => return Ok(Stmt {span: start, stmt_type: StmtType::EOF}),
+        };
 
         match token{
-            Token::Identifier(_) => self.handle_indentifier(token),
             Token::Keyword(Keyword::Local) => self.handle_local(),
-            Token::Keyword(Keyword::If) => self.handle_if_stmt(),
+            Token::Keyword(Keyword::If) => self.handle_if_stmt(&start),
             Token::Keyword(Keyword::Function) => self.handle_func_dec(),
             Token::Keyword(Keyword::Return) => self.handle_return_stmt(),
             Token::Keyword(Keyword::While) => self.handle_while_stmt(),
             Token::Keyword(Keyword::For) => self.handle_for_stmt(),
-            Token::LeftParenthesis | Token::RightParenthesis | Token::StringLiteral(_) | 
-            Token::Operator(_) | Token::NumberLiteral(_) | Token::Comma | Token::Keyword(_) |
-            Token::LeftBrace | Token::RightBrace | Token::Equal =>{ 
-                error(format!("Stmt's cannot start with {:?}", token), self.line)
+            Token::Keyword(Keyword::Do) => self.handle_do_stmt(),
+            Token::Keyword(Keyword::Break) => Ok(Stmt {span: start, stmt_type: StmtType::Break}),
+            Token::Identifier(_) | Token::LeftParenthesis | Token::RightParenthesis | Token::StringLiteral(_) | Token::Operator(_) | Token::NumberLiteral(_) | 
+            Token::IntegerLiteral(_) | Token::Comma | Token::Keyword(_) | Token::LeftBrace | Token::RightBrace | 
+            Token::LeftBracket | Token::RightBracket | Token::Dot | Token::Colon | Token::Equal | Token::Hash =>{ 
+                Err(self.parse_error(format!("Stmt's cannot start with {:?}", token)))
             },
-            Token::Semicolon | Token::Newline => self.scan_stmt(),
-            Token::EOF => return Ok(Stmt {location, stmt_type : StmtType::EOF}),
+            Token::Semicolon => self.scan_stmt(),
+            Token::EOF => Ok(Stmt {span: start, stmt_type : StmtType::EOF}),
         }
     }
 
     fn handle_return_stmt(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-        let value_tokens = self.advance_to_mult(vec![Token::Newline, Token::Semicolon]);
+        let start = self.last_span.clone();
+        let exprs = match self.peek(){
+            None | Some(Token::EOF) | Some(Token::Semicolon) | Some(Token::Keyword(Keyword::End)) | 
+            Some(Token::Keyword(Keyword::Else)) | Some(Token::Keyword(Keyword::Elseif)) => Vec::new(),
+            _ => self.scan_expr_list()?,
+        };
 
-        match expr::parse(value_tokens, self.line){
-            Ok(expr) => Ok(Stmt{location, stmt_type: StmtType::Return(expr)}),
-            Err(e) => Err(e)
+        if self.peek() == Some(&Token::Semicolon){
+            self.next_token();
         }
+
+        Ok(Stmt {span: start.to(&self.last_span), stmt_type: StmtType::Return(exprs)})
     }
 
     fn handle_for_stmt(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
+        let start = self.last_span.clone();
 
-        let var_name = if let Some(x) = self.next_token(){
-            x
-        }else{
-            return error("Expected identifier but found none!".to_string(), self.line);
+        let var_name = match self.next_token(){
+            Some(Token::Identifier(x)) => Token::Identifier(x),
+            x => return Err(self.parse_error(format!("Expected identifier but found {:?}", x))),
         };
 
-        self.next_token(); // Remove equal sign
+        self.expect(Token::Equal)?;
+        let start_expr = self.scan_expr()?;
+        self.expect(Token::Comma)?;
+        let end_expr = self.scan_expr()?;
 
-        let start_expr = expr::parse(self.advance_to(Token::Comma), self.line)?;
-        let end_expr = expr::parse(self.advance_to(Token::Comma), self.line)?;
-        let increment_expr = expr::parse(self.advance_to(Token::Keyword(Keyword::Do)), self.line)?;
-        let block_tokens = self.advance_to_block_end();
-        let block = parse(block_tokens)?;
+        let increment_expr = if self.peek() == Some(&Token::Comma){
+            self.next_token();
+            self.scan_expr()?
+        }else{
+            self.create_expr(&self.last_span, ExprType::SingleValue, StmtType::Value(vec![Token::IntegerLiteral(1)]))
+        };
 
-        Ok(Stmt{location, stmt_type : StmtType::For(var_name, start_expr, end_expr, increment_expr, block)})
+        self.expect(Token::Keyword(Keyword::Do))?;
+        let block = self.scan_block();
+        self.expect_end("for", &start)?;
+
+        Ok(Stmt {span: start.to(&self.last_span), stmt_type : StmtType::For(var_name, start_expr, end_expr, increment_expr, block)})
     }
 
     fn handle_while_stmt(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-        let expr_tokens = self.advance_to(Token::Keyword(Keyword::Do));
-        let expr = expr::parse(expr_tokens, self.line)?;
-        let block_tokens = self.advance_to_block_end();
-        let block = parse(block_tokens)?;
+        let start = self.last_span.clone();
+        let expr = self.scan_expr()?;
+        self.expect(Token::Keyword(Keyword::Do))?;
+        let block = self.scan_block();
+        self.expect_end("while", &start)?;
 
-        Ok(Stmt{location, stmt_type : StmtType::While(expr, block)})
+        Ok(Stmt {span: start.to(&self.last_span), stmt_type : StmtType::While(expr, block)})
     }
 
-    fn handle_if_stmt(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-        let expr_tokens = self.advance_to(Token::Keyword(Keyword::Then));
-        let expr = expr::parse(expr_tokens, self.line)?;
-        let (block_tokens, block_end) = self.advance_to_if_end();
-        let block = parse(block_tokens)?;
+    fn handle_do_stmt(&mut self) -> Result<Stmt, LuaError>{
+        let start = self.last_span.clone();
+        let block = self.scan_block();
+        self.expect_end("do", &start)?;
 
-        if block_end == Some(Keyword::Else) {
-            let else_block_tokens = self.advance_to(Token::Keyword(Keyword::End));
+        Ok(Stmt {span: start.to(&self.last_span), stmt_type : StmtType::Do(block)})
+    }
 
-            return Ok(Stmt {location, stmt_type : StmtType::If(expr, block, Some(parse(else_block_tokens)?))})
-        }
+    /// Parses the rest of an if statement, the span is of the 'if' that the final 'end' closes.
+    /// An 'elseif' is parsed as an if statement in the else block
+    fn handle_if_stmt(&mut self, opened_at: &Span) -> Result<Stmt, LuaError>{
+        let start = self.last_span.clone();
+        let expr = self.scan_expr()?;
+        self.expect(Token::Keyword(Keyword::Then))?;
+        let block = self.scan_block();
+
+        let else_block = match self.next_token(){
+            Some(Token::Keyword(Keyword::End)) => None,
+            Some(Token::Keyword(Keyword::Else)) => {
+                let else_block = self.scan_block();
+                self.expect_end("if", opened_at)?;
+
+                Some(else_block)
+            },
+            Some(Token::Keyword(Keyword::Elseif)) => Some(vec![self.handle_if_stmt(opened_at)?]),
+            _ => return Err(self.end_error("if", opened_at)),
+        };
 
-        Ok(Stmt{location, stmt_type : StmtType::If(expr, block, None)})
+        Ok(Stmt {span: start.to(&self.last_span), stmt_type : StmtType::If(expr, block, else_block)})
     }
 
-    fn advance_to_if_end(&mut self) -> (Vec<Token>, Option<Keyword>){
-        let mut tokens = Vec::new();
-        let stop_keywords: Vec<Keyword> = vec![Keyword::End, Keyword::Else];
+    fn handle_func_dec(&mut self) -> Result<Stmt, LuaError>{
+        let start = self.last_span.clone();
+        let name = match self.next_token(){
+            Some(Token::Identifier(x)) => Token::Identifier(x),
+            x => return Err(self.parse_error(format!("Expected to find function name but found {:?}", x))),
+        };
 
-        loop{
-            let token = self.next_token();
+        self.expect(Token::LeftParenthesis)?;
+        let mut args = Vec::new();
 
-            if let Some(token) = token{
-                match token{
-                    Token::Keyword(ref k) if stop_keywords.contains(k) => return (tokens, Some(k.clone())), 
-                    _ => (), 
-                }
+        while self.peek() != Some(&Token::RightParenthesis){
+            match self.next_token(){
+                Some(Token::Identifier(x)) => args.push(Token::Identifier(x)),
+                x => return Err(self.parse_error(format!("Expected argument name but found {:?}", x))),
+            }
 
-                tokens.push(token);
-            }else{
+            if self.peek() != Some(&Token::Comma){
                 break;
             }
-        }
 
-        (tokens, None)
-    }
+            self.next_token();
+        }
 
+        self.expect(Token::RightParenthesis)?;
+        let block = self.scan_block();
+        self.expect_end("function", &start)?;
 
-    fn handle_func_dec(&mut self) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-        let name = match self.next_token(){
-            Some(x) => x,
-            None => return error(format!("Expected to find function name but found None"), self.line),
-        };
+        Ok(Stmt {span: start.to(&self.last_span), stmt_type : StmtType::FunctionDef(name, args, block)})
+    }
 
-        //Remove left parenthesis
+    /// Consumes the 'end' of a block, the span is of the keyword that opened it
+    fn expect_end(&mut self, opened_by: &str, opened_at: &Span) -> Result<(), LuaError>{
         match self.next_token(){
-            Some(Token::LeftParenthesis) => (),
-            x => return error(format!("Expected left parenthesis but found {:?}", x), self.line),
+            Some(Token::Keyword(Keyword::End)) => Ok(()),
+            _ => Err(self.end_error(opened_by, opened_at)),
         }
+    }
 
-        let mut args = self.advance_to(Token::RightParenthesis);
-        args.retain(|t| t != &Token::Comma);
+    fn end_error(&self, opened_by: &str, opened_at: &Span) -> LuaError{
+        self.parse_error(format!("'end' expected (to close '{}' at line {})", opened_by, opened_at.start_line))
+            .with_label(opened_at.clone(), &format!("this '{}' needs an 'end'", opened_by))
+            .with_kind(ErrorKind::SyntaxError(SyntaxErrorKind::UnclosedBlock))
+    }
 
-        let block_tokens = self.advance_to_block_end();
-        let block = parse(block_tokens)?;
+    /// Parses a statement that starts with an expression: a function call or an assignment
+    fn handle_expr_stmt(&mut self) -> Result<Stmt, LuaError>{
+        let start = self.peek_span();
+        let expr = self.scan_prefix_expr()?;
 
-        //Remove 'End'
-        self.next_token();
+        match self.peek(){
+            Some(Token::Equal) | Some(Token::Comma) => {
+                let mut targets = vec![expr];
 
-        Ok(Stmt{location, stmt_type : StmtType::FunctionDef(name, args, block)})
-    }
+                while self.peek() == Some(&Token::Comma){
+                    self.next_token();
+                    targets.push(self.scan_prefix_expr()?);
+                }
 
-    fn advance_to_block_end(&mut self) -> Vec<Token>{
-        let mut tokens = Vec::new();
-        let mut level = 0;
+                if self.next_token() != Some(Token::Equal){
+                    return Err(self.parse_error("Expected token '=' after variable names".to_string()));
+                }
 
-        loop{
-            let token = self.next_token();
-
-            if let Some(token) = token{
-                match token{
-                    Token::Keyword(Keyword::If) | Token::Keyword(Keyword::While) => level+=1,
-                    Token::Keyword(Keyword::End) => {
-                        if level == 0{
-                            break;
-                        }
-
-                        level -= 1;
-                    } 
-                    _ => (), 
+                if !targets.iter().all(is_assignable){
+                    return Err(self.parse_error("syntax error near '='".to_string()));
                 }
 
-                tokens.push(token);
-            }else{
-                break;
-            }
-        }
+                let stmt_type = StmtType::Assignment(targets, self.scan_expr_list()?);
 
-        tokens
+                Ok(Stmt {span: start.to(&self.last_span), stmt_type})
+            },
+            _ if expr.expr_type == ExprType::Call => Ok(Stmt {span: start.to(&self.last_span), stmt_type: expr.stmts.into_iter().next().unwrap().stmt_type}),
+            _ => Err(self.parse_error("syntax error: expected a function call or an assignment".to_string())),
+        }
     }
 
-    fn handle_indentifier(&mut self, token: Token) -> Result<Stmt, LuaError>{
-        let following_token = self.next_token();
-        let location = format!("Line {}", self.line);
+    fn handle_local(&mut self) -> Result<Stmt, LuaError>{
+        let start = self.last_span.clone();
+        let (names, attribs) = self.scan_name_list()?;
 
-        if let Some(following_token) = following_token{
-            match following_token{
-                Token::LeftParenthesis =>{
-                    let args = self.advance_to_args_end();
-                    let stmt_type = StmtType::FunctionCall(token, self.parse_args(args)?);
+        if attribs.iter().filter(|x| **x == LocalAttrib::Close).count() > 1{
+            let kind = ErrorKind::SyntaxError(SyntaxErrorKind::InvalidStatement);
 
-                    Ok(Stmt {location, stmt_type})
-                },
-                Token::Equal =>{
-                  Ok(self.scan_assignment(token, false)?)
-                },
-                _ => error(format!("Unknown token following identifier: {:?}", token), self.line),
-            }
-        }else{
-            error(format!("Files cannot end with identifiers!"), self.line)
+            return Err(self.parse_error("multiple to-be-closed variables in local list".to_string()).with_kind(kind));
         }
+
+        let exprs = if self.peek() == Some(&Token::Equal){
+            self.next_token();
+            self.scan_expr_list()?
+        }else{
+            Vec::new()
+        };
+
+        Ok(Stmt {span: start.to(&self.last_span), stmt_type: StmtType::Local(names, attribs, exprs)})
     }
 
-    fn advance_to_args_end(&mut self) -> Vec<Token>{
-        let mut tokens = Vec::new();
-        let mut level = 0;
+    /// Scans comma separated names, each optionally followed by an attribute like '<const>'
+    fn scan_name_list(&mut self) -> Result<(Vec<Token>, Vec<LocalAttrib>), LuaError>{
+        let mut names = Vec::new();
+        let mut attribs = Vec::new();
 
         loop{
-            let token = self.next_token();
-
-            if let Some(token) = token{
-                if token == Token::LeftParenthesis{
-                    level += 1;
-                }else if token == Token::RightParenthesis{
-                    if level == 0{
-                        break;
-                    }
-                    level -= 1;
-                }
+            match self.next_token(){
+                Some(Token::Identifier(x)) => names.push(Token::Identifier(x)),
+                x => return Err(self.parse_error(format!("Expected identifier but found {:?}", x))),
+            }
 
-                tokens.push(token);
+            if self.peek() == Some(&Token::Operator(BinOp::LessThan)){
+                self.next_token();
+                attribs.push(self.scan_attrib()?);
             }else{
-                break;
+                attribs.push(LocalAttrib::Plain);
             }
-        }
 
-        tokens
-    }
+            if self.peek() != Some(&Token::Comma){
+                return Ok((names, attribs));
+            }
 
-    fn handle_local(&mut self) -> Result<Stmt, LuaError>{
-        let name = match self.next_token(){
-            Some(x) => x,
-            None => return error(format!("Expected token following keyword local, but found None!"), self.line)
-        };
+            self.next_token();
+        }
+    }
 
-        let equal_token = match self.next_token(){
-            Some(x) => x,
-            None => return error(format!("Expected token '=' but found None!"), self.line)
+    /// Scans the name of an attribute and the closing '>'
+    fn scan_attrib(&mut self) -> Result<LocalAttrib, LuaError>{
+        let attrib = match self.next_token(){
+            Some(Token::Identifier(ref x)) if x == "const" => LocalAttrib::Const,
+            Some(Token::Identifier(ref x)) if x == "close" => LocalAttrib::Close,
+            Some(Token::Identifier(x)) => return Err(self.parse_error(format!("unknown attribute '{}'", x))),
+            x => return Err(self.parse_error(format!("Expected attribute name but found {:?}", x))),
         };
 
-        if equal_token != Token::Equal{
-            return error(format!("Expected token '=' but found, {:?}", equal_token), self.line);
+        match self.next_token(){
+            Some(Token::Operator(BinOp::GreaterThan)) => Ok(attrib),
+            x => Err(self.parse_error(format!("Expected token '>' but found {:?}", x))),
         }
-
-        self.scan_assignment(name, true)
     }
 
-    fn scan_assignment(&mut self, name: Token, is_local: bool) -> Result<Stmt, LuaError>{
-        let location = format!("Line {}", self.line);
-        let tokens = self.advance_to_mult(vec![Token::Newline, Token::Semicolon]);
-        let expr = expr::parse(tokens, self.line)?;
-        let stmt_type = StmtType::Assignment(name, expr, is_local);
-
-        Ok(Stmt {location, stmt_type})
+    pub(super) fn parse_error(&self, message: String) -> LuaError{
+        LuaError::create_parse(&message, Some(self.last_span.clone()))
     }
 
-    fn parse_args(&self, args: Vec<Token>) -> Result<Vec<Expr>, LuaError>{
-        let mut exprs= Vec::new();
-        let mut tokens = Vec::new();
-
-        for token in args{
-            if token == Token::Comma{
-                let expr = expr::parse(tokens.clone(), self.line)?;
-                exprs.push(expr);
-                tokens.clear();
-            }else{
-                tokens.push(token);
-            }
-        }
-
-        //Parse the last argument
-        if tokens.len() > 0{
-            let expr = expr::parse(tokens, self.line)?;
-            exprs.push(expr);
+    fn expect(&mut self, expected: Token) -> Result<(), LuaError>{
+        match self.next_token(){
+            Some(ref x) if *x == expected => Ok(()),
+            x => Err(self.parse_error(format!("Expected {:?} but found {:?}", expected, x))),
         }
-
-        Ok(exprs)
     }
 
-    fn advance_to(&mut self, stop: Token) -> Vec<Token>{
-        self.advance_to_mult(vec![stop])
+    fn peek(&self) -> Option<&Token>{
+        self.tokens.front().map(|info| &info.token)
     }
 
-    fn advance_to_mult(&mut self, stop: Vec<Token>)-> Vec<Token>{
-        let mut tokens = Vec::new();
-
-        loop{
-            let token = self.next_token();
-
-            if let Some(token) = token{
-                if stop.contains(&token) || token == Token::EOF{
-                    break;
-                }
-
-                tokens.push(token);
-            }else{
-                break;
-            }
-        }
+    /// The line of the next token, or of the last one at the end of the tokens
+    fn peek_line(&self) -> usize{
+        self.peek_span().start_line
+    }
 
-        tokens
+    /// The span of the next token, or of the last one at the end of the tokens
+    fn peek_span(&self) -> Span{
+        self.tokens.front().map(|info| info.span.clone()).unwrap_or_else(|| self.last_span.clone())
     }
 
     fn next_token(&mut self) -> Option<Token>{
+        self.next_token_info().map(|info| info.token)
+    }
+
+    fn next_token_info(&mut self) -> Option<TokenInfo>{
         let token = self.tokens.pop_front();
 
-        if token ==Some(Token::Newline){
-            self.line += 1;
+        if let Some(ref token) = token{
+            self.line = token.span.start_line;
+            self.last_span = token.span.clone();
+            self.consumed.push(token.token.clone());
         }
 
         token
     }
 }
 
-fn error(message: String, line: usize) -> Result<Stmt, LuaError>{
-    Err(LuaError::create_parse(&message, Some(format!("Line {}", line))))
+/// Whether a token can be the last one of an expression
+fn ends_value(token: &Token) -> bool{
+    matches!(token, Token::Identifier(_) | Token::StringLiteral(_) | Token::NumberLiteral(_) | Token::IntegerLiteral(_) |
+        Token::RightParenthesis | Token::RightBracket | Token::RightBrace | Token::Keyword(Keyword::True) | Token::Keyword(Keyword::False))
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<Vec<Stmt>, LuaError>{
-    let mut parser = Parser::new(tokens);
+/// Only names and fields can be assigned to
+fn is_assignable(expr: &Expr) -> bool{
+    match expr.stmts[0].stmt_type{
+        StmtType::Value(ref tokens) => matches!(tokens[..], [Token::Identifier(_)]),
+        StmtType::Index(_, _) => expr.expr_type == ExprType::SingleValue,
+        _ => false,
+    }
+}
 
-    parser.parse()
-}
\ No newline at end of file
+/// Parses and checks a chunk, returns all of its syntax errors if there are any
+pub fn parse(tokens: Vec<TokenInfo>) -> Result<Vec<Stmt>, Vec<LuaError>>{
+    Parser::new(tokens).parse()
+}
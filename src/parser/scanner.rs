@@ -1,20 +1,36 @@
 
-use super::{Token, BinOp, Keyword};
+use super::{Token, TokenInfo, Span, BinOp, Keyword};
 use super::super::error::{LuaError};
+use super::super::data::{LuaData, str_to_number};
+
+/// The chunk name of source code that doesn't come from a file
+pub const DEFAULT_CHUNK: &str = "main";
 
 pub struct Scanner{
     src: Vec<char>,
     curr: usize,
     pub line_num: usize,
+    /// The column of the next character
+    column: usize,
+    chunk: String,
+    /// The line and column where the token that is being scanned starts
+    token_start: (usize, usize),
+    /// The line and column of the last character that was read
+    last_position: (usize, usize),
 }
 
 impl Scanner{
 
     pub fn new(src: String) -> Scanner{
-        Scanner{src : src.chars().collect(), curr : 0, line_num: 1}
+        Scanner::with_chunk(src, DEFAULT_CHUNK)
+    }
+
+    pub fn with_chunk(src: String, chunk: &str) -> Scanner{
+        Scanner{src : src.chars().collect(), curr : 0, line_num: 1, column: 1, chunk: chunk.to_string(), 
+            token_start: (1, 1), last_position: (1, 0)}
     }
 
-    pub fn scan(&mut self) -> Result<Vec<Token>, Vec<LuaError>>{
+    pub fn scan(&mut self) -> Result<Vec<TokenInfo>, Vec<LuaError>>{
         let mut tokens = Vec::new();
         let mut errors = Vec::new();
 
@@ -27,12 +43,18 @@ impl Scanner{
                 },
             };
 
-            if token == Token::EOF{
-                tokens.push(token);
+            let is_eof = token == Token::EOF;
+            let span = if is_eof{
+                self.span(self.token_start, self.token_start)
+            }else{
+                self.span(self.token_start, self.last_position)
+            };
+
+            tokens.push(TokenInfo{token, span});
+
+            if is_eof{
                 break;
             }
-
-            tokens.push(token);
         }
 
         if errors.len() > 0{
@@ -43,7 +65,7 @@ impl Scanner{
     }
 
     fn scan_token(&mut self) -> Result<Token, LuaError>{
-        let line = self.line_num.clone();
+        self.token_start = (self.line_num, self.column);
         let next_char = self.advance_character().clone();
 
         if let Some(c) = next_char{
@@ -52,23 +74,31 @@ impl Scanner{
                 ')' => Ok(Token::RightParenthesis),
                 ',' => Ok(Token::Comma),
                 '"' => self.scan_string(),
-                '\n' => Ok(Token::Newline),
                 ';' => Ok(Token::Semicolon),
                 '=' => self.scan_equals(),
                 '+' => Ok(Token::Operator(BinOp::Plus)),
                 '-' => self.check_comment(),
                 '*' => Ok(Token::Operator(BinOp::Multiply)),
-                '/' => Ok(Token::Operator(BinOp::Divide)),
+                '/' => self.scan_slash(),
+                '%' => Ok(Token::Operator(BinOp::Modulo)),
+                '^' => Ok(Token::Operator(BinOp::Power)),
+                '#' => Ok(Token::Hash),
                 '.' => self.check_elipse(),  
                 '<' => self.scan_less_than(),
                 '>' => self.scan_greater_than(),
+                '&' => Ok(Token::Operator(BinOp::BitAnd)),
+                '|' => Ok(Token::Operator(BinOp::BitOr)),
+                '~' => self.scan_tilde(),
                 '{' => Ok(Token::LeftBrace),
                 '}' => Ok(Token::RightBrace),
-                ' ' | '\t' | '\r' => self.scan_token(),
-                x if x.is_alphabetic() => self.scan_identifier(),
+                '[' => Ok(Token::LeftBracket),
+                ']' => Ok(Token::RightBracket),
+                ':' => Ok(Token::Colon),
+                ' ' | '\t' | '\r' | '\n' => self.scan_token(),
+                x if x.is_alphabetic() || x == '_' => self.scan_identifier(),
                 n if n.is_numeric() => self.scan_number(),
                 x => {
-                    return error(format!("Unknown Character: {}", x), line);
+                    return Err(self.error(format!("Unknown Character: {}", x)));
                 }
             }
         }else{
@@ -141,7 +171,7 @@ impl Scanner{
 
             if let Some(c) = c{
                 if c == '\n'{
-                    return Ok(Token::Newline);
+                    return self.scan_token();
                 }
             }else{
                 return Ok(Token::EOF);
@@ -150,38 +180,60 @@ impl Scanner{
     }
 
     fn scan_greater_than(&mut self) -> Result<Token, LuaError>{
-        if self.advance_character().unwrap_or(' ') == '='{
-            return Ok(Token::Operator(BinOp::GreaterEqualThan));
+        match self.peek(){
+            Some('=') => {
+                self.advance_character();
+                Ok(Token::Operator(BinOp::GreaterEqualThan))
+            },
+            Some('>') => {
+                self.advance_character();
+                Ok(Token::Operator(BinOp::ShiftRight))
+            },
+            _ => Ok(Token::Operator(BinOp::GreaterThan)),
         }
-
-        self.curr -= 1;
-
-        Ok(Token::Operator(BinOp::GreaterThan))
     }
 
     fn scan_less_than(&mut self) -> Result<Token, LuaError>{
-        if self.advance_character().unwrap_or(' ') == '='{
-            return Ok(Token::Operator(BinOp::LessEqualThan));
+        match self.peek(){
+            Some('=') => {
+                self.advance_character();
+                Ok(Token::Operator(BinOp::LessEqualThan))
+            },
+            Some('<') => {
+                self.advance_character();
+                Ok(Token::Operator(BinOp::ShiftLeft))
+            },
+            _ => Ok(Token::Operator(BinOp::LessThan)),
         }
+    }
 
-        self.curr -= 1;
+    fn scan_slash(&mut self) -> Result<Token, LuaError>{
+        if self.peek() == Some('/'){
+            self.advance_character();
+            return Ok(Token::Operator(BinOp::FloorDivide));
+        }
+
+        Ok(Token::Operator(BinOp::Divide))
+    }
 
-        Ok(Token::Operator(BinOp::LessThan))
+    fn scan_tilde(&mut self) -> Result<Token, LuaError>{
+        if self.peek() == Some('='){
+            self.advance_character();
+            return Ok(Token::Operator(BinOp::NotEqual));
+        }
+
+        // Binary xor, the parser treats it as a bitwise not when it is used as a unary operator
+        Ok(Token::Operator(BinOp::BitXor))
     }
 
 
+    /// Dots between names are part of the identifier, so a single dot only follows other tokens, i.e. 'f().x'
     fn check_elipse(&mut self) -> Result<Token, LuaError>{
-        let line = self.line_num.clone();
-        let c = self.advance_character();
-
-        if let Some(c) = c{
-            if c != '.'{
-                return error(format!("Expected ellipse, found: {}", c), line);
-            }
-        }else{
-            return error(format!("File cannot end with character '.'"), line);
+        if self.peek() != Some('.'){
+            return Ok(Token::Dot);
         }
 
+        self.advance_character();
         Ok(Token::Operator(BinOp::Concat))
     }
 
@@ -206,47 +258,56 @@ impl Scanner{
     }
 
     fn scan_number(&mut self) -> Result<Token, LuaError>{
-        let mut had_decimal = false;
-        let mut char_vec: Vec<char> = Vec::new();
+        // Read the first digit again
         self.curr -= 1;
+        self.column -= 1;
 
-        loop{
-            if !self.peek().unwrap_or(' ').is_numeric() {
-                if self.peek().unwrap_or(' ') != '.' || had_decimal{
-                    break;
-                }
-
-                had_decimal = true;
-            }
+        let mut exponent_chars = ['e', 'E'];
+        let mut char_vec: Vec<char> = Vec::new();
 
+        if self.peek() == Some('0') && (self.char_at(self.curr + 1) == Some('x') || self.char_at(self.curr + 1) == Some('X')){
+            exponent_chars = ['p', 'P'];
+            char_vec.push(self.advance_character().unwrap());
             char_vec.push(self.advance_character().unwrap());
         }
 
+        // Read everything that could be part of the numeral, str_to_number validates it
+        while let Some(c) = self.peek(){
+            if exponent_chars.contains(&c){
+                char_vec.push(c);
+                self.advance_character();
+
+                if let Some(sign) = self.peek().filter(|x| *x == '+' || *x == '-'){
+                    char_vec.push(sign);
+                    self.advance_character();
+                }
+            }else if c.is_ascii_hexdigit() || c == '.'{
+                char_vec.push(c);
+                self.advance_character();
+            }else{
+                break;
+            }
+        }
+
         let string: String = char_vec.iter().collect();
 
-        match string.parse::<f64>(){
-            Ok(n) => Ok(Token::NumberLiteral(n)),
-            Err(e) => error(format!("Unable to parse number literal {}: {}", string, e), self.line_num),
+        match str_to_number(&string){
+            Some(LuaData::Int(n)) => Ok(Token::IntegerLiteral(n)),
+            Some(LuaData::Number(n)) => Ok(Token::NumberLiteral(n)),
+            _ => Err(self.error(format!("Malformed number near '{}'", string))),
         }
     }
 
     fn scan_identifier(&mut self) -> Result<Token, LuaError>{
         let mut char_vec: Vec<char> = vec![self.char_at(self.curr - 1).unwrap()];
-        let stop_chars = vec![Some(' '), Some('\n'), Some('\t'), Some('('), 
-            Some(')'), Some(','), Some('\r'), Some(';'), Some('=')];
 
-        loop{
-            if stop_chars.contains(&self.peek()){
+        while let Some(c) = self.peek(){
+            if !(c.is_alphanumeric() || c == '_' || c == '.'){
                 break;
             }
 
-            let character_option = self.advance_character();
-
-            if let Some(c) = character_option{
-                char_vec.push(c);   
-            }else{
-                return Ok(Token::EOF);
-            }
+            char_vec.push(c);
+            self.advance_character();
         }
 
         let string: String = char_vec.iter().collect();
@@ -273,22 +334,35 @@ impl Scanner{
     fn advance_character(&mut self) -> Option<char>{
         let c = self.char_at(self.curr);
         self.curr += 1;
+        self.last_position = (self.line_num, self.column);
 
         if c == Some('\n'){
             self.line_num += 1;
+            self.column = 1;
+        }else if c.is_some(){
+            self.column += 1;
         }
 
         c
     }
 
-}
+    fn span(&self, start: (usize, usize), end: (usize, usize)) -> Span{
+        Span::new(&self.chunk, start, end)
+    }
 
-fn error(message: String, line: usize) -> Result<Token, LuaError>{
-        Err(LuaError::create_lexical(&message, Some(format!("[Line {}]", line))))
+    /// An error at the token that is being scanned
+    fn error(&self, message: String) -> LuaError{
+        LuaError::create_lexical(&message, Some(self.span(self.token_start, self.last_position)))
     }
 
-pub fn scan(src: String) -> Result<Vec<Token>, Vec<LuaError>>{
-    let mut scanner = Scanner::new(src);
+}
+
+pub fn scan(src: String) -> Result<Vec<TokenInfo>, Vec<LuaError>>{
+    scan_chunk(src, DEFAULT_CHUNK)
+}
+
+pub fn scan_chunk(src: String, chunk: &str) -> Result<Vec<TokenInfo>, Vec<LuaError>>{
+    let mut scanner = Scanner::with_chunk(src, chunk);
 
     scanner.scan()
 }
\ No newline at end of file
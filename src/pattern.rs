@@ -0,0 +1,567 @@
+//! Lua patterns, as used by 'string.find', 'match', 'gmatch' and 'gsub'.
+//! Subjects and patterns are bytes, positions are byte offsets
+
+/// The most captures a pattern can have
+pub const MAX_CAPTURES: usize = 32;
+
+/// How deep the matcher may recurse before the pattern is rejected as too complex
+pub const MAX_DEPTH: usize = 200;
+
+const ESCAPE: u8 = b'%';
+
+/// A captured value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Capture{
+    /// The start and end offsets of the captured text
+    Text(usize, usize),
+    /// A position capture '()', 1-based like lua positions
+    Position(usize),
+}
+
+/// A match of a pattern, from the start offset up to (not including) the end offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match{
+    pub start: usize,
+    pub end: usize,
+    pub captures: Vec<Capture>,
+}
+
+/// The length of a capture while matching
+#[derive(Clone, Copy, PartialEq)]
+enum CaptureLength{
+    Unfinished,
+    Position,
+    Closed(usize),
+}
+
+struct MatchState<'a>{
+    subject: &'a [u8],
+    pattern: &'a [u8],
+    depth: usize,
+    captures: Vec<(usize, CaptureLength)>,
+}
+
+/// Finds the first match at or after the start offset, a leading '^' anchors the pattern
+pub fn find(subject: &[u8], pattern: &[u8], start: usize) -> Result<Option<Match>, String>{
+    let (anchored, pattern) = match pattern.first(){
+        Some(b'^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+
+    let mut position = start;
+
+    loop{
+        if let Some(m) = match_at(subject, pattern, position)?{
+            return Ok(Some(m));
+        }
+
+        position += 1;
+
+        if anchored || position > subject.len(){
+            return Ok(None);
+        }
+    }
+}
+
+/// Matches the pattern at exactly the start offset. A leading '^' is not treated as an anchor
+pub fn match_at(subject: &[u8], pattern: &[u8], start: usize) -> Result<Option<Match>, String>{
+    let mut state = MatchState {subject, pattern, depth: 0, captures: Vec::new()};
+
+    let end = match state.do_match(start, 0)?{
+        Some(end) => end,
+        None => return Ok(None),
+    };
+
+    let mut captures = Vec::new();
+    for (capture_start, length) in state.captures{
+        captures.push(match length{
+            CaptureLength::Closed(length) => Capture::Text(capture_start, capture_start + length),
+            CaptureLength::Position => Capture::Position(capture_start + 1),
+            CaptureLength::Unfinished => return Err("unfinished capture".to_string()),
+        });
+    }
+
+    Ok(Some(Match {start, end, captures}))
+}
+
+/// Whether a pattern has no special characters and can be searched for literally
+pub fn is_plain(pattern: &[u8]) -> bool{
+    !pattern.iter().any(|c| b"^$*+?.([%-".contains(c))
+}
+
+impl<'a> MatchState<'a>{
+
+    /// Matches the pattern from the pattern offset at the subject offset, returns the end of the match
+    fn do_match(&mut self, s: usize, p: usize) -> Result<Option<usize>, String>{
+        if self.depth >= MAX_DEPTH{
+            return Err("pattern too complex".to_string());
+        }
+
+        self.depth += 1;
+        let result = self.match_here(s, p);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn match_here(&mut self, mut s: usize, mut p: usize) -> Result<Option<usize>, String>{
+        loop{
+            if p == self.pattern.len(){
+                return Ok(Some(s));
+            }
+
+            let next = self.pattern.get(p + 1).copied();
+
+            match self.pattern[p]{
+                b'(' if next == Some(b')') => return self.start_capture(s, p + 2, CaptureLength::Position),
+                b'(' => return self.start_capture(s, p + 1, CaptureLength::Unfinished),
+                b')' => return self.end_capture(s, p + 1),
+                b'$' if p + 1 == self.pattern.len() => {
+                    return Ok(if s == self.subject.len() {Some(s)} else {None});
+                },
+                ESCAPE if next == Some(b'b') => match self.match_balance(s, p + 2)?{
+                    Some(end) => {
+                        s = end;
+                        p += 4;
+                        continue;
+                    },
+                    None => return Ok(None),
+                },
+                ESCAPE if next == Some(b'f') => {
+                    p += 2;
+                    if self.pattern.get(p) != Some(&b'['){
+                        return Err("missing '[' after '%f' in pattern".to_string());
+                    }
+
+                    // The frontier matches where the previous byte isn't in the set and the current one is
+                    let class_end = self.class_end(p)?;
+                    let previous = if s == 0 {0} else {self.subject[s - 1]};
+                    let current = self.subject.get(s).copied().unwrap_or(0);
+
+                    if !self.match_bracket_class(previous, p, class_end - 1) && self.match_bracket_class(current, p, class_end - 1){
+                        p = class_end;
+                        continue;
+                    }
+
+                    return Ok(None);
+                },
+                ESCAPE if next.is_some_and(|c| c.is_ascii_digit()) => match self.match_capture(s, next.unwrap())?{
+                    Some(end) => {
+                        s = end;
+                        p += 2;
+                        continue;
+                    },
+                    None => return Ok(None),
+                },
+                _ => (),
+            }
+
+            // A single character class, possibly followed by a quantifier
+            let class_end = self.class_end(p)?;
+            let is_match = s < self.subject.len() && self.single_match(self.subject[s], p, class_end);
+
+            match self.pattern.get(class_end){
+                Some(b'?') => {
+                    if is_match{
+                        if let Some(end) = self.do_match(s + 1, class_end + 1)?{
+                            return Ok(Some(end));
+                        }
+                    }
+
+                    p = class_end + 1;
+                },
+                Some(b'+') => return if is_match {self.max_expand(s + 1, p, class_end)} else {Ok(None)},
+                Some(b'*') => return self.max_expand(s, p, class_end),
+                Some(b'-') => return self.min_expand(s, p, class_end),
+                _ => {
+                    if !is_match{
+                        return Ok(None);
+                    }
+
+                    s += 1;
+                    p = class_end;
+                },
+            }
+        }
+    }
+
+    /// The offset just after the single character class at the pattern offset
+    fn class_end(&self, mut p: usize) -> Result<usize, String>{
+        let c = self.pattern[p];
+        p += 1;
+
+        if c == ESCAPE{
+            if p >= self.pattern.len(){
+                return Err("malformed pattern (ends with '%')".to_string());
+            }
+
+            return Ok(p + 1);
+        }
+
+        if c == b'['{
+            if self.pattern.get(p) == Some(&b'^'){
+                p += 1;
+            }
+
+            // The first character of a set is never its end, so '[]]' is a set of ']'
+            loop{
+                if p >= self.pattern.len(){
+                    return Err("malformed pattern (missing ']')".to_string());
+                }
+
+                let c = self.pattern[p];
+                p += 1;
+
+                if c == ESCAPE && p < self.pattern.len(){
+                    p += 1;
+                }
+
+                if self.pattern.get(p) == Some(&b']'){
+                    return Ok(p + 1);
+                }
+            }
+        }
+
+        Ok(p)
+    }
+
+    /// Whether a byte matches the single character class between the pattern offsets
+    fn single_match(&self, c: u8, p: usize, class_end: usize) -> bool{
+        match self.pattern[p]{
+            b'.' => true,
+            ESCAPE => match_class(c, self.pattern[p + 1]),
+            b'[' => self.match_bracket_class(c, p, class_end - 1),
+            x => x == c,
+        }
+    }
+
+    /// Whether a byte is in the set from the '[' at the pattern offset to the ']' at the end offset
+    fn match_bracket_class(&self, c: u8, mut p: usize, end: usize) -> bool{
+        let mut matches = true;
+        p += 1;
+
+        if self.pattern[p] == b'^'{
+            matches = false;
+            p += 1;
+        }
+
+        while p < end{
+            if self.pattern[p] == ESCAPE{
+                p += 1;
+
+                if match_class(c, self.pattern[p]){
+                    return matches;
+                }
+
+                p += 1;
+            }else if self.pattern[p + 1] == b'-' && p + 2 < end{
+                if self.pattern[p] <= c && c <= self.pattern[p + 2]{
+                    return matches;
+                }
+
+                p += 3;
+            }else{
+                if self.pattern[p] == c{
+                    return matches;
+                }
+
+                p += 1;
+            }
+        }
+
+        !matches
+    }
+
+    /// Matches '%bxy', text that starts with x and ends with the y that balances it
+    fn match_balance(&self, s: usize, p: usize) -> Result<Option<usize>, String>{
+        if p + 1 >= self.pattern.len(){
+            return Err("malformed pattern (missing arguments to '%b')".to_string());
+        }
+
+        if self.subject.get(s) != Some(&self.pattern[p]){
+            return Ok(None);
+        }
+
+        let (open, close) = (self.pattern[p], self.pattern[p + 1]);
+        let mut depth = 1;
+
+        for index in s + 1..self.subject.len(){
+            let c = self.subject[index];
+
+            if c == close{
+                depth -= 1;
+
+                if depth == 0{
+                    return Ok(Some(index + 1));
+                }
+            }else if c == open{
+                depth += 1;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Matches as many repetitions of the class as possible, then backs off until the rest matches
+    fn max_expand(&mut self, s: usize, p: usize, class_end: usize) -> Result<Option<usize>, String>{
+        let mut count = 0;
+
+        while s + count < self.subject.len() && self.single_match(self.subject[s + count], p, class_end){
+            count += 1;
+        }
+
+        loop{
+            if let Some(end) = self.do_match(s + count, class_end + 1)?{
+                return Ok(Some(end));
+            }
+
+            if count == 0{
+                return Ok(None);
+            }
+
+            count -= 1;
+        }
+    }
+
+    /// Matches as few repetitions of the class as possible
+    fn min_expand(&mut self, mut s: usize, p: usize, class_end: usize) -> Result<Option<usize>, String>{
+        loop{
+            if let Some(end) = self.do_match(s, class_end + 1)?{
+                return Ok(Some(end));
+            }
+
+            if s < self.subject.len() && self.single_match(self.subject[s], p, class_end){
+                s += 1;
+            }else{
+                return Ok(None);
+            }
+        }
+    }
+
+    fn start_capture(&mut self, s: usize, p: usize, length: CaptureLength) -> Result<Option<usize>, String>{
+        if self.captures.len() >= MAX_CAPTURES{
+            return Err("too many captures".to_string());
+        }
+
+        self.captures.push((s, length));
+
+        let result = self.do_match(s, p)?;
+        if result.is_none(){
+            self.captures.pop();
+        }
+
+        Ok(result)
+    }
+
+    fn end_capture(&mut self, s: usize, p: usize) -> Result<Option<usize>, String>{
+        let index = match self.captures.iter().rposition(|(_, length)| *length == CaptureLength::Unfinished){
+            Some(x) => x,
+            None => return Err("invalid pattern capture".to_string()),
+        };
+
+        self.captures[index].1 = CaptureLength::Closed(s - self.captures[index].0);
+
+        let result = self.do_match(s, p)?;
+        if result.is_none(){
+            self.captures[index].1 = CaptureLength::Unfinished;
+        }
+
+        Ok(result)
+    }
+
+    /// Matches a back reference such as '%1', the same text as the finished capture
+    fn match_capture(&self, s: usize, digit: u8) -> Result<Option<usize>, String>{
+        let index = (digit as usize).wrapping_sub(b'1' as usize);
+
+        let (start, length) = match self.captures.get(index){
+            Some((start, CaptureLength::Closed(length))) => (*start, *length),
+            // Position captures have no text, so they never match
+            Some((_, CaptureLength::Position)) => return Ok(None),
+            _ => return Err(format!("invalid capture index %{} in pattern", index.wrapping_add(1))),
+        };
+
+        if self.subject.len() - s >= length && self.subject[start..start + length] == self.subject[s..s + length]{
+            Ok(Some(s + length))
+        }else{
+            Ok(None)
+        }
+    }
+}
+
+/// Whether a byte is in a class such as '%a', upper case classes are the complement
+fn match_class(c: u8, class: u8) -> bool{
+    let matches = match class.to_ascii_lowercase(){
+        b'a' => c.is_ascii_alphabetic(),
+        b'c' => c.is_ascii_control(),
+        b'd' => c.is_ascii_digit(),
+        b'g' => c.is_ascii_graphic(),
+        b'l' => c.is_ascii_lowercase(),
+        b'p' => c.is_ascii_punctuation(),
+        // Like C's isspace, which includes the vertical tab
+        b's' => matches!(c, b' ' | b'\t'..=b'\r'),
+        b'u' => c.is_ascii_uppercase(),
+        b'w' => c.is_ascii_alphanumeric(),
+        b'x' => c.is_ascii_hexdigit(),
+        _ => return class == c,
+    };
+
+    if class.is_ascii_uppercase() {!matches} else {matches}
+}
+
+#[cfg(test)]
+mod pattern_tests{
+
+    use super::{find, match_at, is_plain, Match, Capture};
+
+    /// The text of the first match, or None if there is no match
+    fn matched(subject: &str, pattern: &str) -> Option<String>{
+        find(subject.as_bytes(), pattern.as_bytes(), 0).unwrap().map(|m| subject[m.start..m.end].to_string())
+    }
+
+    /// The captured text of the first match
+    fn captures(subject: &str, pattern: &str) -> Vec<String>{
+        let m = find(subject.as_bytes(), pattern.as_bytes(), 0).unwrap().unwrap();
+
+        m.captures.iter().map(|capture| match capture{
+            Capture::Text(start, end) => subject[*start..*end].to_string(),
+            Capture::Position(position) => position.to_string(),
+        }).collect()
+    }
+
+    fn error(subject: &str, pattern: &str) -> String{
+        find(subject.as_bytes(), pattern.as_bytes(), 0).unwrap_err()
+    }
+
+    #[test]
+    fn class_test(){
+        let cases = vec![("abc123", "%d+", "123"), ("  hi", "%a+", "hi"), ("a b", "%s", " "), ("ab_c", "%w+", "ab"),
+            ("x.y", "%p", "."), ("abCD", "%u+", "CD"), ("ABcd", "%l+", "cd"), ("0xfF", "%x+", "0"), ("a\tb", "%c", "\t"),
+            ("  x", "%g", "x"), ("abc123", "%D+", "abc"), ("12ab", "%A+", "12"), ("a\u{b}b", "%s", "\u{b}"), ("a.b", "%.", ".")];
+
+        for (subject, pattern, expected) in cases{
+            assert_eq!(matched(subject, pattern), Some(expected.to_string()), "{} with {}", subject, pattern);
+        }
+    }
+
+    #[test]
+    fn set_test(){
+        let cases = vec![("hello", "[aeiou]+", "e"), ("hello", "[^aeiou]+", "h"), ("x-y", "[%-]", "-"), ("abc9", "[a-c]+", "abc"),
+            ("]x", "[]]", "]"), ("a1_", "[%d_]+", "1_"), ("-a", "[a-]", "-"), ("^x", "[%^x]+", "^x")];
+
+        for (subject, pattern, expected) in cases{
+            assert_eq!(matched(subject, pattern), Some(expected.to_string()), "{} with {}", subject, pattern);
+        }
+    }
+
+    #[test]
+    fn byte_range_test(){
+        // Ranges compare bytes, so the UTF-8 char pattern matches one encoded character
+        let char_pattern = b"[\0-\x7F\xC2-\xFD][\x80-\xBF]*";
+
+        let m = find("ñandú".as_bytes(), char_pattern, 0).unwrap().unwrap();
+        assert_eq!((m.start, m.end), (0, 2));
+        let m = find("x€".as_bytes(), char_pattern, 1).unwrap().unwrap();
+        assert_eq!((m.start, m.end), (1, 4));
+        assert!(find(&[0xC3], b"[\x80-\xBF]", 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn quantifier_test(){
+        let cases = vec![("aaab", "a*", Some("aaa")), ("baaa", "a*", Some("")), ("aaab", "a+b", Some("aaab")), ("b", "a+", None),
+            ("<a><b>", "<.->", Some("<a>")), ("<a><b>", "<.*>", Some("<a><b>")), ("color", "colou?r", Some("color")),
+            ("colour", "colou?r", Some("colour")), ("aaa", "a-$", Some("aaa")), ("ab", "a?b?c?", Some("ab"))];
+
+        for (subject, pattern, expected) in cases{
+            assert_eq!(matched(subject, pattern), expected.map(|x| x.to_string()), "{} with {}", subject, pattern);
+        }
+    }
+
+    #[test]
+    fn anchor_test(){
+        assert_eq!(matched("hello", "^h"), Some("h".to_string()));
+        assert_eq!(matched("hello", "^e"), None);
+        assert_eq!(matched("hello", "o$"), Some("o".to_string()));
+        assert_eq!(matched("hello", "l$"), None);
+        assert_eq!(matched("a$b", "$b"), Some("$b".to_string()));
+        assert_eq!(matched("", "^$"), Some("".to_string()));
+
+        // Only find treats '^' as an anchor
+        assert_eq!(match_at(b"^a", b"^a", 0).unwrap().map(|m| m.end), Some(2));
+    }
+
+    #[test]
+    fn capture_test(){
+        assert_eq!(captures("key = value", "(%w+)%s*=%s*(%w+)"), vec!["key", "value"]);
+        assert_eq!(captures("hello", "()ll()"), vec!["3", "5"]);
+        assert_eq!(captures("abc", "(a(b)c)"), vec!["abc", "b"]);
+        assert_eq!(captures("hello", "(h)(e)(l)"), vec!["h", "e", "l"]);
+        assert_eq!(captures("x", "()"), vec!["1"]);
+    }
+
+    #[test]
+    fn back_reference_test(){
+        assert_eq!(matched("say \"hi\" now", "([\"'])(.-)%1"), Some("\"hi\"".to_string()));
+        assert_eq!(matched("abab", "(ab)%1"), Some("abab".to_string()));
+        assert_eq!(matched("abac", "(ab)%1"), None);
+    }
+
+    #[test]
+    fn balance_test(){
+        assert_eq!(matched("f(a(b)c) d", "%b()"), Some("(a(b)c)".to_string()));
+        assert_eq!(matched("((a)", "%b()"), Some("(a)".to_string()));
+        assert_eq!(matched("(a", "%b()"), None);
+        assert_eq!(matched("[x]y", "%b[]y"), Some("[x]y".to_string()));
+    }
+
+    #[test]
+    fn frontier_test(){
+        assert_eq!(matched("THE (quick) fox", "%f[%a]%a+"), Some("THE".to_string()));
+        assert_eq!(captures("hello world", "%f[%w]()%w+"), vec!["1"]);
+        assert_eq!(matched("foo bar", "%f[%a]bar"), Some("bar".to_string()));
+        assert_eq!(matched("foobar", "%f[%a]bar"), None);
+        assert_eq!(matched("ab", "b%f[^%a]"), Some("b".to_string()));
+    }
+
+    #[test]
+    fn start_test(){
+        let m = find(b"abcabc", b"b", 2).unwrap().unwrap();
+        assert_eq!(m, Match {start: 4, end: 5, captures: Vec::new()});
+
+        assert_eq!(find(b"abc", b"", 3).unwrap().map(|m| m.start), Some(3));
+        assert_eq!(find(b"abc", b"^b", 1).unwrap().map(|m| m.start), Some(1));
+    }
+
+    #[test]
+    fn malformed_pattern_test(){
+        assert_eq!(error("a", "%"), "malformed pattern (ends with '%')");
+        assert_eq!(error("a", "[a"), "malformed pattern (missing ']')");
+        assert_eq!(error("a", "[a%"), "malformed pattern (missing ']')");
+        assert_eq!(error("a", "%b"), "malformed pattern (missing arguments to '%b')");
+        assert_eq!(error("a", "%fa"), "missing '[' after '%f' in pattern");
+        assert_eq!(error("a", "a)"), "invalid pattern capture");
+        assert_eq!(error("a", "(a"), "unfinished capture");
+        assert_eq!(error("a", "%1"), "invalid capture index %1 in pattern");
+        assert_eq!(error("aa", "(a%1)"), "invalid capture index %1 in pattern");
+        assert_eq!(error("a", &"()".repeat(33)), "too many captures");
+    }
+
+    #[test]
+    fn recursion_limit_test(){
+        // Each optional item recurses, so a long pattern of them is rejected instead of overflowing the stack
+        let subject = "a".repeat(10000);
+        let pattern = "a?".repeat(10000);
+
+        assert_eq!(error(&subject, &pattern), "pattern too complex");
+
+        // Repetitions don't recurse per character, so long subjects are fine
+        assert_eq!(matched(&subject, "a*$").map(|x| x.len()), Some(10000));
+        assert_eq!(matched(&subject, "a-$").map(|x| x.len()), Some(10000));
+    }
+
+    #[test]
+    fn is_plain_test(){
+        assert!(is_plain(b"hello world"));
+        assert!(!is_plain(b"a.b"));
+        assert!(!is_plain(b"100%"));
+    }
+}
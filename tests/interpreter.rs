@@ -18,4 +18,513 @@ fn module_doesnt_load_twice_test() {
         //Core library will fail if loaded twice. This test is simply to guarentee that test case happens
     let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
     aurora.run(r#"require("lib/core");require("lib/core")"#.to_string()).unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn bitwise_float_without_integer_representation_fails_test() {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run("x = 1.5 | 0".to_string()).unwrap_err();
+
+    assert_eq!(errors[0].message, "number has no integer representation");
+}
+
+#[test]
+fn bitwise_on_non_number_fails_test() {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run("x = true & 1".to_string()).unwrap_err();
+
+    assert_eq!(errors[0].message, "attempt to perform bitwise operation on a boolean value");
+}
+
+#[test]
+fn arithmetic_on_non_numeric_string_fails_test() {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run(r#"x = "ten" + 1"#.to_string()).unwrap_err();
+
+    assert_eq!(errors[0].message, "attempt to perform arithmetic on a string value (constant 'ten')");
+}
+
+#[test]
+fn concatenate_table_fails_test() {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run(r#"x = "table: " .. {}"#.to_string()).unwrap_err();
+
+    assert_eq!(errors[0].message, "attempt to concatenate a table value");
+}
+
+#[test]
+fn compare_incompatible_types_fails_test() {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run(r#"x = 1 < "2""#.to_string()).unwrap_err();
+
+    assert_eq!(errors[0].message, "attempt to compare number with string");
+}
+
+#[test]
+fn compare_two_tables_fails_test() {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run("x = {} <= {}".to_string()).unwrap_err();
+
+    assert_eq!(errors[0].message, "attempt to compare two table values");
+}
+
+fn run_error(src: &str) -> String {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run(src.to_string()).unwrap_err();
+
+    errors[0].message.clone()
+}
+
+#[test]
+fn arithmetic_on_nil_names_variable_test() {
+    assert_eq!(run_error("x = y + 1"), "attempt to perform arithmetic on a nil value (global 'y')");
+    assert_eq!(run_error("x = -y"), "attempt to perform arithmetic on a nil value (global 'y')");
+    assert_eq!(run_error("x = 1 - true"), "attempt to perform arithmetic on a boolean value");
+    assert_eq!(run_error("t = {}\nx = 2 * t.size"), "attempt to perform arithmetic on a nil value (field 'size')");
+    assert_eq!(run_error("function f(a)\nreturn a / 2\nend\nf({})"), "attempt to perform arithmetic on a table value (local 'a')");
+    assert_eq!(run_error("x = 1 .. y"), "attempt to concatenate a nil value (global 'y')");
+}
+
+#[test]
+fn index_non_table_names_variable_test() {
+    assert_eq!(run_error("x = cfg.width"), "attempt to index a nil value (global 'cfg')");
+    assert_eq!(run_error("cfg = {}\nx = cfg.window.width"), "attempt to index a nil value (field 'window')");
+    assert_eq!(run_error("cfg = 5\ncfg.width = 1"), "attempt to index a number value (global 'cfg')");
+}
+
+#[test]
+fn call_non_function_names_variable_test() {
+    assert_eq!(run_error("foo()"), "attempt to call a nil value (global 'foo')");
+    assert_eq!(run_error("t = {}\nt.x = 5\nt.x()"), "attempt to call a number value (field 'x')");
+    assert_eq!(run_error("print = 1\nprint(2)"), "attempt to call a number value (global 'print')");
+}
+
+#[test]
+fn setmetatable_errors_test() {
+    assert_eq!(run_error("setmetatable(5, {})"), "bad argument #1 to 'setmetatable' (table expected, got number)");
+    assert_eq!(run_error("setmetatable({}, 5)"), "bad argument #2 to 'setmetatable' (nil or table expected)");
+    assert_eq!(run_error("mt = {}\nmt.__metatable = false\nt = setmetatable({}, mt)\nsetmetatable(t, {})"), "cannot change a protected metatable");
+}
+
+#[test]
+fn metamethod_errors_test() {
+    assert_eq!(run_error("mt = {}\nt = setmetatable({}, mt)\nmt.__index = t\nx = t.missing"), "'__index' chain too long; possible loop");
+    assert_eq!(run_error("mt = {}\nmt.__index = 5\nt = setmetatable({}, mt)\nx = t.missing"), "attempt to index a number value");
+    assert_eq!(run_error("mt = {}\nfunction mt.__tostring(t)\nreturn 1\nend\nt = setmetatable({}, mt)\nprint(t)"), "'__tostring' must return a string");
+    assert_eq!(run_error("t = {}\nt()"), "attempt to call a table value (global 't')");
+    assert_eq!(run_error("x = #5"), "attempt to get length of a number value");
+    assert_eq!(run_error("x = 1 % 0"), "attempt to perform 'n%0'");
+}
+
+#[test]
+fn coroutine_errors_test() {
+    assert_eq!(run_error("coroutine.yield(1)"), "attempt to yield from outside a coroutine");
+    assert_eq!(run_error("coroutine.resume(5)"), "bad argument #1 to 'resume' (coroutine expected, got number)");
+    assert_eq!(run_error("coroutine.create()"), "bad argument #1 to 'create' (function expected, got no value)");
+    assert_eq!(run_error("function f()\nreturn 1\nend\nw = coroutine.wrap(f)\nw()\nw()"), "cannot resume dead coroutine");
+}
+
+#[test]
+fn unreachable_coroutines_end_test() {
+    let src = "function g(x)\n  coroutine.yield(x)\n  return x\nend\n\
+        kept = coroutine.create(g)\ncoroutine.resume(kept, 5)\n\
+        local i = 0\nwhile i < 3000 do\n  local co = coroutine.create(g)\n  coroutine.resume(co, i)\n  i = i + 1\nend\n\
+        ok, x = coroutine.resume(kept)";
+    let mut stmts = parser::parse(parser::scanner::scan(src.to_string()).unwrap()).unwrap();
+    let interpreter = interpreter::run(&mut stmts).unwrap();
+
+    // The coroutine in the global still works after the others were collected
+    assert_eq!(interpreter.get_variable("x".to_string()).unwrap(), Some(&data::LuaData::Int(5)));
+    // Only the coroutine in the global and the last one of the loop are left
+    assert!(interpreter.live_coroutines() <= 2);
+}
+
+#[test]
+fn unreachable_files_close_test() {
+    let src = "function first_line()\n  local f = io.open(\"Cargo.toml\")\n  return f:read(\"l\")\nend\n\
+        kept = io.open(\"Cargo.toml\")\n\
+        local i = 0\nwhile i < 3000 do\n  first_line()\n  i = i + 1\nend\n\
+        line = kept:read(\"l\")";
+    let mut stmts = parser::parse(parser::scanner::scan(src.to_string()).unwrap()).unwrap();
+    let interpreter = interpreter::run(&mut stmts).unwrap();
+
+    // The file in the global stays open while the ones only the calls referred to are closed
+    assert_eq!(interpreter.get_variable("line".to_string()).unwrap(), Some(&data::LuaData::Str(b"[package]".to_vec())));
+    // Only the standard files and the one in the global are left
+    assert_eq!(interpreter.live_userdata(), 4);
+}
+
+#[test]
+fn uncaught_error_values_test() {
+    assert_eq!(run_error("error(\"boom\")"), "main:1: boom");
+    assert_eq!(run_error("error(\"boom\", 0)"), "boom");
+    assert_eq!(run_error("error({})"), "(error object is a table value)");
+    assert_eq!(run_error("error(42)"), "42");
+    assert_eq!(run_error("pcall()"), "bad argument #1 to 'pcall' (value expected)");
+}
+
+#[test]
+fn local_attribute_errors_test() {
+    assert_eq!(run_error("local x <const> = 1\nx = 2"), "attempt to assign to const variable 'x'");
+    assert_eq!(run_error("local x <close> = nil\nif true then\nx = 2\nend"), "attempt to assign to const variable 'x'");
+    assert_eq!(run_error("local x <foo> = 1"), "unknown attribute 'foo'");
+    assert_eq!(run_error("local a <close>, b <close> = nil, nil"), "multiple to-be-closed variables in local list");
+    assert_eq!(run_error("local x <close> = {}"), "variable 'x' got a non-closable value");
+    assert_eq!(run_error("if true then\nbreak\nend"), "break outside a loop");
+    assert_eq!(run_error("while true do\nfunction f()\nbreak\nend\nend"), "break outside a loop");
+
+    // The parser checks the chunk too, not only 'parser::parse'
+    let tokens = parser::scanner::scan("local x <const> = 1\nx = 2\nbreak".to_string()).unwrap();
+    let errors = parser::Parser::new(tokens).parse().unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages, vec!["attempt to assign to const variable 'x'", "break outside a loop"]);
+}
+
+#[test]
+fn call_expression_errors_test() {
+    assert_eq!(run_error("function f()\nreturn nil\nend\nf()()"), "attempt to call a nil value");
+    assert_eq!(run_error("t = {}\nt.get().x = 1"), "attempt to call a nil value (field 'get')");
+    assert_eq!(run_error("t = {}\nx = t.a.b"), "attempt to index a nil value (field 'a')");
+    assert_eq!(run_error("t = {}\nx = t[\"a\"][1]"), "attempt to index a nil value (field 'a')");
+    assert_eq!(run_error("t = {}\nt:missing()"), "attempt to call a nil value (method 'missing')");
+    assert_eq!(run_error("x = 1\nx:method()"), "attempt to index a number value (global 'x')");
+    assert_eq!(run_error("t = {[nil] = 1}"), "index is nil");
+    assert_eq!(run_error("f() = 1"), "syntax error near '='");
+    assert_eq!(run_error("x + 1"), "syntax error: expected a function call or an assignment");
+}
+
+#[test]
+fn newline_parsing_test() {
+    assert_eq!(run_error("f = print\n(f)(1)"), "ambiguous syntax (function call x new statement)");
+    assert_eq!(run_error("x = 1 +\n\n{} + 2"), "attempt to perform arithmetic on a table value");
+    assert_eq!(run_error("local t = {\n1,\n2\n}\nx = t.a.b"), "attempt to index a nil value (field 'a')");
+}
+
+#[test]
+fn block_nesting_errors_test() {
+    assert_eq!(run_error("function f()\nfor i = 1, 2 do\nend\n"), "'end' expected (to close 'function' at line 1)");
+    assert_eq!(run_error("x = 1\nwhile true do\nif x then\nend"), "'end' expected (to close 'while' at line 2)");
+    assert_eq!(run_error("if x then\nelseif y then\nelse\n"), "'end' expected (to close 'if' at line 1)");
+    assert_eq!(run_error("do\nx = 1"), "'end' expected (to close 'do' at line 1)");
+    assert_eq!(run_error("for i = 1, 2 do\nend\nend"), "'<eof>' expected near 'end'");
+}
+
+#[test]
+fn token_spans_test() {
+    let tokens = parser::scanner::scan_chunk("local x = 10\n  print(\"hi\")".to_string(), "spans.lua").unwrap();
+    let spans: Vec<(usize, usize, usize, usize)> = tokens.iter()
+        .map(|info| (info.span.start_line, info.span.start_column, info.span.end_line, info.span.end_column))
+        .collect();
+
+    assert_eq!(spans, vec![(1, 1, 1, 5), (1, 7, 1, 7), (1, 9, 1, 9), (1, 11, 1, 12),
+        (2, 3, 2, 7), (2, 8, 2, 8), (2, 9, 2, 12), (2, 13, 2, 13), (2, 14, 2, 14)]);
+    assert_eq!(tokens[0].span.chunk, "spans.lua");
+}
+
+#[test]
+fn error_spans_test() {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run_chunk("x = 1\ny = 2 +\n  z".to_string(), "spans.lua").unwrap_err();
+    let span = errors[0].span().unwrap();
+
+    assert_eq!(errors[0].message, "attempt to perform arithmetic on a nil value (global 'z')");
+    assert_eq!((span.start_line, span.start_column, span.end_line, span.end_column), (2, 1, 3, 3));
+    assert_eq!(span.chunk, "spans.lua");
+    assert_eq!(errors[0].value().to_string(), "spans.lua:2: attempt to perform arithmetic on a nil value (global 'z')");
+
+    let errors = aurora.run("x = (1 +\n2".to_string()).unwrap_err();
+    let span = errors[0].span().unwrap();
+
+    assert_eq!((span.start_line, span.start_column), (2, 2));
+}
+
+fn render_error(src: &str) -> String {
+    let mut config = Config::new(LogLevel::Normal);
+    config.color = false;
+
+    let mut aurora = Aurora::new(config);
+    let errors = aurora.run_chunk(src.to_string(), "game.lua").unwrap_err();
+
+    aurora.render_error(&errors[0])
+}
+
+#[test]
+fn diagnostic_rendering_test() {
+    assert_eq!(render_error("x = 1\nprint(x .. y)"), "\
+error: attempt to concatenate a nil value (global 'y')
+ --> game.lua:2:1
+  |
+2 | print(x .. y)
+  | ^^^^^^^^^^^^^
+stack traceback:
+\tgame.lua:2: in main chunk
+");
+    assert_eq!(render_error("while true do\n  x = 1\n\n\n\n\n\n\n\n"), "\
+syntax error: 'end' expected (to close 'while' at line 1)
+  --> game.lua:10:1
+   |
+ 1 | while true do
+   | ----- this 'while' needs an 'end'
+...
+10 |
+   | ^
+");
+    assert_eq!(render_error("local f = g\n(f)()"), "\
+syntax error: ambiguous syntax (function call x new statement)
+ --> game.lua:2:1
+  |
+2 | (f)()
+  | ^
+  = note: add a ';' before the '(' if it starts a new statement
+");
+    assert_eq!(render_error("error({})"), "error: (error object is a table value)\nstack traceback:\n\t[C]: in function 'error'\n\tgame.lua:1: in main chunk\n");
+    // Tabs are only expanded in the snippet
+    assert_eq!(render_error("if true then\n\tprint(x .. y)\nend"), "\
+error: attempt to concatenate a nil value (global 'x')
+ --> game.lua:2:2
+  |
+2 |     print(x .. y)
+  |     ^^^^^^^^^^^^^
+stack traceback:
+\tgame.lua:2: in main chunk
+");
+}
+
+fn run_errors(src: &str) -> Vec<(usize, String)> {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run(src.to_string()).unwrap_err();
+
+    errors.iter().map(|e| (e.span().unwrap().start_line, e.message.clone())).collect()
+}
+
+#[test]
+fn parser_error_recovery_test() {
+    let src = "x = = 1\nprint(\"ok\")\nif a b() end\nlocal q <foo> = 1\nfor 1 = 1, 2 do\n  do f( end\nend\nlocal c <const> = 1\nc = 2\nend\nt = {1, 2";
+    let lines: Vec<usize> = run_errors(src).iter().map(|(line, _)| *line).collect();
+
+    assert_eq!(lines, vec![1, 3, 4, 5, 9, 10, 11]);
+    assert_eq!(run_errors("while x = 1 do\n  y = 2\nend\nz(")[1], (4, "Illegal Token: Some(EOF) isn't a value".to_string()));
+    assert_eq!(run_errors("x = 1 +\ny = 2")[0], (2, "Stmt's cannot start with Equal".to_string()));
+    assert_eq!(run_errors("x = = 1\ny = = 2\nf(\ng()\nh(").len(), 3);
+    assert_eq!(run_errors("print(pcall(function() return 1 end))\nprint(1)"), vec![(1, "Illegal Token: Some(Keyword(Function)) isn't a value".to_string())]);
+    assert_eq!(run_errors("f(1, (2 +), 3)\ng()").len(), 1);
+    assert_eq!(run_errors("function f()\n  if x then\n    y = = 1\n  end\nend\nf()").len(), 1);
+    // A statement keyword ends the brackets a header or an argument list left open
+    let lines: Vec<usize> = run_errors("function f(\n  return 1\nend\nx = = 1").iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![2, 4]);
+    let lines: Vec<usize> = run_errors("print(1\nlocal a = 1\nx = = 1\nif y then\n  z = = 2\nend").iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![2, 3, 5]);
+}
+
+fn run_error_kind(src: &str) -> aurora::error::ErrorKind {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+
+    aurora.run(src.to_string()).unwrap_err()[0].kind()
+}
+
+#[test]
+fn error_kinds_test() {
+    use aurora::error::{ErrorKind, SyntaxErrorKind};
+
+    assert_eq!(run_error_kind("x = y + 1"), ErrorKind::UndefinedVariable);
+    assert_eq!(run_error_kind("missing()"), ErrorKind::UndefinedVariable);
+    assert_eq!(run_error_kind("x = missing.field"), ErrorKind::UndefinedVariable);
+    assert_eq!(run_error_kind("t = {}\nx = t.size + 1"), ErrorKind::TypeMismatch);
+    assert_eq!(run_error_kind("x = 1 < \"2\""), ErrorKind::TypeMismatch);
+    assert_eq!(run_error_kind("setmetatable(1, {})"), ErrorKind::TypeMismatch);
+    assert_eq!(run_error_kind("pcall()"), ErrorKind::ArityMismatch);
+    assert_eq!(run_error_kind("require(\"does/not/exist\")"), ErrorKind::IoError);
+    assert_eq!(run_error_kind("error({})"), ErrorKind::Thrown);
+    assert_eq!(run_error_kind("x = = 1"), ErrorKind::SyntaxError(SyntaxErrorKind::UnexpectedToken));
+    assert_eq!(run_error_kind("while true do"), ErrorKind::SyntaxError(SyntaxErrorKind::UnclosedBlock));
+    assert_eq!(run_error_kind("break"), ErrorKind::SyntaxError(SyntaxErrorKind::InvalidStatement));
+    assert_eq!(run_error_kind("x = 1 @ 2"), ErrorKind::SyntaxError(SyntaxErrorKind::InvalidToken));
+}
+
+#[test]
+fn error_accessors_test() {
+    use std::error::Error;
+
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run_chunk("require(\"does/not/exist\")".to_string(), "game.lua").unwrap_err();
+
+    assert_eq!(errors[0].chunk(), Some("does/not/exist"));
+    assert!(errors[0].lua_value().is_none());
+    assert!(errors[0].source().unwrap().downcast_ref::<std::io::Error>().is_some());
+
+    let errors = aurora.run_chunk("error(42)".to_string(), "game.lua").unwrap_err();
+
+    assert_eq!(errors[0].lua_value(), Some(&aurora::data::LuaData::Int(42)));
+    assert!(errors[0].source().is_none());
+
+    let errors = aurora.run_chunk("x = y.z".to_string(), "game.lua").unwrap_err();
+
+    assert_eq!(errors[0].chunk(), Some("game.lua"));
+    assert!(!errors[0].is_syntax_error());
+}
+
+#[test]
+fn traceback_test() {
+    let src = "function inner()\n  return nil_value.x\nend\nfunction outer()\n  inner()\nend\nouter()";
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run(src.to_string()).unwrap_err();
+
+    assert_eq!(errors[0].traceback(), Some("stack traceback:\n\tmain:2: in function 'inner'\n\tmain:5: in function 'outer'\n\tmain:7: in main chunk"));
+    assert_eq!(errors[0].message, "attempt to index a nil value (global 'nil_value')");
+
+    let src = "function failing()\n  error(\"deep\")\nend\nlocal ok, trace = xpcall(failing, debug.traceback)\nerror(trace, 0)";
+    assert_eq!(run_error(src), "main:2: deep\nstack traceback:\n\t[C]: in function 'error'\n\tmain:2: in function 'failing'\n\t[C]: in function 'xpcall'\n\tmain:4: in main chunk");
+    assert_eq!(run_error("function f()\n  error(debug.traceback(\"here\", 2), 0)\nend\nf()"), "here\nstack traceback:\n\tmain:4: in main chunk");
+
+    let errors = Aurora::new(Config::new(LogLevel::Normal)).run("x = = 1".to_string()).unwrap_err();
+    assert_eq!(errors[0].traceback(), None);
+}
+
+#[test]
+fn interpreter_traceback_test() {
+    let mut interpreter = Interpreter::new();
+    interpreter.load_library(library::new_std());
+    interpreter.register_func("where", function::FunctionDef::Rust(|_, interpreter| {
+        let frames = interpreter.call_stack();
+
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].is_rust);
+        assert_eq!((frames[1].name.as_deref(), frames[1].line), (Some("f"), 2));
+        assert_eq!((frames[2].name.as_deref(), frames[2].chunk.as_str()), (None, "main"));
+
+        Ok(vec![data::LuaData::Str(interpreter.traceback().into_bytes())])
+    }));
+
+    let tokens = parser::scanner::scan("function f()\n  where()\nend\nf()".to_string()).unwrap();
+    let mut stmts = parser::parse(tokens).unwrap();
+    interpreter.run_block(&mut stmts).unwrap();
+
+    assert_eq!(interpreter.traceback(), "stack traceback:\n\tmain:4: in main chunk");
+}
+
+fn run_seeded(seed: i64, src: &str) -> String {
+    let mut config = Config::new(LogLevel::Normal);
+    config.random_seed = Some(seed);
+
+    let errors = Aurora::new(config).run(src.to_string()).unwrap_err();
+    errors[0].message.clone()
+}
+
+#[test]
+fn random_seed_test() {
+    let src = "error(math.random(1000000) .. \" \" .. math.random(0) .. \" \" .. math.random(), 0)";
+
+    assert_eq!(run_seeded(7, src), run_seeded(7, src));
+    assert_ne!(run_seeded(7, src), run_seeded(8, src));
+
+    // Seeding from the host is the same as calling math.randomseed
+    assert_eq!(run_seeded(7, src), run_seeded(1, &format!("math.randomseed(7)\n{}", src)));
+
+    let mut interpreter = Interpreter::new();
+    interpreter.seed_random(7, 0);
+    let first = interpreter.random().next_integer();
+    interpreter.seed_random(7, 0);
+    assert_eq!(interpreter.random().next_integer(), first);
+}
+
+/// Serves one read-only file from memory and denies everything else
+struct SandboxFileSystem;
+
+impl fs::FileSystem for SandboxFileSystem {
+    fn open(&mut self, path: &str, options: &fs::OpenOptions) -> std::io::Result<Box<dyn fs::FileHandle>> {
+        match path {
+            "data.txt" if !options.write => Ok(Box::new(std::io::Cursor::new(b"first\nsecond\n42 0x10 -1.5e1\n".to_vec()))),
+            "chunk.lua" if !options.write => Ok(Box::new(std::io::Cursor::new(b"local x = 6\nreturn x * 7\n".to_vec()))),
+            _ => Err(denied()),
+        }
+    }
+
+    fn remove(&mut self, _: &str) -> std::io::Result<()> {
+        Err(denied())
+    }
+
+    fn rename(&mut self, _: &str, _: &str) -> std::io::Result<()> {
+        Err(denied())
+    }
+}
+
+fn denied() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::PermissionDenied, "sandboxed")
+}
+
+fn run_sandboxed(src: &str) -> String {
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    aurora.set_file_system(Box::new(SandboxFileSystem));
+
+    let errors = aurora.run(src.to_string()).unwrap_err();
+    errors[0].message.clone()
+}
+
+#[test]
+fn sandboxed_file_system_test() {
+    assert_eq!(run_sandboxed("local f, message, errno = io.open(\"secret.txt\", \"w\")\nif f == nil then error(message .. \" \" .. errno, 0) end"),
+        "secret.txt: sandboxed 13");
+    assert_eq!(run_sandboxed("local f = io.open(\"data.txt\")\nerror(f:read(\"l\") .. \"|\" .. f:read(\"L\") .. \"|\" .. f:read(\"n\", \"n\", \"n\"), 0)"),
+        "first|second\n|42");
+    assert_eq!(run_sandboxed("local f = io.open(\"data.txt\")\nf:read(\"l\", \"l\")\nlocal a, b, c = f:read(\"n\", \"n\", \"n\")\nerror(a + b + c .. \"\", 0)"), "43.0");
+    assert_eq!(run_sandboxed("io.lines(\"other.txt\")"), "cannot open file 'other.txt' (sandboxed)");
+    assert_eq!(run_sandboxed("require(\"lib/core\")"), "Failed to load file lib/core.lua: sandboxed");
+}
+
+#[test]
+fn load_test() {
+    assert_eq!(run_sandboxed("error(dofile(\"chunk.lua\") .. \"\", 0)"), "42");
+    assert_eq!(run_sandboxed("local f = loadfile(\"chunk.lua\")\nerror(f() .. \"\", 0)"), "42");
+    assert_eq!(run_sandboxed("local f, message = loadfile(\"secret.lua\")\nerror(message, 0)"), "cannot open secret.lua: sandboxed");
+    assert_eq!(run_sandboxed("dofile(\"secret.lua\")"), "cannot open secret.lua: sandboxed");
+    assert_eq!(run_sandboxed("local f, message = load(\"x = = 1\", \"=config\")\nerror(message, 0)"), "config:1: Illegal Token: Some(Equal) isn't a value");
+    // Positions in errors start with the chunk name given to load
+    assert_eq!(run_sandboxed("local f, message = load(\"x = = 1\")\nerror(message, 0)"), "[string \"x = = 1\"]:1: Illegal Token: Some(Equal) isn't a value");
+    assert_eq!(run_sandboxed("reason = \"plugin failed\"\nlocal f = load(\"local x = 1\" .. string.char(10) .. \"error(reason)\", \"@plugin.lua\")\nlocal ok, message = pcall(f)\nerror(message, 0)"), "plugin.lua:2: plugin failed");
+    assert_eq!(run_sandboxed("local ok, message = pcall(load(\"return 1 + {}\", \"=snippet\"))\nerror(message, 0)"), "snippet:1: attempt to perform arithmetic on a table value");
+    assert_eq!(run_sandboxed("local env = {}\nload(\"x = 1\", \"=config\", \"t\", env)()\nerror(env.x .. \" \" .. type(x), 0)"), "1 nil");
+}
+
+fn run_with_host(src: &str) -> (String, Option<i32>) {
+    let mut clock = clock::FixedClock::new(1700000000);
+    clock.cpu_time = 1.5;
+    clock.utc_offset = 2 * 3600;
+
+    let mut environment = std::collections::HashMap::new();
+    environment.insert("LOG_LEVEL".to_string(), "debug".to_string());
+
+    let mut config = Config::new(LogLevel::Normal);
+    config.clock = Some(Box::new(clock));
+    config.environment = Some(Box::new(environment));
+
+    let mut aurora = Aurora::new(config);
+    let errors = aurora.run(src.to_string()).unwrap_err();
+
+    (errors[0].message.clone(), aurora.exit_code())
+}
+
+#[test]
+fn host_clock_and_environment_test() {
+    assert_eq!(run_with_host("error(os.time() .. \" \" .. os.clock(), 0)").0, "1700000000 1.5");
+    assert_eq!(run_with_host("error(os.date(\"%Y-%m-%d %H:%M:%S %z\"), 0)").0, "2023-11-15 00:13:20 +0200");
+    assert_eq!(run_with_host("error(os.date(\"!%H:%M %Z\"), 0)").0, "22:13 UTC");
+    assert_eq!(run_with_host("error(os.time({year = 2023, month = 11, day = 15, hour = 0, min = 13, sec = 20}) .. \"\", 0)").0, "1700000000");
+    assert_eq!(run_with_host("error(os.getenv(\"LOG_LEVEL\"), 0)").0, "debug");
+    assert_eq!(run_with_host("if os.getenv(\"HOME\") == nil then error(\"unset\", 0) end").0, "unset");
+}
+
+#[test]
+fn exit_test() {
+    let (message, code) = run_with_host("os.exit(3)");
+    assert_eq!((message.as_str(), code), ("exited with code 3", Some(3)));
+
+    // Protected calls and coroutines don't stop the exit
+    assert_eq!(run_with_host("pcall(os.exit, false)\nerror(\"not reached\")").1, Some(1));
+    assert_eq!(run_with_host("xpcall(os.exit, debug.traceback)\nerror(\"not reached\")").1, Some(0));
+    assert_eq!(run_with_host("local co = coroutine.create(os.exit)\ncoroutine.resume(co, 5)\nerror(\"not reached\")").1, Some(5));
+
+    let mut aurora = Aurora::new(Config::new(LogLevel::Normal));
+    let errors = aurora.run("os.exit(true)".to_string()).unwrap_err();
+    assert_eq!(errors[0].kind(), aurora::error::ErrorKind::Exit(0));
+    assert_eq!(run_with_host("error(\"failed\", 0)").1, None);
+}
@@ -10,7 +10,7 @@ use std::path::PathBuf;
 
 use aurora::data::LuaData;
 use aurora::interpreter::Interpreter;
-use aurora::Token;
+use aurora::TokenInfo;
 use aurora::Stmt;
 use aurora::parser::Parser;
 use aurora::parser::scanner::Scanner;
@@ -77,7 +77,7 @@ fn check_variables(vars: TestVars, interpreter: Interpreter){
     }
 }
 
-fn create_scanner(src: String) -> (Scanner, Vec<Token>){
+fn create_scanner(src: String) -> (Scanner, Vec<TokenInfo>){
     let mut scanner = Scanner::new(src);    
     let tokens = match scanner.scan(){
         Ok(x) => x,
@@ -93,13 +93,13 @@ fn create_scanner(src: String) -> (Scanner, Vec<Token>){
     return (scanner, tokens);
 }
 
-fn create_parser(tokens: Vec<Token>) -> (Parser, Vec<Stmt>){
+fn create_parser(tokens: Vec<TokenInfo>) -> (Parser, Vec<Stmt>){
     let mut parser = Parser::new(tokens);
 
     let stmts = match parser.parse(){
         Ok(x) => x,
         Err(e) => {
-            panic!("{}", e);
+            panic!("{}", e[0]);
         }
     };
 